@@ -0,0 +1,10 @@
+/// Enables the `hardware` cfg when building the hardware backends, which requires the `hardware` feature and a
+/// Raspberry Pi target, unless the `simulation` feature is selected.
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(hardware)");
+    let arch = std::env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let is_raspberry_pi = arch == "arm" || arch == "aarch64";
+    if std::env::var_os("CARGO_FEATURE_HARDWARE").is_some() && std::env::var_os("CARGO_FEATURE_SIMULATION").is_none() && is_raspberry_pi {
+        println!("cargo::rustc-cfg=hardware");
+    }
+}
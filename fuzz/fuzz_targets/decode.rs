@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cat_reminder::Protocol;
+
+// Any packet either fails to decode or decodes to a message that survives encoding it again in the same encoding.
+fuzz_target!(|data: &[u8]| {
+    if let Ok((message, encoding)) = Protocol::decode_with_encoding(data) {
+        let frame = message.encode_as(encoding).expect("Could not encode decoded message");
+        assert_eq!(Protocol::decode(&frame).expect("Could not decode encoded message"), message);
+    }
+});
@@ -0,0 +1,108 @@
+//! Blink patterns of the displays: each state is shown solid, blinking slow or fast, or as a chase running along the
+//! strip. The reminder loop advances the pattern by one frame on every render, waiting the cadence of the pattern in
+//! between.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::reminder::LEDStripState;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BlinkPattern {
+    Solid,
+    SlowBlink,
+    FastBlink,
+    /// Every few LEDs lit, moving along the strip, displays without LEDs blinking instead
+    Chase
+}
+
+impl BlinkPattern {
+    fn default_frame_ms(&self) -> u64 {
+        match self {
+            BlinkPattern::Solid => 1000,
+            BlinkPattern::SlowBlink => 1000,
+            BlinkPattern::FastBlink => 500,
+            BlinkPattern::Chase => 150
+        }
+    }
+}
+
+/// The pattern of a state, e.g. `Orange = { pattern = "slow_blink" }`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct PatternConfig {
+    pub pattern: BlinkPattern,
+    /// Time each frame of a blinking or chasing pattern is shown, e.g. how long a blinking display stays on, the
+    /// default of the pattern if not set
+    pub frame_ms: Option<u64>
+}
+
+impl PatternConfig {
+    fn frame_delay(&self) -> Duration {
+        match self.pattern {
+            // nothing changes from one frame to the next
+            BlinkPattern::Solid => Duration::from_millis(BlinkPattern::Solid.default_frame_ms()),
+            pattern => Duration::from_millis(self.frame_ms.unwrap_or_else(|| pattern.default_frame_ms()))
+        }
+    }
+}
+
+/// The pattern shown while a reset waits for its confirmation.
+const ARMED_PATTERN: PatternConfig = PatternConfig { pattern: BlinkPattern::FastBlink, frame_ms: None };
+
+/// What the displays show in the current frame of the pattern.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Frame {
+    /// Whether displays that blink are lit, always set for solid patterns
+    pub on: bool,
+    /// Offset of the chase along the strip, for chasing patterns
+    pub chase: Option<usize>
+}
+
+/// Plays the pattern of the current state, starting over whenever the pattern changes.
+#[derive(Default)]
+pub struct Animation {
+    patterns: BTreeMap<LEDStripState, PatternConfig>,
+    current: Option<BlinkPattern>,
+    frame: usize
+}
+
+impl Animation {
+    pub fn new(patterns: BTreeMap<LEDStripState, PatternConfig>) -> Self {
+        Animation { patterns, current: None, frame: 0 }
+    }
+
+    /// The configured pattern of the state, blinking red blinking fast and the others being solid by default.
+    pub fn pattern(&self, state: LEDStripState, is_reset_armed: bool) -> PatternConfig {
+        if is_reset_armed {
+            return ARMED_PATTERN;
+        }
+        self.patterns.get(&state).copied().unwrap_or(match state {
+            LEDStripState::BlinkingRed => PatternConfig { pattern: BlinkPattern::FastBlink, frame_ms: None },
+            _ => PatternConfig { pattern: BlinkPattern::Solid, frame_ms: None }
+        })
+    }
+
+    /// Advances the pattern of the state to its next frame.
+    pub fn advance(&mut self, state: LEDStripState, is_reset_armed: bool) -> Frame {
+        let pattern = self.pattern(state, is_reset_armed).pattern;
+        if self.current == Some(pattern) {
+            self.frame = self.frame.wrapping_add(1);
+        } else {
+            self.current = Some(pattern);
+            self.frame = 0;
+        }
+        match pattern {
+            BlinkPattern::Solid => Frame { on: true, chase: None },
+            BlinkPattern::SlowBlink | BlinkPattern::FastBlink => Frame { on: self.frame % 2 == 0, chase: None },
+            BlinkPattern::Chase => Frame { on: self.frame % 2 == 0, chase: Some(self.frame) }
+        }
+    }
+
+    /// Time until the next frame of the pattern of the state.
+    pub fn frame_delay(&self, state: LEDStripState, is_reset_armed: bool) -> Duration {
+        self.pattern(state, is_reset_armed).frame_delay()
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::mpsc::Sender;
+
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::notifier::format_duration;
+use crate::reminder::{ReminderEvent, ReminderStatus, ResetSource};
+
+/// Intent resetting the state, e.g. "Alexa, tell cat reminder I cleaned the litter box"
+pub const CLEANED_INTENT: &str = "LitterCleanedIntent";
+/// Intent reading back the elapsed time, e.g. "Alexa, ask cat reminder how long since the litter was cleaned"
+pub const ELAPSED_INTENT: &str = "LitterElapsedIntent";
+
+#[derive(Deserialize)]
+struct AlexaRequest {
+    request: AlexaRequestBody
+}
+
+#[derive(Deserialize)]
+struct AlexaRequestBody {
+    #[serde(rename = "type")]
+    request_type: String,
+    intent: Option<AlexaIntent>
+}
+
+#[derive(Deserialize)]
+struct AlexaIntent {
+    name: String
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DialogflowRequest {
+    query_result: DialogflowQueryResult
+}
+
+#[derive(Deserialize)]
+struct DialogflowQueryResult {
+    intent: DialogflowIntent
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DialogflowIntent {
+    display_name: String
+}
+
+/// Handles an Alexa custom skill request, returning the response document.
+pub fn alexa(body: &str, status: &ReminderStatus, reminder_tx: &Sender<ReminderEvent>) -> Result<Value, serde_json::Error> {
+    let request: AlexaRequest = serde_json::from_str(body)?;
+    let text = match (request.request.request_type.as_str(), request.request.intent) {
+        ("IntentRequest", Some(intent)) => fulfill(&intent.name, status, reminder_tx, "Alexa"),
+        _ => "You can tell me that you cleaned the litter box, or ask how long it has been since it was cleaned.".to_string()
+    };
+    Ok(json!({
+        "version": "1.0",
+        "response": {
+            "outputSpeech": { "type": "PlainText", "text": text },
+            "shouldEndSession": true
+        }
+    }))
+}
+
+/// Handles a Dialogflow fulfillment request (as used by Google Assistant), returning the response document.
+pub fn dialogflow(body: &str, status: &ReminderStatus, reminder_tx: &Sender<ReminderEvent>) -> Result<Value, serde_json::Error> {
+    let request: DialogflowRequest = serde_json::from_str(body)?;
+    let text = fulfill(&request.query_result.intent.display_name, status, reminder_tx, "Google Assistant");
+    Ok(json!({ "fulfillmentText": text }))
+}
+
+fn fulfill(intent: &str, status: &ReminderStatus, reminder_tx: &Sender<ReminderEvent>, source: &str) -> String {
+    match intent {
+        CLEANED_INTENT => match reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Assistant(source.to_string()), None)) {
+            Ok(()) => "Thanks, the litter box has been marked as clean.".to_string(),
+            Err(_) => "Sorry, the reminder is not running.".to_string()
+        },
+        ELAPSED_INTENT => {
+            let elapsed = Utc::now().signed_duration_since(status.last_cleaning_time);
+            format!("The litter box was cleaned {} hours and {} minutes ago.", elapsed.num_hours(), elapsed.num_minutes() % 60)
+        }
+        _ => format!("Sorry, I don't know how to handle {}. The litter box was cleaned {} ago.",
+                     intent, format_duration(&Utc::now().signed_duration_since(status.last_cleaning_time)))
+    }
+}
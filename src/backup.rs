@@ -0,0 +1,83 @@
+use std::fs::{self, File};
+use std::io::{self, Error, Write};
+use std::io::ErrorKind::InvalidData;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, CONFIG_FILE_PATH};
+use crate::control;
+use crate::history;
+use crate::state::{self, STATE_FILE_PATH};
+
+const BACKUP_VERSION: u32 = 1;
+
+/// The state, history and configuration bundled in a single JSON document.
+/// The files are kept verbatim, the history as JSON lines whatever its store, and missing ones are left out.
+#[derive(Serialize, Deserialize)]
+struct Backup {
+    version: u32,
+    created: DateTime<Utc>,
+    state: Option<String>,
+    history: Option<String>,
+    config: Option<String>
+}
+
+/// Writes the state, history and configuration to the backup file.
+pub fn backup(file: &Path) -> io::Result<()> {
+    let entries = history::load()?;
+    let backup = Backup {
+        version: BACKUP_VERSION,
+        created: Utc::now(),
+        state: read_if_exists(&state_file())?,
+        history: if entries.is_empty() { None } else { Some(history::to_lines(&entries)?) },
+        config: read_if_exists(Path::new(CONFIG_FILE_PATH))?
+    };
+    let mut writer = File::create(file)?;
+    serde_json::to_writer_pretty(&mut writer, &backup)?;
+    writeln!(writer)?;
+    writer.sync_all()
+}
+
+/// Restores the files contained in the backup file, replacing the current ones, and returns when the backup was made.
+/// The configuration is restored first, so that the state and the history end up where the restored configuration
+/// expects them.
+///
+/// # Errors
+///
+/// This function will return an error if the reminder is running, since it would overwrite the restored state with
+/// the one it holds, or if the backup cannot be read or restored.
+pub fn restore(file: &Path) -> io::Result<DateTime<Utc>> {
+    if UnixStream::connect(control::socket_path(&config::load())).is_ok() {
+        return Err(Error::other("The reminder is running, stop it before restoring"));
+    }
+    let backup: Backup = serde_json::from_str(&fs::read_to_string(file)?)?;
+    if backup.version > BACKUP_VERSION {
+        return Err(Error::new(InvalidData, format!("Unsupported backup version {}", backup.version)));
+    }
+    if let Some(config) = &backup.config {
+        state::write_atomically(Path::new(CONFIG_FILE_PATH), config.as_bytes())?;
+    }
+    if let Some(history) = &backup.history {
+        history::replace_all(&history::path_for(&state_file()), &history::parse_lines(history))?;
+    }
+    if let Some(state) = &backup.state {
+        state::write_atomically(&state_file(), state.as_bytes())?;
+    }
+    Ok(backup.created)
+}
+
+/// The state file of the configuration file as it is now, which the history is kept next to.
+fn state_file() -> PathBuf {
+    config::load().state_file.unwrap_or_else(|| PathBuf::from(STATE_FILE_PATH))
+}
+
+fn read_if_exists(path: &Path) -> io::Result<Option<String>> {
+    if path.exists() {
+        fs::read_to_string(path).map(Some)
+    } else {
+        Ok(None)
+    }
+}
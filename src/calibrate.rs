@@ -0,0 +1,40 @@
+//! The `calibrate` command, which steps through test colors on the strip, so that the calibration in the configuration
+//! can be adjusted until the strip shows what is intended. The reminder must not be running, as it holds the strip.
+
+use std::io::{self, Write};
+
+use crate::config;
+use crate::led::{LedController, RPILedController};
+use crate::palette;
+use crate::reminder::LEDStripState;
+
+/// Shows the primary colors, white and the colors of the states, one after the other, each until Enter is pressed.
+pub fn run() {
+    let config = config::load();
+    let calibration = config.displays.calibration;
+    let mut strip = RPILedController::with_led_count(config.displays.led_count).with_calibration(calibration)
+        .with_layout(&config.displays.layout);
+    let state_colors = palette::state_colors(config.displays.palette, &config.displays.colors);
+    let mut colors = vec![
+        ("red".to_string(), [255, 0, 0]),
+        ("green".to_string(), [0, 255, 0]),
+        ("blue".to_string(), [0, 0, 255]),
+        ("white".to_string(), [255, 255, 255])
+    ];
+    colors.extend(LEDStripState::ALL.iter().zip(state_colors).map(|(state, color)| (format!("{:?}", state), color)));
+
+    println!("Calibration: gamma {}, scale {:?}", calibration.gamma, calibration.scale);
+    for (name, [red, green, blue]) in colors {
+        if let Err(e) = strip.set_all_to([blue, green, red, 0]) {
+            eprintln!("Could not show {}: {}", name, e);
+            return;
+        }
+        print!("Showing {} ({}, {}, {}), press Enter for the next color ", name, red, green, blue);
+        let _ = io::stdout().flush();
+        if io::stdin().read_line(&mut String::new()).is_err() {
+            break;
+        }
+    }
+    let _ = strip.set_all_to(RPILedController::BLACK);
+    println!("Adjust the gamma and the scale of the channels under [displays.calibration] until the colors look right");
+}
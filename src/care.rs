@@ -0,0 +1,90 @@
+//! Care reminders recurring every few months, such as the flea treatment or the vet checkup. They do not occupy the
+//! displays: while one is due, the displays briefly pulse in its color once a day.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{DateTime, Months, NaiveDate, NaiveTime, Utc};
+use serde::Deserialize;
+
+use crate::config::schedule;
+
+#[derive(Deserialize, Clone)]
+pub struct CareConfig {
+    /// What is due, e.g. `flea treatment`
+    pub name: String,
+    /// Months after which it is due again, e.g. 1 for monthly and 3 for quarterly
+    pub months: u32,
+    /// Color of the daily pulse, in RGB
+    #[serde(default = "default_color")]
+    pub color: [u8; 3],
+    /// Local time from which the daily pulse is shown
+    #[serde(default = "default_pulse_time")]
+    pub pulse_time: NaiveTime
+}
+
+fn default_color() -> [u8; 3] {
+    [255, 0, 255]
+}
+
+fn default_pulse_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(18, 0, 0).unwrap()
+}
+
+/// The outcome of a check of the care reminders.
+#[derive(Default)]
+pub struct CareCheck {
+    /// Names of the reminders that are due
+    pub due: Vec<String>,
+    /// Names of the reminders that became due since the previous check
+    pub newly_due: Vec<String>,
+    /// Colors to pulse, once a day for each reminder that is due
+    pub pulses: Vec<[u8; 3]>
+}
+
+/// The configured care reminders, with what was already notified and pulsed.
+#[derive(Default)]
+pub struct CareReminders {
+    configs: Vec<CareConfig>,
+    notified: BTreeSet<String>,
+    /// Local date of the last pulse of each reminder
+    pulsed_on: HashMap<String, NaiveDate>
+}
+
+impl CareReminders {
+    pub fn new(configs: Vec<CareConfig>) -> Self {
+        CareReminders { configs, notified: BTreeSet::new(), pulsed_on: HashMap::new() }
+    }
+
+    pub fn configs(&self) -> &[CareConfig] {
+        &self.configs
+    }
+
+    /// Checks which reminders are due, given when each of them was last done. Pulses are only handed out if they can
+    /// be shown, e.g. while the displays are not dark, so that they are not missed.
+    pub fn check(&mut self, last_done: &BTreeMap<String, DateTime<Utc>>, now: DateTime<Utc>, can_pulse: bool) -> CareCheck {
+        let local_now = now.with_timezone(&schedule().timezone);
+        let mut check = CareCheck::default();
+        for config in &self.configs {
+            let is_due = last_done.get(&config.name).is_some_and(|done| now >= due_time(config, *done));
+            if !is_due {
+                self.notified.remove(&config.name);
+                continue;
+            }
+            check.due.push(config.name.clone());
+            if self.notified.insert(config.name.clone()) {
+                check.newly_due.push(config.name.clone());
+            }
+            let pulsed_today = self.pulsed_on.get(&config.name) == Some(&local_now.date_naive());
+            if can_pulse && !pulsed_today && local_now.time() >= config.pulse_time {
+                self.pulsed_on.insert(config.name.clone(), local_now.date_naive());
+                check.pulses.push(config.color);
+            }
+        }
+        check
+    }
+}
+
+/// The time at which the reminder is due, given when it was last done.
+pub fn due_time(config: &CareConfig, last_done: DateTime<Utc>) -> DateTime<Utc> {
+    last_done.checked_add_months(Months::new(config.months)).unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
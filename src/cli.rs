@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::litter::LitterProfile;
+
+#[derive(Parser)]
+#[command(version, about = "The Cat Litter Reminder")]
+pub struct Cli {
+    /// Cycles through all states on a fast schedule, e.g. to check the wiring, without touching the state or the network
+    #[arg(long)]
+    pub demo: bool,
+    /// Runs the clock of the reminder N times faster, e.g. 1440 to watch a day go by in a minute, without touching the
+    /// state or the network
+    #[arg(long, value_name = "N", value_parser = time_scale)]
+    pub time_scale: Option<f64>,
+    #[command(subcommand)]
+    pub command: Option<Command>
+}
+
+/// Parses a time scale, which only makes sense as a positive number.
+fn time_scale(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(scale) if scale.is_finite() && scale > 0.0 => Ok(scale),
+        Ok(_) => Err("the time scale must be a positive number".to_string()),
+        Err(e) => Err(e.to_string())
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Runs the reminder (the default when no command is given)
+    Run,
+    /// Exports the cleaning history
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Only export entries from this date on (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// File to write to instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>
+    },
+    /// Feeds a recorded history through the reminder with a simulated clock, printing the states it goes through and
+    /// where they differ from the recorded ones
+    Replay {
+        /// History file to replay, e.g. one attached to a bug report, the one of the node if not given
+        file: Option<PathBuf>,
+        /// Only replay from this local date on (YYYY-MM-DD), starting with the last cleaning before it
+        #[arg(long)]
+        since: Option<NaiveDate>
+    },
+    /// Checks the hardware, the state file, the clock and the peers, exiting with a non-zero code on failure
+    Health,
+    /// Walks every configured peripheral, asking to press the inputs and to confirm what the outputs show, and
+    /// reports which ones work. Exits with a non-zero code if one of them fails.
+    Selftest,
+    /// Steps through test colors on the strip, to adjust the calibration of its colors
+    Calibrate,
+    /// Bundles the state, history and configuration into a single file
+    Backup {
+        file: PathBuf
+    },
+    /// Restores the state, history and configuration from a backup, replacing the current ones
+    Restore {
+        file: PathBuf
+    },
+    /// Prints the status of the running reminder
+    Status,
+    /// Resets the timer of the running reminder
+    Reset {
+        /// Who cleaned the litter box
+        #[arg(long)]
+        by: Option<String>
+    },
+    /// Snoozes the running reminder, zero minutes ending a running snooze
+    Snooze {
+        minutes: i64,
+        /// Who snoozes the reminder
+        #[arg(long)]
+        by: Option<String>
+    },
+    /// Pauses the running reminder until it is resumed
+    Pause {
+        /// Who pauses the reminder
+        #[arg(long)]
+        by: Option<String>
+    },
+    /// Resumes the paused reminder
+    Resume {
+        /// Who resumes the reminder
+        #[arg(long)]
+        by: Option<String>
+    },
+    /// Sets the time of the last cleaning, e.g. `2024-01-31T08:00:00Z`
+    SetTime {
+        time: DateTime<Utc>,
+        /// Who cleaned the litter box
+        #[arg(long)]
+        by: Option<String>
+    },
+    /// Prints the QR code with which phones pair with the HTTP API
+    Pair,
+    /// Prints the points and streaks of the household members
+    Leaderboard,
+    /// Prints the last lines of the log files
+    Logs {
+        #[arg(long, short = 'n', default_value_t = 50)]
+        lines: usize,
+        /// Keeps printing the lines written afterwards
+        #[arg(long, short)]
+        follow: bool
+    },
+    /// Spares visitors the blinking and the alarms of the running reminder for a few hours, while the time keeps being
+    /// tracked. Zero hours end the guest mode.
+    Guest {
+        #[arg(default_value_t = 4)]
+        hours: i64
+    },
+    /// Records that the litter was replaced entirely, which also resets the timer of the running reminder
+    LitterReplaced {
+        /// Who replaced the litter
+        #[arg(long)]
+        by: Option<String>
+    },
+    /// Changes the litter used in the litter box: clumping_clay, silica or pellets
+    Litter {
+        profile: LitterProfile
+    },
+    /// Records that a new bag of litter was opened, from which the full changes are counted
+    BagOpened {
+        /// Who opened the bag
+        #[arg(long)]
+        by: Option<String>
+    },
+    /// Records that a care reminder was done, e.g. `care-done "flea treatment"`, the first one that is due if no name is
+    /// given
+    CareDone {
+        name: Option<String>,
+        /// Who did it
+        #[arg(long)]
+        by: Option<String>
+    },
+    /// Makes the running reminder check for an update right away
+    Update {
+        /// Asks all the other nodes to check as well
+        #[arg(long)]
+        fleet: bool
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ExportFormat {
+    Csv,
+    Json
+}
@@ -0,0 +1,59 @@
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Source of the current time of the reminder, so that time can be simulated.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The wall clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to, e.g. to let hours pass in an instant.
+pub struct SimulatedClock {
+    now: Mutex<DateTime<Utc>>
+}
+
+impl SimulatedClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        SimulatedClock { now: Mutex::new(start) }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// A clock running the given number of times faster than the wall clock from the time it was created, e.g. to watch
+/// a day go by in a few minutes.
+pub struct ScaledClock {
+    start: DateTime<Utc>,
+    started: Instant,
+    scale: f64
+}
+
+impl ScaledClock {
+    pub fn new(scale: f64) -> Self {
+        ScaledClock { start: Utc::now(), started: Instant::now(), scale }
+    }
+}
+
+impl Clock for ScaledClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.start + Duration::milliseconds((self.started.elapsed().as_millis() as f64 * self.scale).round() as i64)
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Days, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::PatternConfig;
+use crate::care::CareConfig;
+use crate::dbus::DbusConfig;
+use crate::eink::EinkConfig;
+use crate::gamification::GamificationConfig;
+use crate::encoder::EncoderConfig;
+use crate::history::HistoryConfig;
+use crate::holiday::HolidaysConfig;
+use crate::http::HttpConfig;
+use crate::influx::InfluxConfig;
+use crate::input::InputConfig;
+use crate::ir_remote::IrRemoteConfig;
+use crate::lcd::LcdConfig;
+use crate::litter::{self, LitterProfile};
+use crate::lamp::LampConfig;
+use crate::led::{Calibration, HeartbeatConfig, SecondStripConfig, StripLayout};
+use crate::led_matrix::MatrixConfig;
+use crate::logging::LoggingConfig;
+use crate::network::NetworkConfig;
+use crate::notifier::{EscalationPolicy, NotifierConfig};
+use crate::oled::OledConfig;
+use crate::palette::Palette;
+use crate::presence::PresenceConfig;
+use crate::reminder::LEDStripState;
+use crate::provisioning::ProvisioningConfig;
+use crate::pwm_led::PwmLedConfig;
+use crate::relay::RelayConfig;
+use crate::segment::SegmentConfig;
+use crate::sensor::SensorsConfig;
+use crate::sound::SoundConfig;
+use crate::state::STATE_FILE_PATH;
+use crate::storage::StorageConfig;
+use crate::supplies::SuppliesConfig;
+use crate::thermal::ThermalConfig;
+use crate::update::UpdateConfig;
+use crate::users::UserConfig;
+use crate::ups::UpsConfig;
+use crate::websocket::WebSocketConfig;
+
+pub const CONFIG_FILE_PATH: &str = "cat_reminder.toml";
+
+/// The schedule of the first configuration loaded, which is the one of the running process.
+static SCHEDULE: OnceLock<ScheduleConfig> = OnceLock::new();
+/// The household members of the first configuration loaded.
+static USERS: OnceLock<Vec<UserConfig>> = OnceLock::new();
+/// The state file of the first configuration loaded, next to which the other files of the node are kept.
+static STATE_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Configuration of the reminder, read from a TOML file. All sections are optional.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Care reminders recurring every few months, such as the flea treatment
+    pub care: Vec<CareConfig>,
+    pub chores: ChoresConfig,
+    /// Unix domain socket through which local processes control the reminder, `/run/cat-reminder.sock` if not set
+    pub control_socket: Option<PathBuf>,
+    pub dbus: Option<DbusConfig>,
+    pub displays: DisplaysConfig,
+    pub eink: Option<EinkConfig>,
+    pub encoder: Option<EncoderConfig>,
+    pub gamification: Option<GamificationConfig>,
+    pub history: HistoryConfig,
+    /// Trips of the household, during which a cat-sitter looks after the litter box
+    pub holidays: Option<HolidaysConfig>,
+    pub http: Option<HttpConfig>,
+    pub influxdb: Option<InfluxConfig>,
+    /// GPIO inputs and their actions, a reset button on GPIO 5 if none are configured
+    pub inputs: Vec<InputConfig>,
+    pub ir_remote: Option<IrRemoteConfig>,
+    pub lamp: Option<LampConfig>,
+    pub lcd: Option<LcdConfig>,
+    pub led_matrix: Option<MatrixConfig>,
+    /// Disables every integration talking to anything but the other nodes, which are reached over mDNS and the
+    /// transport only, for an explicitly offline device
+    pub local_only: bool,
+    pub logging: LoggingConfig,
+    pub network: NetworkConfig,
+    pub notifications: EscalationPolicy,
+    pub notifiers: Vec<NotifierConfig>,
+    pub oled: Option<OledConfig>,
+    pub presence: Option<PresenceConfig>,
+    pub provisioning: ProvisioningConfig,
+    pub pwm_led: Option<PwmLedConfig>,
+    pub relay: Option<RelayConfig>,
+    pub schedule: ScheduleConfig,
+    pub segment: Option<SegmentConfig>,
+    pub sensors: SensorsConfig,
+    pub sounds: Option<SoundConfig>,
+    /// File holding the state of the reminder, `/var/lib/cat-reminder/state` if not set
+    pub state_file: Option<PathBuf>,
+    pub storage: StorageConfig,
+    pub supplies: Option<SuppliesConfig>,
+    pub thermal: Option<ThermalConfig>,
+    pub ups: Option<UpsConfig>,
+    pub update: Option<UpdateConfig>,
+    /// Household members, with the tags and chat handles identifying them
+    pub users: Vec<UserConfig>,
+    pub websocket: Option<WebSocketConfig>
+}
+
+impl Config {
+    /// Names of the enabled sections that talk to anything but the other nodes, be it on the local network or beyond.
+    pub fn outbound_integrations(&self) -> Vec<&'static str> {
+        let sections = [
+            ("notifiers", !self.notifiers.is_empty()),
+            ("http", self.http.is_some()),
+            ("websocket", self.websocket.is_some()),
+            ("influxdb", self.influxdb.is_some()),
+            ("lamp", self.lamp.is_some()),
+            ("presence", self.presence.is_some()),
+            ("update", self.update.is_some())
+        ];
+        sections.into_iter().filter(|(_, enabled)| *enabled).map(|(name, _)| name).collect()
+    }
+
+    /// Disables the sections talking to anything but the other nodes.
+    pub fn disable_outbound_integrations(&mut self) {
+        self.notifiers.clear();
+        self.http = None;
+        self.websocket = None;
+        self.influxdb = None;
+        self.lamp = None;
+        self.presence = None;
+        self.update = None;
+    }
+}
+
+/// When the litter box needs attention, and in which time zone the night, the quiet hours and the dates are determined.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    pub timezone: Tz,
+    /// Seconds since the last cleaning after which the strip turns dark green, orange, red and blinking red, for a
+    /// single cat
+    pub thresholds: [i64; 4],
+    /// Number of cats using the litter box, shortening the thresholds when there are several
+    pub cats: u32,
+    /// Litter used, adjusting the thresholds and the interval at which it is replaced entirely
+    pub litter: LitterProfile,
+    /// Days after which the litter is replaced entirely, as recommended for the litter if not set
+    pub full_change_days: Option<i64>,
+    /// Settings of single litter boxes, by name, overriding the ones above
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub boxes: BTreeMap<String, BoxScheduleConfig>,
+    /// Hours during which the urgency builds up slower, if at all, so that the morning does not start with an alarm
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overnight: Option<OvernightConfig>,
+    /// Number of cleanings to aim for every day, as vets advise for several cats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_target: Option<DailyTargetConfig>
+}
+
+/// Hours of the night during which the time since the last cleaning counts less toward the urgency.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OvernightConfig {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Share of the time counted during these hours, 0 pausing the urgency and 1 counting the time as usual
+    pub factor: f64
+}
+
+impl Default for OvernightConfig {
+    fn default() -> Self {
+        OvernightConfig { start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(), end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(), factor: 0.0 }
+    }
+}
+
+/// Cleanings to do every day, the urgency being raised by one level while the target is missed in the evening.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DailyTargetConfig {
+    pub scoops: usize,
+    /// Local time by which the target needs to be met
+    pub deadline: NaiveTime
+}
+
+impl Default for DailyTargetConfig {
+    fn default() -> Self {
+        DailyTargetConfig { scoops: 2, deadline: NaiveTime::from_hms_opt(20, 0, 0).unwrap() }
+    }
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        ScheduleConfig { timezone: chrono_tz::Europe::Vienna, thresholds: [8, 12, 24, 26], cats: 1, litter: LitterProfile::default(),
+            full_change_days: None, boxes: BTreeMap::new(), overnight: None, daily_target: None }
+    }
+}
+
+impl ScheduleConfig {
+    /// The thresholds of the litter box, scaled to the number of cats using it and to its litter. Each cat after the
+    /// first one fills the box half as fast as the first one, cats sharing the work with the other boxes of the
+    /// household: the thresholds of two cats are two thirds of the ones of a single cat, the ones of four cats 40%.
+    pub fn thresholds(&self, name: &str) -> [i64; 4] {
+        let overrides = self.boxes.get(name);
+        let thresholds = overrides.and_then(|b| b.thresholds).unwrap_or(self.thresholds);
+        let cats = overrides.and_then(|b| b.cats).unwrap_or(self.cats).max(1);
+        let factor = self.litter(name).scoop_factor() / (1.0 + (cats - 1) as f64 / 2.0);
+        thresholds.map(|seconds| (seconds as f64 * factor).round() as i64)
+    }
+
+    /// The time since the last cleaning counted toward the urgency, the overnight hours counting only by their factor.
+    pub fn urgency_elapsed(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+        let elapsed = now.signed_duration_since(since);
+        let Some(overnight) = &self.overnight else {
+            return elapsed;
+        };
+        if elapsed <= Duration::zero() {
+            return elapsed;
+        }
+        // the night that started the day before the cleaning may still be running
+        let first_date = since.with_timezone(&self.timezone).date_naive() - Days::new(1);
+        let last_date = now.with_timezone(&self.timezone).date_naive();
+        let mut night_seconds = 0;
+        for date in first_date.iter_days().take_while(|date| *date <= last_date) {
+            let end_date = if overnight.start <= overnight.end { date } else { date + Days::new(1) };
+            let start = self.timezone.from_local_datetime(&date.and_time(overnight.start)).earliest();
+            let end = self.timezone.from_local_datetime(&end_date.and_time(overnight.end)).earliest();
+            if let (Some(start), Some(end)) = (start, end) {
+                let overlap = now.min(end.with_timezone(&Utc)).signed_duration_since(since.max(start.with_timezone(&Utc)));
+                if overlap > Duration::zero() {
+                    night_seconds += overlap.num_seconds();
+                }
+            }
+        }
+        let uncounted = night_seconds as f64 * (1.0 - overnight.factor.clamp(0.0, 1.0));
+        elapsed - Duration::seconds(uncounted.round() as i64)
+    }
+
+    /// The time at which the given urgency is reached after the given cleaning, to the second, the inverse of
+    /// [Self::urgency_elapsed].
+    pub fn urgency_reached_at(&self, since: DateTime<Utc>, urgency: Duration) -> DateTime<Utc> {
+        // the urgency never builds up faster than the time passes, so it is reached after the urgency at the earliest
+        if urgency <= Duration::zero() || self.urgency_elapsed(since, since + urgency) >= urgency {
+            return since + urgency;
+        }
+        let reached = |seconds: i64| self.urgency_elapsed(since, since + Duration::seconds(seconds)) >= urgency;
+        let mut low = urgency.num_seconds();
+        let mut high = low * 2;
+        // the days make up for the nights sooner or later, unless the night hardly leaves any day
+        for _ in 0..16 {
+            if reached(high) {
+                break;
+            }
+            low = high;
+            high *= 2;
+        }
+        while high - low > 1 {
+            let middle = (low + high) / 2;
+            if reached(middle) {
+                high = middle;
+            } else {
+                low = middle;
+            }
+        }
+        since + Duration::seconds(high)
+    }
+
+    /// The time at which the given litter box, cleaned at the given time, becomes overdue, i.e. its state turns red.
+    pub fn due_time(&self, name: &str, since: DateTime<Utc>) -> DateTime<Utc> {
+        self.urgency_reached_at(since, Duration::seconds(self.thresholds(name)[LEDStripState::Red as usize - 1]))
+    }
+
+    /// The litter of the box, the one selected at runtime taking precedence over the configured ones.
+    pub fn litter(&self, name: &str) -> LitterProfile {
+        litter::selected(name).or_else(|| self.boxes.get(name).and_then(|b| b.litter)).unwrap_or(self.litter)
+    }
+
+    /// Days after which the litter of the box is replaced entirely.
+    pub fn full_change_days(&self, name: &str) -> i64 {
+        self.boxes.get(name).and_then(|b| b.full_change_days)
+            .or(self.full_change_days)
+            .unwrap_or_else(|| self.litter(name).full_change_days())
+    }
+}
+
+/// The schedule of a single litter box, anything not set being taken from the schedule.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct BoxScheduleConfig {
+    pub cats: Option<u32>,
+    pub thresholds: Option<[i64; 4]>,
+    pub litter: Option<LitterProfile>,
+    pub full_change_days: Option<i64>
+}
+
+/// The schedule of the running process, the default one if no configuration was loaded, e.g. in tests.
+pub fn schedule() -> &'static ScheduleConfig {
+    SCHEDULE.get_or_init(ScheduleConfig::default)
+}
+
+/// The household members of the running process, none if no configuration was loaded.
+pub fn users() -> &'static [UserConfig] {
+    USERS.get_or_init(Vec::new)
+}
+
+/// The state file of the running process, the default one if no configuration was loaded.
+pub fn state_file() -> &'static Path {
+    STATE_FILE.get_or_init(|| PathBuf::from(STATE_FILE_PATH))
+}
+
+/// Household members taking turns in cleaning the litter box, in order.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ChoresConfig {
+    pub members: Vec<String>
+}
+
+/// Displays without settings of their own. The other displays are enabled by their sections.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaysConfig {
+    /// The LED strip, enabled by default
+    pub strip: bool,
+    /// Number of LEDs of the strip
+    pub led_count: usize,
+    /// Prints state changes to the standard output
+    pub terminal: bool,
+    /// Blink patterns by state, blinking red blinking fast and the other states being solid if not set
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub patterns: BTreeMap<LEDStripState, PatternConfig>,
+    /// Colors of the states on the strip
+    pub palette: Palette,
+    /// RGB colors by state, overriding the ones of the palette
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub colors: BTreeMap<LEDStripState, [u8; 3]>,
+    /// Correction of the colors for the strip, see the `calibrate` command
+    pub calibration: Calibration,
+    /// How the strip is mounted, e.g. reversed or with broken LEDs
+    pub layout: StripLayout,
+    /// Single dim LED lit every now and then while the strip is dark, e.g. during the night
+    pub heartbeat: Option<HeartbeatConfig>,
+    /// Zone whose litter box the strip shows on hubs, the most urgent of all if not set
+    pub zone: Option<String>,
+    /// Strip on the second channel of the controller, sharing the palette, the calibration and the heartbeat
+    pub second_strip: Option<SecondStripConfig>,
+    /// Whether the displays play a short animation on startup, ending with the minor version
+    pub startup_animation: bool,
+    /// Whether the strip dims some of its LEDs to show the progress toward the next state
+    pub progress: bool
+}
+
+impl Default for DisplaysConfig {
+    fn default() -> Self {
+        DisplaysConfig { strip: true, led_count: 10, terminal: false, patterns: BTreeMap::new(), palette: Palette::default(),
+            colors: BTreeMap::new(), calibration: Calibration::default(),
+            layout: StripLayout::default(), heartbeat: None,
+            zone: None, second_strip: None, startup_animation: true,
+            progress: false }
+    }
+}
+
+/// Loads the configuration file, falling back to the default configuration if there is none or if it cannot be read.
+pub fn load() -> Config {
+    let config = read();
+    let _ = SCHEDULE.set(config.schedule.clone());
+    let _ = USERS.set(config.users.clone());
+    let _ = STATE_FILE.set(config.state_file.clone().unwrap_or_else(|| PathBuf::from(STATE_FILE_PATH)));
+    config
+}
+
+fn read() -> Config {
+    if Path::new(CONFIG_FILE_PATH).exists() {
+        let parsed_config = fs::read_to_string(CONFIG_FILE_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|str| toml::from_str(&str).map_err(|e| e.to_string()));
+
+        parsed_config.unwrap_or_else(|err| {
+            log::error!("Error reading configuration: {}", err);
+            Config::default()
+        })
+    } else {
+        Config::default()
+    }
+}
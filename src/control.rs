@@ -0,0 +1,169 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::litter::LitterProfile;
+use crate::reminder::{ReminderEvent, ReminderStatus, ResetSource, MAX_GUEST_HOURS, MAX_SNOOZE_MINUTES};
+
+pub const CONTROL_SOCKET_PATH: &str = "/run/cat-reminder.sock";
+
+const POLL_DELAY: Duration = Duration::from_millis(200);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A command sent over the control socket, one JSON document per line, e.g. `{"command": "snooze", "minutes": 30}`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum ControlCommand {
+    Status,
+    Reset { by: Option<String> },
+    Snooze { minutes: i64, by: Option<String> },
+    Pause { by: Option<String> },
+    Resume { by: Option<String> },
+    /// Sets the time of the last cleaning, e.g. when the reset was forgotten
+    SetTime { time: DateTime<Utc>, by: Option<String> },
+    /// Checks for an update right away, on all nodes if `fleet` is set
+    Update { fleet: bool },
+    /// Enables the guest mode for the given number of hours, zero hours ending it
+    Guest { hours: i64 },
+    /// Records that the litter was replaced entirely, which also resets the timer
+    LitterReplaced { by: Option<String> },
+    /// Changes the litter used in the litter box
+    Litter { profile: LitterProfile },
+    /// Records that a new bag of litter was opened
+    BagOpened { by: Option<String> },
+    /// Records that a care reminder was done, the first one that is due if no name is given
+    CareDone { name: Option<String>, by: Option<String> }
+}
+
+/// The answer to a command, one JSON document per line. The status is only sent for the `status` command.
+#[derive(Serialize)]
+struct ControlResponse {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<ReminderStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>
+}
+
+/// The path of the control socket.
+pub fn socket_path(config: &Config) -> PathBuf {
+    config.control_socket.clone().unwrap_or_else(|| PathBuf::from(CONTROL_SOCKET_PATH))
+}
+
+/// Runs the control socket, through which local processes such as the CLI query and control the running reminder.
+/// The socket is removed on shutdown.
+pub fn run(path: PathBuf, status: Arc<RwLock<ReminderStatus>>, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    // a socket left behind by a reminder that did not shut down cleanly
+    let _ = fs::remove_file(&path);
+    let listener = match UnixListener::bind(&path).and_then(|listener| listener.set_nonblocking(true).map(|_| listener)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Could not listen at {}: {}", path.display(), e);
+            return;
+        }
+    };
+    log::info!("Control socket listening at {}", path.display());
+
+    std::thread::Builder::new().name("control".to_string()).spawn(move || {
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = serve(stream, &status, &reminder_tx) {
+                        log::warn!("Control connection failed: {}", e);
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => sleep(POLL_DELAY),
+                Err(e) => {
+                    log::error!("Failed to accept control connection: {}", e);
+                    sleep(POLL_DELAY);
+                }
+            }
+        }
+        let _ = fs::remove_file(&path);
+    }).expect("Could not start control thread");
+}
+
+/// Answers the commands of a connection until it is closed.
+fn serve(stream: UnixStream, status: &Arc<RwLock<ReminderStatus>>, reminder_tx: &Sender<ReminderEvent>) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<ControlCommand>(&line) {
+            Ok(command) => {
+                log::debug!("Control command {:?}", command);
+                handle(command, status, reminder_tx)
+            }
+            Err(e) => ControlResponse { ok: false, status: None, error: Some(format!("Invalid command: {}", e)) }
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+fn handle(command: ControlCommand, status: &Arc<RwLock<ReminderStatus>>, reminder_tx: &Sender<ReminderEvent>) -> ControlResponse {
+    let current_status = status.read().unwrap().clone();
+    let is_paused = current_status.snoozed_until == Some(DateTime::<Utc>::MAX_UTC);
+    let event = match command {
+        ControlCommand::Status => return ControlResponse { ok: true, status: Some(current_status), error: None },
+        ControlCommand::Reset { by } => Some(ReminderEvent::ResetRequested(ResetSource::Control, by)),
+        ControlCommand::Snooze { minutes, .. } if minutes < 0 => return ControlResponse { ok: false, status: None, error: Some("Negative snooze".to_string()) },
+        ControlCommand::Snooze { minutes, .. } if minutes > MAX_SNOOZE_MINUTES => {
+            return ControlResponse { ok: false, status: None, error: Some(format!("Snooze longer than {} minutes, pause instead", MAX_SNOOZE_MINUTES)) };
+        }
+        ControlCommand::Snooze { minutes, by } => Some(ReminderEvent::Snooze(chrono::Duration::minutes(minutes), by)),
+        ControlCommand::Pause { by } => (!is_paused).then_some(ReminderEvent::TogglePause(by)),
+        ControlCommand::Resume { by } => is_paused.then_some(ReminderEvent::TogglePause(by)),
+        ControlCommand::SetTime { time, .. } if time > Utc::now() => return ControlResponse { ok: false, status: None, error: Some("Cleaning time in the future".to_string()) },
+        ControlCommand::SetTime { time, by } => Some(ReminderEvent::CleaningTimeSet(time, ResetSource::Control, by)),
+        ControlCommand::Update { fleet } => Some(ReminderEvent::UpdateRequested(fleet)),
+        ControlCommand::Guest { hours } if hours < 0 => return ControlResponse { ok: false, status: None, error: Some("Negative guest mode duration".to_string()) },
+        ControlCommand::Guest { hours } if hours > MAX_GUEST_HOURS => {
+            return ControlResponse { ok: false, status: None, error: Some(format!("Guest mode longer than {} hours", MAX_GUEST_HOURS)) };
+        }
+        ControlCommand::Guest { hours } => Some(ReminderEvent::GuestMode(chrono::Duration::hours(hours))),
+        ControlCommand::LitterReplaced { by } => Some(ReminderEvent::LitterReplaced(ResetSource::Control, by)),
+        ControlCommand::Litter { profile } => Some(ReminderEvent::LitterSelected(profile)),
+        ControlCommand::BagOpened { by } => Some(ReminderEvent::BagOpened(by)),
+        ControlCommand::CareDone { name, by } => Some(ReminderEvent::CareDone(name, by))
+    };
+    match event.map(|event| reminder_tx.send(event)) {
+        Some(Err(_)) => ControlResponse { ok: false, status: None, error: Some("The reminder stopped".to_string()) },
+        _ => ControlResponse { ok: true, status: None, error: None }
+    }
+}
+
+/// Sends a command to the running reminder and prints the response. Returns an error if the reminder could not be
+/// reached or rejected the command.
+pub fn send(path: &Path, command: &ControlCommand) -> io::Result<()> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    serde_json::to_writer(&mut stream, command)?;
+    writeln!(stream)?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response: serde_json::Value = serde_json::from_str(&line)?;
+    if response["ok"].as_bool() != Some(true) {
+        let error = response["error"].as_str().unwrap_or("No response from the reminder");
+        return Err(io::Error::other(error.to_string()));
+    }
+    if let Some(status) = response.get("status") {
+        println!("{}", serde_json::to_string_pretty(status)?);
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Kept next to the state file
+pub const CRASH_LOG_FILE_NAME: &str = "crashes";
+
+#[derive(Serialize)]
+struct CrashRecord {
+    time: DateTime<Utc>,
+    thread: String,
+    message: String,
+    location: Option<String>
+}
+
+/// Installs a panic hook that appends a crash record to the crash log. A panic of the main thread, which runs the
+/// reminder loop, shows an error pattern on the strip and aborts, so that a dark strip is never mistaken for the night.
+/// The pattern is shown through the controller of the strip, and aborting keeps it from turning the strip off.
+/// The other threads are left to unwind, most of them being restarted or logging their failure.
+pub fn install_hook(crash_log: PathBuf, strip: bool) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+        if let Err(e) = append(&crash_log, record(info, &thread)) {
+            log::error!("Could not write crash record to {}: {}", crash_log.display(), e);
+        }
+        if thread == "main" {
+            if strip {
+                crate::led::show_failure();
+            }
+            std::process::abort();
+        }
+    }));
+}
+
+fn record(info: &PanicHookInfo, thread: &str) -> CrashRecord {
+    let message = info.payload().downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    CrashRecord { time: Utc::now(), thread: thread.to_string(), message, location: info.location().map(|l| l.to_string()) }
+}
+
+fn append(path: &Path, record: CrashRecord) -> std::io::Result<()> {
+    let line = serde_json::to_string(&record)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    file.sync_all()
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Deserialize;
+use zbus::{fdo, interface};
+use zbus::blocking::connection::Builder;
+
+use crate::reminder::{LEDStripState, ReminderEvent, ReminderStatus, ResetSource};
+
+const SERVICE_NAME: &str = "org.catreminder.Daemon1";
+const OBJECT_PATH: &str = "/org/catreminder/Daemon1";
+const POLL_DELAY: Duration = Duration::from_millis(1000);
+
+/// The D-Bus service is enabled by adding the section. Owning the name on the system bus requires a policy allowing it.
+#[derive(Deserialize, Clone)]
+pub struct DbusConfig {
+    #[serde(default)]
+    pub bus: Bus
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Bus {
+    #[default]
+    System,
+    Session
+}
+
+/// The reminder as seen from D-Bus, e.g. `busctl get-property org.catreminder.Daemon1 /org/catreminder/Daemon1
+/// org.catreminder.Daemon1 Urgency`.
+struct Daemon {
+    status: Arc<RwLock<ReminderStatus>>,
+    reminder_tx: Sender<ReminderEvent>
+}
+
+#[interface(name = "org.catreminder.Daemon1")]
+impl Daemon {
+    /// Seconds since the last cleaning. Not announced as changed, since it changes all the time.
+    #[zbus(property)]
+    fn elapsed_seconds(&self) -> i64 {
+        Utc::now().signed_duration_since(self.status.read().unwrap().last_cleaning_time).num_seconds()
+    }
+
+    /// The state of the strip, from `LightGreen` to `BlinkingRed`
+    #[zbus(property)]
+    fn urgency(&self) -> String {
+        format!("{:?}", self.status.read().unwrap().state)
+    }
+
+    /// Time of the last cleaning, in RFC 3339
+    #[zbus(property)]
+    fn last_cleaning_time(&self) -> String {
+        self.status.read().unwrap().last_cleaning_time.to_rfc3339()
+    }
+
+    /// Whose turn it is, empty if chores are not configured
+    #[zbus(property)]
+    fn assignee(&self) -> String {
+        self.status.read().unwrap().assignee.clone().unwrap_or_default()
+    }
+
+    /// Resets the timer, with the name of who cleaned, which may be empty
+    fn reset(&self, by: String) -> fdo::Result<()> {
+        let by = Some(by).filter(|by| !by.is_empty());
+        self.send(ReminderEvent::ResetRequested(ResetSource::DBus, by))
+    }
+
+    /// Snoozes the reminder, zero minutes ending a running snooze
+    fn snooze(&self, minutes: u32) -> fdo::Result<()> {
+        self.send(ReminderEvent::Snooze(chrono::Duration::minutes(minutes as i64), None))
+    }
+}
+
+impl Daemon {
+    fn send(&self, event: ReminderEvent) -> fdo::Result<()> {
+        self.reminder_tx.send(event).map_err(|_| fdo::Error::Failed("The reminder stopped".to_string()))
+    }
+}
+
+/// Runs the D-Bus service, announcing changes of the urgency and of the last cleaning time.
+pub fn run(config: DbusConfig, status: Arc<RwLock<ReminderStatus>>, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    std::thread::Builder::new().name("dbus".to_string()).spawn(move || {
+        let daemon = Daemon { status: status.clone(), reminder_tx };
+        let connection = match config.bus { Bus::System => Builder::system(), Bus::Session => Builder::session() }
+            .and_then(|builder| builder.name(SERVICE_NAME))
+            .and_then(|builder| builder.serve_at(OBJECT_PATH, daemon))
+            .and_then(|builder| builder.build());
+        let connection = match connection {
+            Ok(connection) => connection,
+            Err(e) => {
+                log::error!("Could not register the D-Bus service: {}", e);
+                return;
+            }
+        };
+        log::info!("D-Bus service {} registered", SERVICE_NAME);
+
+        let mut last: Option<(LEDStripState, chrono::DateTime<Utc>)> = None;
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let current = status.read().unwrap().clone();
+            let changed = last.map(|(state, time)| (state != current.state, time != current.last_cleaning_time));
+            last = Some((current.state, current.last_cleaning_time));
+            let Some((state_changed, time_changed)) = changed else { continue };
+            if !state_changed && !time_changed {
+                continue;
+            }
+            let Ok(daemon) = connection.object_server().interface::<_, Daemon>(OBJECT_PATH) else { continue };
+            let context = daemon.signal_context();
+            let result = zbus::block_on(async {
+                if state_changed {
+                    daemon.get().urgency_changed(context).await?;
+                }
+                if time_changed {
+                    daemon.get().last_cleaning_time_changed(context).await?;
+                    daemon.get().assignee_changed(context).await?;
+                }
+                Ok::<(), zbus::Error>(())
+            });
+            if let Err(e) = result {
+                log::warn!("Could not announce D-Bus property change: {}", e);
+            }
+        }
+    }).expect("Could not start D-Bus thread");
+}
@@ -0,0 +1,109 @@
+//! Diagnostics of a node: its health, status, peers and recent logs. They are served by the HTTP API, which also
+//! fetches them from the peers, so that a node can be debugged from another one.
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use gethostname::gethostname;
+use serde::Serialize;
+
+use crate::discovery;
+use crate::health::{self, Check, Health, HealthProbe};
+use crate::http::HttpConfig;
+use crate::logging::LogFiles;
+use crate::reminder::ReminderStatus;
+
+pub const DEFAULT_LOG_LINES: usize = 100;
+pub const MAX_LOG_LINES: usize = 1000;
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+pub struct Diagnostics {
+    /// Host name of the node
+    pub node: String,
+    pub version: &'static str,
+    pub uptime_seconds: u64,
+    pub health: Health,
+    pub checks: Vec<Check>,
+    pub status: ReminderStatus,
+    /// The other nodes, by instance name, with their addresses
+    pub peers: HashMap<String, Vec<Ipv4Addr>>,
+    pub logs: Vec<String>
+}
+
+/// Gathers the diagnostics of the running node.
+pub struct DiagnosticsProbe {
+    pub health: HealthProbe,
+    /// The log files, the journal of the process being read instead if they are disabled
+    pub log_files: Option<LogFiles>,
+    pub peer_cache: PathBuf,
+    pub started: Instant
+}
+
+impl DiagnosticsProbe {
+    pub fn run(&self, status: ReminderStatus, lines: usize) -> Diagnostics {
+        let checks = self.checks(&status);
+        Diagnostics {
+            node: gethostname().to_string_lossy().into_owned(),
+            version: env!("CARGO_PKG_VERSION"),
+            uptime_seconds: self.started.elapsed().as_secs(),
+            health: health::overall(&checks),
+            checks,
+            status,
+            peers: self.peers(),
+            logs: self.logs(lines)
+        }
+    }
+
+    /// The health checks of the node, along with the ones of the subsystems of the running reminder.
+    pub fn checks(&self, status: &ReminderStatus) -> Vec<Check> {
+        let mut checks = self.health.run();
+        checks.push(subsystems(status));
+        checks
+    }
+
+    /// The addresses of the other nodes, which are the only ones whose diagnostics are fetched.
+    pub fn peers(&self) -> HashMap<String, Vec<Ipv4Addr>> {
+        discovery::load_peers(&self.peer_cache)
+    }
+
+    fn logs(&self, lines: usize) -> Vec<String> {
+        let result = match &self.log_files {
+            Some(log_files) => log_files.last_lines(lines).map_err(|e| e.to_string()),
+            None => journal(lines)
+        };
+        result.unwrap_or_else(|e| vec![format!("Could not read the logs: {}", e)])
+    }
+}
+
+/// Whether the subsystems and displays of the running reminder work, retrying being of no help to the failing ones.
+fn subsystems(status: &ReminderStatus) -> Check {
+    let (health, detail) = if status.failing.is_empty() {
+        (Health::Ok, "all working".to_string())
+    } else {
+        (Health::Failed, format!("failing: {}", status.failing.iter().cloned().collect::<Vec<_>>().join(", ")))
+    };
+    Check { name: "subsystems", health, detail }
+}
+
+/// The last lines logged by this process to the journal.
+fn journal(lines: usize) -> Result<Vec<String>, String> {
+    let output = Command::new("journalctl")
+        .args([format!("_PID={}", std::process::id()), format!("--lines={}", lines), "--output=cat".to_string(), "--no-pager".to_string()])
+        .output()
+        .map_err(|e| format!("could not run journalctl: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+/// Fetches the diagnostics of a peer, which is expected to serve the HTTP API on the same port and to accept the
+/// same token, returning them as they were sent.
+pub fn fetch(peer: Ipv4Addr, config: &HttpConfig, token: &str, lines: usize) -> Result<String, ureq::Error> {
+    let url = format!("{}://{}:{}/diagnostics?lines={}", config.scheme(), peer, config.port, lines);
+    Ok(ureq::get(&url).set("Authorization", &format!("Bearer {}", token)).timeout(PEER_TIMEOUT).call()?.into_string()?)
+}
@@ -1,74 +1,189 @@
 use std::collections::HashMap;
+use std::fs;
 use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
 
 use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
 use rnglib::{Language, RNG};
 use gethostname::gethostname;
-use std::os::unix::ffi::OsStrExt;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-use super::transport::TransportEvent;
+use super::error::CatReminderError;
+use super::protocol::PROTOCOL_VERSION;
+use super::transport::{NodeRole, TransportEvent, Zoning};
 
 const SERVICE_TYPE: &str = "_cat._udp.local.";
+/// Kept next to the state file
+pub const PEER_CACHE_FILE_NAME: &str = "peers";
+const POLL_DELAY: Duration = Duration::from_millis(1000);
+const REGISTRATION_RETRY_DELAY: Duration = Duration::from_secs(30);
 
+/// Runs the mDNS discovery of the other nodes. The last known nodes are kept in the peer cache,
+/// so that they can be reached right after a restart even when mDNS is slow to resolve them. Nodes only keep the nodes
+/// of their zone, which is advertised in the `zone` TXT record, while hubs keep all of them. The role of the node is
+/// advertised in the `profile` TXT record.
+pub fn run(ip_addr: IpAddr, port: u16, peer_cache: PathBuf, zoning: Zoning, role: NodeRole, network_tx: Sender<TransportEvent>, peer_count: Arc<AtomicUsize>, shutdown_flag: Arc<AtomicBool>) -> JoinHandle<()> {
+    std::thread::Builder::new().name("discovery".to_string()).spawn(move || {
+        let _span = tracing::info_span!("discovery", %ip_addr, port).entered();
+        // the supervisor starts the discovery again when the daemon cannot be created
+        let mdns = match ServiceDaemon::new() {
+            Ok(mdns) => mdns,
+            Err(e) => {
+                log::error!("Could not create the mDNS daemon: {}", CatReminderError::from(e));
+                return;
+            }
+        };
 
-pub fn run(ip_addr: IpAddr, port: u16, network_tx: Sender<TransportEvent>, shutdown_flag: Arc<AtomicBool>) {
-    let mdns = ServiceDaemon::new().expect("Failed to create mDNS daemon");
-
-    let rng = RNG::try_from(&Language::Demonic).unwrap();
-    let instance_name = rng.generate_name();
-    log::info!("Instance name: {}", instance_name);
-    let hostname = gethostname();
-    let host_name: &str = std::str::from_utf8(hostname.as_bytes()).unwrap();
-    let host_name_full = format!("{}.local.", host_name);
-    log::info!("Hostname: {}", host_name_full);
+        let rng = RNG::try_from(&Language::Demonic).unwrap();
+        let instance_name = rng.generate_name();
+        log::info!("Instance name: {}", instance_name);
+        let hostname = gethostname();
+        let host_name_full = format!("{}.local.", hostname.to_string_lossy());
+        log::info!("Hostname: {}", host_name_full);
 
-    let service_info = ServiceInfo::new(
-        SERVICE_TYPE,
-        instance_name.as_str(),
-        host_name_full.as_str(),
-        ip_addr,
-        port,
-        None
-    ).unwrap().enable_addr_auto();
+        let mut properties = HashMap::from([
+            ("protocol".to_string(), PROTOCOL_VERSION.to_string()),
+            ("encodings".to_string(), "bincode,json".to_string()),
+            ("profile".to_string(), role.to_string())
+        ]);
+        if let Some(zone) = &zoning.zone {
+            properties.insert("zone".to_string(), zone.clone());
+        }
+        if zoning.hub {
+            properties.insert("hub".to_string(), "true".to_string());
+        }
+        let service_info = match ServiceInfo::new(
+            SERVICE_TYPE,
+            instance_name.as_str(),
+            host_name_full.as_str(),
+            ip_addr,
+            port,
+            // every node decodes both encodings, see the protocol
+            properties
+        ) {
+            Ok(service_info) => service_info.enable_addr_auto(),
+            Err(e) => {
+                log::error!("Invalid mDNS service: {}", CatReminderError::from(e));
+                let _ = mdns.shutdown();
+                return;
+            }
+        };
 
-    let service_fullname = service_info.get_fullname().to_string();
-    mdns.register(service_info).expect("Failed to register mDNS service");
+        let service_fullname = service_info.get_fullname().to_string();
 
-    let mut cat_reminder_instances = HashMap::new();
+        let mut cat_reminder_instances = load_peers(&peer_cache);
+        if !cat_reminder_instances.is_empty() {
+            log::info!("Reconnecting to cached peers {:?}", cat_reminder_instances);
+            peer_count.store(cat_reminder_instances.len(), Ordering::Relaxed);
+            if network_tx.send(TransportEvent::NodeListUpdated(cat_reminder_instances.clone())).is_err() {
+                log::error!("{}", CatReminderError::ChannelClosed("transport"));
+                let _ = mdns.shutdown();
+                return;
+            }
+        }
 
-    let receiver = mdns.browse(SERVICE_TYPE).expect("Failed to browse mDNS services");
-    std::thread::spawn(move || {
-        while let Ok(event) = receiver.recv() {
-            match event {
+        // registering and browsing are retried until they succeed, and again whenever the daemon drops the browse
+        let mut registered = false;
+        let mut receiver = None;
+        let mut last_attempt: Option<Instant> = None;
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            if (!registered || receiver.is_none()) && last_attempt.is_none_or(|t| t.elapsed() >= REGISTRATION_RETRY_DELAY) {
+                last_attempt = Some(Instant::now());
+                if !registered {
+                    match mdns.register(service_info.clone()) {
+                        Ok(()) => registered = true,
+                        Err(e) => log::warn!("Could not register the mDNS service, retrying later: {}", CatReminderError::from(e))
+                    }
+                }
+                if receiver.is_none() {
+                    match mdns.browse(SERVICE_TYPE) {
+                        Ok(r) => receiver = Some(r),
+                        Err(e) => log::warn!("Could not browse mDNS services, retrying later: {}", CatReminderError::from(e))
+                    }
+                }
+            }
+            let Some(browse) = &receiver else {
+                std::thread::sleep(POLL_DELAY);
+                continue;
+            };
+            let event = match browse.recv_timeout(POLL_DELAY) {
+                Ok(event) => event,
+                Err(_) if browse.is_disconnected() => {
+                    log::warn!("The mDNS browse stopped, registering again");
+                    registered = false;
+                    receiver = None;
+                    continue;
+                }
+                Err(_) => continue
+            };
+            let sent = match event {
                 ServiceEvent::ServiceResolved(info) => {
                     let is_other_service = !info.get_fullname().starts_with(instance_name.as_str());
-                    if is_other_service {
+                    let is_other_zone = !zoning.hub && info.get_property_val_str("zone") != zoning.zone.as_deref();
+                    if is_other_service && is_other_zone {
+                        log::debug!("Leaving out {} of another zone", info.get_fullname());
+                        Ok(())
+                    } else if is_other_service {
                         log::info!("Resolved a new service: {}", info.get_fullname());
                         let full_name = info.get_fullname().to_string();
                         let addresses: Vec<Ipv4Addr> = info.get_addresses_v4().iter().map(|addr| **addr).collect();
+                        // instance names change on every start, so a cached entry of the same node has another name
+                        cat_reminder_instances.retain(|name, ips| *name == full_name || !ips.iter().any(|ip| addresses.contains(ip)));
                         cat_reminder_instances.insert(full_name, addresses);
-                        network_tx.send(TransportEvent::NodeListUpdated(cat_reminder_instances.clone())).expect("Failed to send updated cat reminder instances");
+                        save_peers(&peer_cache, &cat_reminder_instances);
+                        peer_count.store(cat_reminder_instances.len(), Ordering::Relaxed);
+                        network_tx.send(TransportEvent::NodeListUpdated(cat_reminder_instances.clone()))
+                    } else {
+                        Ok(())
                     }
                 }
                 ServiceEvent::ServiceRemoved(removed_service_type, full_name) => {
                     if removed_service_type == SERVICE_TYPE {
                         log::info!("Removed service on: {}", full_name);
                         cat_reminder_instances.remove(&full_name);
-                        network_tx.send(TransportEvent::NodeListUpdated(cat_reminder_instances.clone())).expect("Failed to send updated cat reminder instances");
+                        save_peers(&peer_cache, &cat_reminder_instances);
+                        peer_count.store(cat_reminder_instances.len(), Ordering::Relaxed);
+                        network_tx.send(TransportEvent::NodeListUpdated(cat_reminder_instances.clone()))
+                    } else {
+                        Ok(())
                     }
                 }
-                _ => {
-                    if shutdown_flag.load(Ordering::Relaxed) {
-                        break;
-                    }
-
-                }
+                _ => Ok(())
+            };
+            if sent.is_err() {
+                log::error!("{}", CatReminderError::ChannelClosed("transport"));
+                break;
             }
         }
-        mdns.unregister(&service_fullname).unwrap();
+        if registered {
+            let _ = mdns.unregister(&service_fullname);
+        }
         let _ = mdns.shutdown();
-    });
+    }).expect("Could not start discovery thread")
+}
+
+pub(crate) fn load_peers(path: &Path) -> HashMap<String, Vec<Ipv4Addr>> {
+    if !path.exists() {
+        return HashMap::new();
+    }
+    fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|str| serde_json::from_str(&str).map_err(|e| e.to_string()))
+        .unwrap_or_else(|err| {
+            log::warn!("Error reading peer cache: {}", err);
+            HashMap::new()
+        })
+}
+
+fn save_peers(path: &Path, peers: &HashMap<String, Vec<Ipv4Addr>>) {
+    let result = serde_json::to_vec_pretty(peers)
+        .map_err(|e| e.into())
+        .and_then(|contents| crate::state::write_atomically(path, &contents));
+    if let Err(e) = result {
+        log::warn!("Could not write peer cache to {}: {}", path.display(), e);
+    }
 }
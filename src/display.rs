@@ -0,0 +1,63 @@
+use crate::reminder::{LEDStripState, ReminderStatus};
+
+/// What the displays show, as computed by the reminder loop.
+pub struct DisplayState {
+    pub status: ReminderStatus,
+    /// State of the node's own litter box, which hubs do not necessarily show
+    pub own_state: LEDStripState,
+    /// Number of other nodes currently known on the network
+    pub peer_count: usize,
+    /// Displays that light up the room stay dark during the night and while the reminder is snoozed
+    pub is_dark: bool,
+    /// Displays that light up the room are dimmed while the SoC is too hot
+    pub is_dimmed: bool,
+    /// Brightness of displays that light up the room, relative to their full brightness, following the ambient light
+    pub brightness: f64,
+    /// Number of snooze steps being selected with the rotary encoder, if a selection is in progress
+    pub snooze_selection: Option<usize>,
+    /// Whether a reset waits for its confirmation by a second press
+    pub is_reset_armed: bool,
+    /// Whether the bag of litter is about to run out
+    pub is_supply_low: bool,
+    /// Whether displays that blink are lit in the current frame of the blink pattern, always set while it is solid
+    pub blink_on: bool,
+    /// Offset along the strip of the LEDs lit by a chasing pattern, if the pattern chases
+    pub chase: Option<usize>,
+    /// Whether a subsystem keeps failing, e.g. the network
+    pub is_failing: bool,
+    /// Progress of the time since the last cleaning toward the next state, between 0 and 1
+    pub progress: f64
+}
+
+/// An output showing the reminder state. The reminder loop renders all configured displays about once per second,
+/// implementations are expected to only touch the hardware when what they show changes.
+pub trait Display {
+    fn name(&self) -> &str;
+
+    /// Shows the state.
+    fn render(&mut self, state: &DisplayState);
+
+    /// Plays a short animation on startup, ending with the minor version, so that the version running can be told
+    /// from across the room, e.g. after an update.
+    fn start(&mut self) {}
+
+    /// Briefly acknowledges a cleaning that was detected automatically.
+    fn confirm(&mut self) {}
+
+    /// Briefly replays the states reached during the night, in order, before the current state is shown again.
+    fn catch_up(&mut self, _states: &[LEDStripState]) {}
+
+    /// Briefly pulses in the RGB color, as a reminder of something that is due besides the litter box.
+    fn pulse(&mut self, _color: [u8; 3]) {}
+
+    /// Briefly shows that a state was received from another node.
+    fn synced(&mut self) {}
+
+    /// Whether the display keeps failing to show the state, despite retrying.
+    fn is_failing(&self) -> bool {
+        false
+    }
+
+    /// Turns the display off, called on shutdown.
+    fn clear(&mut self) {}
+}
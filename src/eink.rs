@@ -0,0 +1,138 @@
+use std::sync::mpsc::{self, Sender};
+
+use chrono::{DateTime, Utc};
+use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Baseline, Text};
+use epd_waveshare::color::TriColor;
+use epd_waveshare::epd2in13bc::{Display2in13bc, Epd2in13bc};
+use epd_waveshare::graphics::DisplayRotation;
+use epd_waveshare::prelude::*;
+use linux_embedded_hal::gpio_cdev::{Chip, LineRequestFlags};
+use linux_embedded_hal::spidev::{SpiModeFlags, SpidevOptions};
+use linux_embedded_hal::{CdevPin, Delay, SpidevDevice};
+use serde::Deserialize;
+
+use crate::config::schedule;
+use crate::display::{Display, DisplayState};
+use crate::reminder::{LEDStripState, ReminderStatus};
+
+const GAUGE_SEGMENT_WIDTH: u32 = 40;
+
+/// Pins of a Waveshare 2.13" three-color e-Paper HAT (B), with the defaults of the HAT.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct EinkConfig {
+    pub spi_device: String,
+    pub gpio_chip: String,
+    pub busy_pin: u32,
+    pub dc_pin: u32,
+    pub rst_pin: u32
+}
+
+impl Default for EinkConfig {
+    fn default() -> Self {
+        EinkConfig {
+            spi_device: "/dev/spidev0.0".to_string(),
+            gpio_chip: "/dev/gpiochip0".to_string(),
+            busy_pin: 24,
+            dc_pin: 25,
+            rst_pin: 17
+        }
+    }
+}
+
+type Epd = Epd2in13bc<SpidevDevice, CdevPin, CdevPin, CdevPin, Delay>;
+
+/// A Waveshare e-ink display, showing the time of the last cleaning and a gauge of the urgency.
+/// A full refresh takes several seconds and e-ink panels only support a limited number of them,
+/// which is why the display is only refreshed when the state changes, from a thread of its own.
+pub struct EinkDisplay {
+    tx: Sender<ReminderStatus>,
+    shown: Option<(LEDStripState, DateTime<Utc>)>
+}
+
+impl EinkDisplay {
+    pub fn new(config: &EinkConfig) -> Self {
+        let (mut epd, mut spi) = init(config);
+        let (tx, rx) = mpsc::channel::<ReminderStatus>();
+        std::thread::spawn(move || {
+            let mut delay = Delay;
+            // the thread stops when the display is dropped
+            for status in rx {
+                let display = draw(&status);
+                let result = epd.wake_up(&mut spi, &mut delay)
+                    .and_then(|_| epd.update_color_frame(&mut spi, &mut delay, display.bw_buffer(), display.chromatic_buffer()))
+                    .and_then(|_| epd.display_frame(&mut spi, &mut delay))
+                    .and_then(|_| epd.sleep(&mut spi, &mut delay));
+                if let Err(e) = result {
+                    log::error!("Could not refresh the e-ink display: {:?}", e);
+                }
+            }
+        });
+        EinkDisplay { tx, shown: None }
+    }
+}
+
+impl Display for EinkDisplay {
+    fn name(&self) -> &str {
+        "eink"
+    }
+
+    fn render(&mut self, state: &DisplayState) {
+        let current = (state.status.state, state.status.last_cleaning_time);
+        if self.shown != Some(current) {
+            self.shown = Some(current);
+            let _ = self.tx.send(state.status.clone());
+        }
+    }
+}
+
+/// Opens the SPI device and the GPIO lines of the display.
+///
+/// # Panics
+///
+/// Panics if the display cannot be initialized.
+fn init(config: &EinkConfig) -> (Epd, SpidevDevice) {
+    let mut spi = SpidevDevice::open(&config.spi_device).expect("Could not open SPI device");
+    spi.configure(&SpidevOptions::new().bits_per_word(8).max_speed_hz(4_000_000).mode(SpiModeFlags::SPI_MODE_0).build())
+        .expect("Could not configure SPI device");
+
+    let mut chip = Chip::new(&config.gpio_chip).expect("Could not open GPIO chip");
+    let mut pin = |line: u32, flags: LineRequestFlags| {
+        let handle = chip.get_line(line).and_then(|l| l.request(flags, 0, "cat-reminder-eink")).expect("Could not request GPIO line");
+        CdevPin::new(handle).expect("Could not create GPIO pin")
+    };
+    let busy = pin(config.busy_pin, LineRequestFlags::INPUT);
+    let dc = pin(config.dc_pin, LineRequestFlags::OUTPUT);
+    let rst = pin(config.rst_pin, LineRequestFlags::OUTPUT);
+
+    let epd = Epd2in13bc::new(&mut spi, busy, dc, rst, &mut Delay, None).expect("Could not initialize e-ink display");
+    (epd, spi)
+}
+
+fn draw(status: &ReminderStatus) -> Display2in13bc {
+    let mut display = Display2in13bc::default();
+    display.set_rotation(DisplayRotation::Rotate90);
+    // drawing into the buffers cannot fail
+    let _ = display.clear(TriColor::White);
+
+    let large = MonoTextStyle::new(&FONT_10X20, TriColor::Black);
+    let small = MonoTextStyle::new(&FONT_6X10, TriColor::Black);
+    let cleaned_at = status.last_cleaning_time.with_timezone(&schedule().timezone).format("%a %H:%M").to_string();
+    let _ = Text::with_baseline("Last cleaned", Point::new(4, 4), small, Baseline::Top).draw(&mut display);
+    let _ = Text::with_baseline(&cleaned_at, Point::new(4, 16), large, Baseline::Top).draw(&mut display);
+    let due_at = status.due_time.with_timezone(&schedule().timezone).format("Due %a %H:%M").to_string();
+    let _ = Text::with_baseline(&due_at, Point::new(4, 40), small, Baseline::Top).draw(&mut display);
+
+    // one segment per state, filled up to the current one, overdue states in red
+    for (i, state) in LEDStripState::ALL.iter().enumerate() {
+        let color = if *state >= LEDStripState::Red { TriColor::Chromatic } else { TriColor::Black };
+        let segment = Rectangle::new(Point::new(4 + i as i32 * (GAUGE_SEGMENT_WIDTH as i32 + 2), 60), Size::new(GAUGE_SEGMENT_WIDTH, 30));
+        let style = if *state <= status.state { PrimitiveStyle::with_fill(color) } else { PrimitiveStyle::with_stroke(color, 2) };
+        let _ = segment.into_styled(style).draw(&mut display);
+    }
+    display
+}
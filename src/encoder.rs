@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::gpio::{Chip, Options};
+use crate::reminder::ReminderEvent;
+
+const POLL_DELAY: Duration = Duration::from_millis(1);
+/// Time after which an unconfirmed selection is abandoned
+const SELECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A rotary encoder with a push switch, such as the KY-040, used to snooze the reminder.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct EncoderConfig {
+    pub clk_pin: u32,
+    pub dt_pin: u32,
+    pub sw_pin: u32,
+    /// Snooze duration per step, one step being shown as one lit LED
+    pub step_minutes: i64,
+    pub max_steps: usize
+}
+
+impl Default for EncoderConfig {
+    fn default() -> Self {
+        EncoderConfig { clk_pin: 12, dt_pin: 19, sw_pin: 7, step_minutes: 30, max_steps: 10 }
+    }
+}
+
+/// Runs the encoder thread. Turning the knob selects a number of snooze steps, which the strip shows as lit LEDs,
+/// and pushing it snoozes the reminder for the selected duration. Selecting zero steps ends a running snooze.
+///
+/// # Panics
+///
+/// Panics if the GPIO lines of the encoder cannot be requested.
+pub fn run(config: EncoderConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let chip = Chip::new("gpiochip0").expect("Cannot open GPIO");
+    let inputs = chip.request_lines(Options::input([config.clk_pin, config.dt_pin, config.sw_pin]).consumer("cat-reminder-encoder"))
+        .expect("Could not request encoder lines");
+    std::thread::spawn(move || {
+        let mut last_clk = true;
+        let mut last_sw = true;
+        let mut steps: usize = 0;
+        let mut selecting_since: Option<Instant> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let [clk, dt, sw] = match inputs.get_values([false; 3]) {
+                Ok(values) => values,
+                Err(e) => {
+                    log::error!("Could not read encoder: {:?}", e);
+                    continue;
+                }
+            };
+
+            let mut event = None;
+            // one detent per falling edge of the clock, the data line giving the direction
+            if last_clk && !clk {
+                steps = if dt { (steps + 1).min(config.max_steps) } else { steps.saturating_sub(1) };
+                selecting_since = Some(Instant::now());
+                event = Some(ReminderEvent::SnoozeSelecting(Some(steps)));
+            } else if last_sw && !sw && selecting_since.is_some() {
+                log::info!("Snoozing for {} minutes", steps as i64 * config.step_minutes);
+                selecting_since = None;
+                event = Some(ReminderEvent::Snooze(chrono::Duration::minutes(steps as i64 * config.step_minutes), None));
+                steps = 0;
+            } else if selecting_since.is_some_and(|since| since.elapsed() > SELECTION_TIMEOUT) {
+                selecting_since = None;
+                steps = 0;
+                event = Some(ReminderEvent::SnoozeSelecting(None));
+            }
+            last_clk = clk;
+            last_sw = sw;
+
+            if event.is_some_and(|e| reminder_tx.send(e).is_err()) {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,50 @@
+use std::error::Error;
+use std::fmt;
+
+/// Failures that the daemon recovers from instead of panicking:
+/// - LED renders are retried, and the next refresh tries again if they keep failing
+/// - network packets that cannot be decoded are dropped
+/// - the mDNS service is registered again until it succeeds
+/// - GPIO reads are skipped until the next poll, and inputs whose lines cannot be requested are left out
+/// - the discovery and the transport stop when they cannot start, and the supervisor starts them again
+/// - events for a module that stopped are logged and dropped
+#[derive(Debug)]
+pub enum CatReminderError {
+    Gpio(std::io::Error),
+    Led(String),
+    Protocol(bincode::Error),
+    /// A packet that is not a valid frame of the wire protocol
+    Frame(String),
+    Discovery(mdns_sd::Error),
+    /// The module at the other end of a channel stopped
+    ChannelClosed(&'static str)
+}
+
+pub type Result<T> = std::result::Result<T, CatReminderError>;
+
+impl fmt::Display for CatReminderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatReminderError::Gpio(e) => write!(f, "GPIO error: {}", e),
+            CatReminderError::Led(e) => write!(f, "LED strip error: {}", e),
+            CatReminderError::Protocol(e) => write!(f, "Invalid message: {}", e),
+            CatReminderError::Frame(e) => write!(f, "Invalid frame: {}", e),
+            CatReminderError::Discovery(e) => write!(f, "mDNS error: {}", e),
+            CatReminderError::ChannelClosed(module) => write!(f, "The {} module stopped", module)
+        }
+    }
+}
+
+impl Error for CatReminderError {}
+
+impl From<bincode::Error> for CatReminderError {
+    fn from(e: bincode::Error) -> Self {
+        CatReminderError::Protocol(e)
+    }
+}
+
+impl From<mdns_sd::Error> for CatReminderError {
+    fn from(e: mdns_sd::Error) -> Self {
+        CatReminderError::Discovery(e)
+    }
+}
@@ -0,0 +1,64 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use chrono::{NaiveDate, Utc};
+
+use crate::cli::ExportFormat;
+use crate::config::schedule;
+use crate::history::{self, HistoryEntry, HistoryEvent};
+
+/// Writes the history entries since the given local date to the output file, or stdout if there is none.
+pub fn run(format: ExportFormat, since: Option<NaiveDate>, output: Option<PathBuf>) -> io::Result<()> {
+    let since_time = since
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .and_then(|time| time.and_local_timezone(schedule().timezone).earliest())
+        .map(|time| time.with_timezone(&Utc));
+    let entries: Vec<HistoryEntry> = match since_time {
+        Some(time) => history::since(time)?,
+        None => history::load()?
+    };
+
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock())
+    };
+    match format {
+        ExportFormat::Csv => write_csv(&mut writer, &entries)?,
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, &entries)?;
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()
+}
+
+fn write_csv(writer: &mut dyn Write, entries: &[HistoryEntry]) -> io::Result<()> {
+    writeln!(writer, "time,event,state,by,source")?;
+    for entry in entries {
+        let (event, state) = match &entry.event {
+            HistoryEvent::Cleaned => ("cleaned", String::new()),
+            HistoryEvent::StateChanged(state) => ("state_changed", format!("{:?}", state)),
+            HistoryEvent::CatVisited => ("cat_visited", String::new()),
+            HistoryEvent::Weighed(grams) => ("weighed", format!("{:.0}", grams)),
+            HistoryEvent::ClimateMeasured(climate) => ("climate_measured", format!("{:.1}C {:.0}%", climate.temperature, climate.humidity)),
+            HistoryEvent::Snoozed(until) => ("snoozed", until.to_rfc3339()),
+            HistoryEvent::Paused => ("paused", String::new()),
+            HistoryEvent::Resumed => ("resumed", String::new()),
+            HistoryEvent::LitterReplaced => ("litter_replaced", String::new()),
+            HistoryEvent::BagOpened => ("bag_opened", String::new()),
+            HistoryEvent::CareDone(name) => ("care_done", csv_field(name))
+        };
+        let source = entry.source.as_ref().map(|s| s.to_string()).unwrap_or_default();
+        writeln!(writer, "{},{},{},{},{}", entry.time.to_rfc3339(), event, state, csv_field(entry.by.as_deref().unwrap_or("")), csv_field(&source))?;
+    }
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,82 @@
+//! Points and streaks for the household members, so that cleaning on time is rewarded rather than only being nagged
+//! about. Everything is derived from the attributed cleanings in the history.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::history::{HistoryEntry, HistoryEvent};
+use crate::reminder::LEDStripState;
+
+/// Gamification is enabled by adding the section.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct GamificationConfig {
+    /// Points for a cleaning before the litter box turns red
+    pub on_time_points: u32,
+    /// Points for a cleaning once it is red or blinking
+    pub late_points: u32,
+    /// Extra points for every previous on-time cleaning of the current streak
+    pub streak_bonus: u32,
+    pub max_streak_bonus: u32,
+    /// Only the points of the cleanings of the last days count, all of them if not set
+    pub period_days: Option<i64>
+}
+
+impl Default for GamificationConfig {
+    fn default() -> Self {
+        GamificationConfig { on_time_points: 10, late_points: 2, streak_bonus: 2, max_streak_bonus: 10, period_days: None }
+    }
+}
+
+/// The points and streaks of a household member.
+#[derive(Serialize, Clone, Debug)]
+pub struct Standing {
+    pub name: String,
+    pub points: u32,
+    pub cleanings: usize,
+    /// On-time cleanings in a row, up to the last cleaning of the member
+    pub streak: usize,
+    pub best_streak: usize
+}
+
+/// The standings of the members who cleaned, best first. Cleanings of unknown members are not counted.
+pub fn leaderboard(config: &GamificationConfig, entries: &[HistoryEntry], now: DateTime<Utc>) -> Vec<Standing> {
+    let since = config.period_days.map(|days| now - Duration::days(days));
+    let mut standings: HashMap<String, Standing> = HashMap::new();
+    let mut previous: Option<DateTime<Utc>> = None;
+    for cleaning in entries.iter().filter(|e| e.event == HistoryEvent::Cleaned) {
+        let elapsed = previous.map_or(Duration::zero(), |previous| cleaning.time - previous);
+        previous = Some(cleaning.time);
+        let Some(name) = &cleaning.by else { continue };
+        let standing = standings.entry(name.clone())
+            .or_insert_with(|| Standing { name: name.clone(), points: 0, cleanings: 0, streak: 0, best_streak: 0 });
+        let on_time = LEDStripState::state_from_duration(&elapsed) < LEDStripState::Red;
+        let points = if on_time {
+            config.on_time_points + (config.streak_bonus * standing.streak as u32).min(config.max_streak_bonus)
+        } else {
+            config.late_points
+        };
+        standing.streak = if on_time { standing.streak + 1 } else { 0 };
+        standing.best_streak = standing.best_streak.max(standing.streak);
+        if since.map_or(true, |since| cleaning.time >= since) {
+            standing.points += points;
+            standing.cleanings += 1;
+        }
+    }
+    let mut standings: Vec<Standing> = standings.into_values().collect();
+    standings.sort_by(|a, b| b.points.cmp(&a.points).then(b.streak.cmp(&a.streak)).then(a.name.cmp(&b.name)));
+    standings
+}
+
+/// The leaderboard as text, one member per line.
+pub fn format(standings: &[Standing]) -> String {
+    if standings.is_empty() {
+        return "No attributed cleanings yet.".to_string();
+    }
+    standings.iter().enumerate()
+        .map(|(i, s)| format!("{}. {}: {} points, {} cleanings, streak {} (best {})", i + 1, s.name, s.points, s.cleanings, s.streak, s.best_streak))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,98 @@
+//! GPIO lines, through gpiod on the Raspberry Pi and through stubs elsewhere, where inputs are never active and
+//! outputs are only logged.
+
+#[cfg(hardware)]
+pub use gpiod::{Active, Bias, Chip, Input, Lines, Options, Output};
+
+#[cfg(not(hardware))]
+pub use self::stub::{Active, Bias, Chip, Input, Lines, Options, Output};
+
+#[cfg(not(hardware))]
+mod stub {
+    use std::fmt::Debug;
+    use std::io::Result;
+    use std::marker::PhantomData;
+    use std::path::Path;
+
+    pub struct Input;
+    pub struct Output;
+
+    pub enum Active {
+        Low,
+        High
+    }
+
+    pub enum Bias {
+        Disable,
+        PullUp,
+        PullDown
+    }
+
+    pub struct Chip;
+
+    impl Chip {
+        pub fn new(_path: impl AsRef<Path>) -> Result<Chip> {
+            Ok(Chip)
+        }
+
+        pub fn request_lines<Direction>(&self, options: Options<Direction>) -> Result<Lines<Direction>> {
+            log::info!("Simulating GPIO lines {:?} for {}", options.lines, options.consumer);
+            Ok(Lines { lines: options.lines, direction: PhantomData })
+        }
+    }
+
+    pub struct Options<Direction> {
+        lines: Vec<u32>,
+        consumer: String,
+        direction: PhantomData<Direction>
+    }
+
+    impl Options<Input> {
+        pub fn input(lines: impl AsRef<[u32]>) -> Self {
+            Options { lines: lines.as_ref().to_vec(), consumer: String::new(), direction: PhantomData }
+        }
+
+        pub fn active(self, _active: Active) -> Self {
+            self
+        }
+
+        pub fn bias(self, _bias: Bias) -> Self {
+            self
+        }
+    }
+
+    impl Options<Output> {
+        pub fn output(lines: impl AsRef<[u32]>) -> Self {
+            Options { lines: lines.as_ref().to_vec(), consumer: String::new(), direction: PhantomData }
+        }
+
+        pub fn values(self, _values: impl AsRef<[bool]>) -> Self {
+            self
+        }
+    }
+
+    impl<Direction> Options<Direction> {
+        pub fn consumer(mut self, consumer: &str) -> Self {
+            self.consumer = consumer.to_string();
+            self
+        }
+    }
+
+    pub struct Lines<Direction> {
+        lines: Vec<u32>,
+        direction: PhantomData<Direction>
+    }
+
+    impl Lines<Input> {
+        pub fn get_values<T>(&self, values: T) -> Result<T> {
+            Ok(values)
+        }
+    }
+
+    impl Lines<Output> {
+        pub fn set_values<T: Debug>(&self, values: T) -> Result<()> {
+            log::debug!("Setting simulated GPIO lines {:?} to {:?}", self.lines, values);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::discovery;
+use crate::state;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Health {
+    Ok,
+    /// Works, but something deserves a look
+    Warning,
+    Failed
+}
+
+#[derive(Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub health: Health,
+    pub detail: String
+}
+
+/// Checks what the reminder needs from the node, both from the `health` command and from the running reminder.
+pub struct HealthProbe {
+    pub strip: bool,
+    pub state_file: PathBuf,
+    /// Number of peers known by the running reminder, the peer cache being used otherwise
+    pub peer_count: Option<Arc<AtomicUsize>>
+}
+
+impl HealthProbe {
+    pub fn new(config: &Config, peer_count: Option<Arc<AtomicUsize>>) -> Self {
+        let state_file = config.state_file.clone().unwrap_or_else(|| PathBuf::from(state::STATE_FILE_PATH));
+        HealthProbe { strip: config.displays.strip, state_file, peer_count }
+    }
+
+    pub fn run(&self) -> Vec<Check> {
+        vec![self.led(), gpio(), self.state_file(), clock(), self.peers()]
+    }
+
+    fn led(&self) -> Check {
+        let (health, detail) = if !self.strip {
+            (Health::Ok, "disabled".to_string())
+        } else if cfg!(not(hardware)) {
+            (Health::Ok, "simulated".to_string())
+        } else {
+            // the strip driver maps the PWM and DMA registers through /dev/mem
+            match OpenOptions::new().read(true).write(true).open("/dev/mem") {
+                Ok(_) => (Health::Ok, "/dev/mem accessible".to_string()),
+                Err(e) => (Health::Failed, format!("/dev/mem not accessible: {}", e))
+            }
+        };
+        Check { name: "led", health, detail }
+    }
+
+    fn state_file(&self) -> Check {
+        let probe_path = self.state_file.with_file_name(".health");
+        let result = fs::write(&probe_path, b"").and_then(|_| fs::remove_file(&probe_path));
+        let (health, detail) = match result {
+            Ok(()) => (Health::Ok, format!("{} writable", directory(&self.state_file).display())),
+            Err(e) => (Health::Failed, format!("{} not writable: {}", directory(&self.state_file).display(), e))
+        };
+        Check { name: "state_file", health, detail }
+    }
+
+    fn peers(&self) -> Check {
+        let (count, source) = match &self.peer_count {
+            Some(peer_count) => (peer_count.load(Ordering::Relaxed), "connected"),
+            None => {
+                let peer_cache = self.state_file.with_file_name(discovery::PEER_CACHE_FILE_NAME);
+                let count = fs::read_to_string(peer_cache).ok()
+                    .and_then(|str| serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&str).ok())
+                    .map(|peers| peers.len())
+                    .unwrap_or(0);
+                (count, "known")
+            }
+        };
+        // a single node is a valid setup, but usually not the intended one
+        let health = if count == 0 { Health::Warning } else { Health::Ok };
+        Check { name: "peers", health, detail: format!("{} {} peers", count, source) }
+    }
+}
+
+fn gpio() -> Check {
+    let (health, detail) = if cfg!(not(hardware)) {
+        (Health::Ok, "simulated".to_string())
+    } else {
+        match File::open("/dev/gpiochip0") {
+            Ok(_) => (Health::Ok, "/dev/gpiochip0 accessible".to_string()),
+            Err(e) => (Health::Failed, format!("/dev/gpiochip0 not accessible: {}", e))
+        }
+    };
+    Check { name: "gpio", health, detail }
+}
+
+/// Whether the clock is synchronized, as reported by systemd, since the Pi has no real-time clock.
+fn clock() -> Check {
+    let output = Command::new("timedatectl").args(["show", "--property=NTPSynchronized", "--value"]).output();
+    let (health, detail) = match output {
+        Ok(output) if String::from_utf8_lossy(&output.stdout).trim() == "yes" => (Health::Ok, "synchronized".to_string()),
+        Ok(_) => (Health::Failed, "not synchronized".to_string()),
+        Err(e) => (Health::Warning, format!("could not run timedatectl: {}", e))
+    };
+    Check { name: "clock", health, detail }
+}
+
+fn directory(path: &Path) -> &Path {
+    path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."))
+}
+
+/// The overall health, which is the worst of all checks.
+pub fn overall(checks: &[Check]) -> Health {
+    checks.iter().map(|c| c.health).max().unwrap_or(Health::Ok)
+}
+
+/// Prints the checks for the `health` command, returning whether none of them failed.
+pub fn report() -> bool {
+    let checks = HealthProbe::new(&crate::config::load(), None).run();
+    for check in &checks {
+        println!("{:<12} {:<8} {}", check.name, format!("{:?}", check.health).to_lowercase(), check.detail);
+    }
+    overall(&checks) != Health::Failed
+}
@@ -0,0 +1,44 @@
+//! The history kept in a file, one JSON document per line, which every query reads in full.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use super::{load_from, to_lines, HistoryEntry, HistoryEvent};
+
+/// Appends the entries with a single write.
+pub fn append_all(path: &Path, entries: &[HistoryEntry]) -> io::Result<()> {
+    let content = to_lines(entries)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(content.as_bytes())
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<HistoryEntry>> {
+    load_from(path)
+}
+
+pub fn since(path: &Path, time: DateTime<Utc>) -> io::Result<Vec<HistoryEntry>> {
+    Ok(load_from(path)?.into_iter().filter(|e| e.time >= time).collect())
+}
+
+pub fn cleanings(path: &Path) -> io::Result<Vec<HistoryEntry>> {
+    Ok(load_from(path)?.into_iter().filter(|e| e.event == HistoryEvent::Cleaned).collect())
+}
+
+/// Drops the entries older than the cutoff, except for cleanings, and returns how many were dropped.
+pub fn prune(path: &Path, cutoff: DateTime<Utc>) -> io::Result<usize> {
+    let entries = load_from(path)?;
+    let count = entries.len();
+    let kept: Vec<HistoryEntry> = entries.into_iter().filter(|e| e.event == HistoryEvent::Cleaned || e.time >= cutoff).collect();
+    if kept.len() == count {
+        return Ok(0);
+    }
+    replace_all(path, &kept)?;
+    Ok(count - kept.len())
+}
+
+pub fn replace_all(path: &Path, entries: &[HistoryEntry]) -> io::Result<()> {
+    crate::state::write_atomically(path, to_lines(entries)?.as_bytes())
+}
@@ -0,0 +1,220 @@
+//! The history of the node: cleanings, sensor readings and state transitions, kept in a JSON-lines file, or in an
+//! SQLite database with the `sqlite` feature.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::reminder::{LEDStripState, ResetSource};
+use crate::sensor::Climate;
+
+#[cfg(not(feature = "sqlite"))]
+mod file;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(not(feature = "sqlite"))]
+use file as store;
+#[cfg(feature = "sqlite")]
+use sqlite as store;
+
+/// Name of the history file, kept next to the state file
+#[cfg(not(feature = "sqlite"))]
+pub const HISTORY_FILE_NAME: &str = "history";
+#[cfg(feature = "sqlite")]
+pub const HISTORY_FILE_NAME: &str = "history.db";
+/// Name of the history file in the JSON-lines format, which the database takes over
+#[cfg(feature = "sqlite")]
+const LINES_FILE_NAME: &str = "history";
+/// Where the history used to be kept, relative to the working directory
+const LEGACY_HISTORY_FILE_PATH: &str = "cat_reminder_history";
+
+/// How long history entries are kept. Cleanings are kept forever, since the rotation and the calendar rely on them.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Days after which sensor readings and state changes are dropped, kept forever if not set
+    pub retention_days: Option<u32>
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum HistoryEvent {
+    Cleaned,
+    StateChanged(LEDStripState),
+    /// A cat used the litter box
+    CatVisited,
+    /// The weight of the litter box settled, in grams
+    Weighed(f64),
+    ClimateMeasured(Climate),
+    /// The reminder was snoozed until the given time
+    Snoozed(DateTime<Utc>),
+    Paused,
+    /// A snooze or pause was ended early
+    Resumed,
+    /// The litter was replaced entirely, which is also recorded as a cleaning
+    LitterReplaced,
+    /// A new bag of litter was opened
+    BagOpened,
+    /// The care reminder with the given name was done
+    CareDone(String)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    pub time: DateTime<Utc>,
+    pub event: HistoryEvent,
+    /// Who triggered the event, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
+    /// What triggered a cleaning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ResetSource>,
+    /// Whose turn a cleaning was, if the chores rotate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>
+}
+
+impl HistoryEntry {
+    pub fn new(time: DateTime<Utc>, event: HistoryEvent) -> Self {
+        HistoryEntry { time, event, by: None, source: None, assignee: None }
+    }
+}
+
+/// The history file of the node, next to its state file.
+pub fn path() -> PathBuf {
+    path_for(config::state_file())
+}
+
+/// The history file of a node with the given state file.
+pub fn path_for(state_file: &Path) -> PathBuf {
+    state_file.with_file_name(HISTORY_FILE_NAME)
+}
+
+/// Takes over the history from the working directory, where earlier versions kept it, and, with the `sqlite` feature,
+/// from the JSON-lines file next to the state file. The files taken over are removed.
+pub fn migrate() -> std::io::Result<()> {
+    let path = path();
+    if path.exists() {
+        return Ok(());
+    }
+    #[cfg(feature = "sqlite")]
+    let lines_path = config::state_file().with_file_name(LINES_FILE_NAME);
+    #[cfg(feature = "sqlite")]
+    let candidates = [lines_path.as_path(), Path::new(LEGACY_HISTORY_FILE_PATH)];
+    #[cfg(not(feature = "sqlite"))]
+    let candidates = [Path::new(LEGACY_HISTORY_FILE_PATH)];
+    let Some(legacy_path) = candidates.into_iter().find(|candidate| candidate.exists()) else {
+        return Ok(());
+    };
+    log::info!("Moving the history from {} to {}", legacy_path.display(), path.display());
+    // the state directory usually is on another filesystem than the working directory, which renaming does not cross
+    store::replace_all(&path, &load_from(legacy_path)?)?;
+    fs::remove_file(legacy_path)
+}
+
+/// Appends entries to the history with a single write.
+pub fn append_all(entries: &[HistoryEntry]) -> std::io::Result<()> {
+    store::append_all(&path(), entries)
+}
+
+/// Replaces the history at the given path, e.g. with the one of a backup.
+pub fn replace_all(path: &Path, entries: &[HistoryEntry]) -> std::io::Result<()> {
+    store::replace_all(path, entries)
+}
+
+/// The entries as JSON lines, the format of the history file.
+pub fn to_lines(entries: &[HistoryEntry]) -> std::io::Result<String> {
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&serde_json::to_string(entry).map_err(|e| Error::new(InvalidData, e))?);
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// Loads all history entries, skipping the ones that cannot be parsed.
+pub fn load() -> std::io::Result<Vec<HistoryEntry>> {
+    store::load(&path())
+}
+
+/// Loads all history entries of the given JSON-lines history file, e.g. one attached to a bug report, skipping lines
+/// that cannot be parsed.
+pub fn load_from(path: &Path) -> std::io::Result<Vec<HistoryEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(parse_lines(&fs::read_to_string(path)?))
+}
+
+/// The entries of JSON lines, skipping lines that cannot be parsed.
+pub fn parse_lines(content: &str) -> Vec<HistoryEntry> {
+    content.lines().filter_map(|line| {
+        serde_json::from_str(line).map_err(|e| log::warn!("Skipping invalid history entry: {}", e)).ok()
+    }).collect()
+}
+
+/// Loads the history entries from the given time on.
+pub fn since(time: DateTime<Utc>) -> std::io::Result<Vec<HistoryEntry>> {
+    store::since(&path(), time)
+}
+
+/// Loads all cleanings, oldest first.
+pub fn cleanings() -> std::io::Result<Vec<HistoryEntry>> {
+    store::cleanings(&path())
+}
+
+/// The number of cleanings of each member, e.g. `Anna 3, Paul 2`, if any of them is attributed.
+pub fn count_by_member<'a>(cleanings: impl IntoIterator<Item = &'a HistoryEntry>) -> Option<String> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for cleaning in cleanings {
+        *counts.entry(cleaning.by.as_deref().unwrap_or("unknown")).or_default() += 1;
+    }
+    if counts.keys().all(|name| *name == "unknown") {
+        return None;
+    }
+    Some(counts.iter().map(|(name, count)| format!("{} {}", name, count)).collect::<Vec<_>>().join(", "))
+}
+
+/// The periods between `from` and `to` during which the litter box was overdue, i.e. red or worse.
+pub fn overdue_periods(entries: &[HistoryEntry], from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut periods: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    let mut overdue_since: Option<DateTime<Utc>> = None;
+    for entry in entries.iter().filter(|e| e.time <= to) {
+        let is_overdue = match &entry.event {
+            HistoryEvent::StateChanged(state) => *state >= LEDStripState::Red,
+            HistoryEvent::Cleaned => false,
+            HistoryEvent::CatVisited | HistoryEvent::Weighed(_) | HistoryEvent::ClimateMeasured(_) | HistoryEvent::Snoozed(_)
+            | HistoryEvent::Paused | HistoryEvent::Resumed | HistoryEvent::LitterReplaced | HistoryEvent::BagOpened
+            | HistoryEvent::CareDone(_) => continue
+        };
+        match overdue_since {
+            None if is_overdue => overdue_since = Some(entry.time),
+            Some(start) if !is_overdue => {
+                periods.push((start, entry.time));
+                overdue_since = None;
+            }
+            _ => ()
+        }
+    }
+    if let Some(start) = overdue_since {
+        periods.push((start, to));
+    }
+    periods.into_iter()
+        .filter(|(_, end)| *end > from)
+        .map(|(start, end)| (start.max(from), end))
+        .collect()
+}
+
+/// Drops the entries older than the retention period, except for cleanings, and returns how many were dropped.
+pub fn prune(config: &HistoryConfig, now: DateTime<Utc>) -> std::io::Result<usize> {
+    let Some(retention_days) = config.retention_days else {
+        return Ok(0);
+    };
+    store::prune(&path(), now - chrono::Duration::days(retention_days as i64))
+}
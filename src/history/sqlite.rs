@@ -0,0 +1,86 @@
+//! The history kept in an embedded SQLite database, so that the queries of the calendar, the leaderboard and the
+//! exports only read what they need instead of the whole history. The entries are stored as the same JSON documents as
+//! in the file, along with their time and whether they are cleanings, which the queries select on.
+
+use std::io::{self, Error};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, ToSql};
+
+use super::{HistoryEntry, HistoryEvent};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS history (
+        id INTEGER PRIMARY KEY,
+        time INTEGER NOT NULL,
+        cleaning INTEGER NOT NULL,
+        entry TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS history_time ON history (time);
+    CREATE INDEX IF NOT EXISTS history_cleaning ON history (cleaning, time);";
+
+/// Opens the database, creating it on first use.
+fn open(path: &Path) -> io::Result<Connection> {
+    let connection = Connection::open(path).map_err(Error::other)?;
+    connection.execute_batch(SCHEMA).map_err(Error::other)?;
+    Ok(connection)
+}
+
+fn insert(connection: &Connection, entries: &[HistoryEntry]) -> io::Result<()> {
+    let mut statement = connection.prepare_cached("INSERT INTO history (time, cleaning, entry) VALUES (?1, ?2, ?3)").map_err(Error::other)?;
+    for entry in entries {
+        let json = serde_json::to_string(entry)?;
+        statement.execute(params![entry.time.timestamp_millis(), entry.event == HistoryEvent::Cleaned, json]).map_err(Error::other)?;
+    }
+    Ok(())
+}
+
+/// The entries matching the condition, in the order they were recorded, skipping the ones that cannot be parsed.
+fn select(path: &Path, condition: &str, parameters: &[&dyn ToSql]) -> io::Result<Vec<HistoryEntry>> {
+    let connection = open(path)?;
+    let mut statement = connection.prepare(&format!("SELECT entry FROM history WHERE {} ORDER BY id", condition)).map_err(Error::other)?;
+    let rows = statement.query_map(parameters, |row| row.get::<_, String>(0)).map_err(Error::other)?;
+    let mut entries = Vec::new();
+    for row in rows {
+        match serde_json::from_str(&row.map_err(Error::other)?) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::warn!("Skipping invalid history entry: {}", e)
+        }
+    }
+    Ok(entries)
+}
+
+/// Appends the entries in a single transaction.
+pub fn append_all(path: &Path, entries: &[HistoryEntry]) -> io::Result<()> {
+    let mut connection = open(path)?;
+    let transaction = connection.transaction().map_err(Error::other)?;
+    insert(&transaction, entries)?;
+    transaction.commit().map_err(Error::other)
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<HistoryEntry>> {
+    select(path, "1", &[])
+}
+
+pub fn since(path: &Path, time: DateTime<Utc>) -> io::Result<Vec<HistoryEntry>> {
+    select(path, "time >= ?1", &[&time.timestamp_millis()])
+}
+
+pub fn cleanings(path: &Path) -> io::Result<Vec<HistoryEntry>> {
+    select(path, "cleaning = 1", &[])
+}
+
+/// Drops the entries older than the cutoff, except for cleanings, and returns how many were dropped.
+pub fn prune(path: &Path, cutoff: DateTime<Utc>) -> io::Result<usize> {
+    let connection = open(path)?;
+    connection.execute("DELETE FROM history WHERE cleaning = 0 AND time < ?1", params![cutoff.timestamp_millis()]).map_err(Error::other)
+}
+
+pub fn replace_all(path: &Path, entries: &[HistoryEntry]) -> io::Result<()> {
+    let mut connection = open(path)?;
+    let transaction = connection.transaction().map_err(Error::other)?;
+    transaction.execute("DELETE FROM history", []).map_err(Error::other)?;
+    insert(&transaction, entries)?;
+    transaction.commit().map_err(Error::other)
+}
@@ -0,0 +1,142 @@
+//! Trips of the household, during which the thresholds are relaxed and a cat-sitter gets the notifications about the
+//! litter box instead of the household.
+
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{Days, Duration, NaiveDate};
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct HolidaysConfig {
+    /// Trips, with their first and last day
+    pub trips: Vec<Trip>,
+    /// Calendar file in the iCalendar format, e.g. exported from a shared calendar, every event of which is a trip
+    pub ics: Option<PathBuf>,
+    /// Factor by which the thresholds are stretched during trips, e.g. 2 if the cat-sitter only comes every other day
+    pub threshold_factor: f64,
+    /// Person looking after the cats during trips, notified through the notifiers of this user instead of the household
+    pub sitter: Option<String>
+}
+
+impl Default for HolidaysConfig {
+    fn default() -> Self {
+        HolidaysConfig { trips: Vec::new(), ics: None, threshold_factor: 1.0, sitter: None }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Trip {
+    pub start: NaiveDate,
+    /// Last day of the trip, included
+    pub end: NaiveDate
+}
+
+/// The trips of the configuration and of its calendar, with whether one is under way.
+#[derive(Default)]
+pub struct Holidays {
+    trips: Vec<Trip>,
+    threshold_factor: f64,
+    sitter: Option<String>,
+    on_trip: bool
+}
+
+impl Holidays {
+    /// The configured trips, along with the ones of the calendar file, which is read once.
+    pub fn new(config: HolidaysConfig) -> Self {
+        let mut trips = config.trips;
+        if let Some(path) = &config.ics {
+            match fs::read_to_string(path) {
+                Ok(content) => trips.extend(parse_ics(&content)),
+                Err(e) => log::error!("Could not read the trips from {}: {}", path.display(), e)
+            }
+        }
+        Holidays { trips, threshold_factor: config.threshold_factor, sitter: config.sitter, on_trip: false }
+    }
+
+    pub fn sitter(&self) -> Option<&str> {
+        self.sitter.as_deref()
+    }
+
+    /// Whether the given local date is part of a trip.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.trips.iter().any(|trip| trip.start <= date && date <= trip.end)
+    }
+
+    /// Checks whether a trip is under way on the given local date, returning whether it is if that changed since the
+    /// previous check.
+    pub fn update(&mut self, date: NaiveDate) -> Option<bool> {
+        let on_trip = self.contains(date);
+        (on_trip != self.on_trip).then(|| {
+            self.on_trip = on_trip;
+            on_trip
+        })
+    }
+
+    /// The time since the last cleaning counted toward the urgency, stretched thresholds amounting to less of it
+    /// during a trip.
+    pub fn relaxed(&self, elapsed: Duration) -> Duration {
+        if !self.on_trip || self.threshold_factor <= 1.0 {
+            return elapsed;
+        }
+        Duration::seconds((elapsed.num_seconds() as f64 / self.threshold_factor).round() as i64)
+    }
+
+    /// The time to elapse for the relaxed time to reach the given one, the inverse of [Self::relaxed], as long as the
+    /// trip lasts.
+    pub fn unrelaxed(&self, relaxed: Duration) -> Duration {
+        if !self.on_trip || self.threshold_factor <= 1.0 {
+            return relaxed;
+        }
+        Duration::seconds((relaxed.num_seconds() as f64 * self.threshold_factor).round() as i64)
+    }
+}
+
+/// The trips of an iCalendar document, one per event. The end of an all-day event is the day after its last one, as
+/// the format has it, while events with a time end on the day of their end.
+pub fn parse_ics(content: &str) -> Vec<Trip> {
+    let mut trips = Vec::new();
+    let mut event: Option<(Option<NaiveDate>, Option<NaiveDate>)> = None;
+    for line in unfolded_lines(content).iter().map(|line| line.trim_end()) {
+        match line {
+            "BEGIN:VEVENT" => event = Some((None, None)),
+            "END:VEVENT" => {
+                if let Some((Some(start), end)) = event.take() {
+                    trips.push(Trip { start, end: end.unwrap_or(start).max(start) });
+                }
+            }
+            _ => {
+                let Some((name, value)) = line.split_once(':') else { continue };
+                let Some((start, end)) = event.as_mut() else { continue };
+                // properties may carry parameters, e.g. `DTSTART;VALUE=DATE:20240701`
+                match name.split(';').next() {
+                    Some("DTSTART") => *start = ics_date(value),
+                    Some("DTEND") => {
+                        *end = ics_date(value).map(|date| if value.contains('T') { date } else { date - Days::new(1) });
+                    }
+                    _ => ()
+                }
+            }
+        }
+    }
+    trips
+}
+
+/// The lines of an iCalendar document, with the long ones that were folded onto lines starting with a space or a tab
+/// joined back.
+fn unfolded_lines(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string())
+        }
+    }
+    lines
+}
+
+/// The date of an iCalendar date or date-time, e.g. `20240701` or `20240701T080000Z`.
+fn ics_date(value: &str) -> Option<NaiveDate> {
+    value.get(..8).and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+}
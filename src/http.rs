@@ -0,0 +1,457 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::Cursor;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::sync::mpsc::Sender;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tiny_http::{Header, Method, Request, Response, Server, SslConfig};
+
+use crate::assistant;
+use crate::gamification::{self, GamificationConfig};
+use crate::diagnostics::{self, DiagnosticsProbe};
+use crate::health::{self, Health};
+use crate::history;
+use crate::input;
+use crate::litter::LitterProfile;
+use crate::observation::Observation;
+use crate::pairing;
+use crate::reminder::{ReminderEvent, ReminderStatus, ResetSource, MAX_GUEST_HOURS};
+use crate::setup;
+
+const POLL_DELAY: Duration = Duration::from_millis(500);
+const ICS_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Clone)]
+pub struct HttpConfig {
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// PEM certificate chain, serving HTTPS when set together with the private key
+    pub tls_certificate: Option<String>,
+    pub tls_private_key: Option<String>,
+    /// Token required by the voice assistant endpoints, which are disabled when there is none.
+    /// Passed either as `Authorization: Bearer <token>` header or as `token` query parameter.
+    pub assistant_token: Option<String>,
+    /// Token required by the observation endpoint, which is disabled when there is none, passed like the assistant token
+    pub observation_token: Option<String>,
+    /// Tokens of phones and web UIs, required by the status, reset and pairing endpoints, which are disabled when there
+    /// are none. Passed like the assistant token. The first one is handed over with the pairing QR code.
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>
+}
+
+fn default_port() -> u16 {
+    8080
+}
+
+impl HttpConfig {
+    pub fn scheme(&self) -> &'static str {
+        if self.tls_certificate.is_some() && self.tls_private_key.is_some() { "https" } else { "http" }
+    }
+}
+
+/// A token of the API, with what it allows.
+#[derive(Deserialize, Clone)]
+pub struct ApiToken {
+    /// Who or what uses the token, for the logs
+    pub name: String,
+    pub token: String,
+    pub role: Role,
+    /// Requests allowed per minute, slowing down whatever got hold of the token
+    #[serde(default = "default_max_per_minute")]
+    pub max_per_minute: usize
+}
+
+fn default_max_per_minute() -> usize {
+    30
+}
+
+/// What a token allows, each role allowing what the previous ones do.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Reads the status
+    Viewer,
+    /// Resets the timer, and hands over tokens by pairing
+    Resetter,
+    /// Reads the logs and diagnostics of the node and of its peers
+    Admin
+}
+
+/// Why a request is denied by the [`Authorizer`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Denial {
+    /// None of the presented tokens is known
+    Unauthorized,
+    /// The token does not have the role the endpoint requires
+    Forbidden,
+    /// The token made too many requests in the last minute
+    TooManyRequests
+}
+
+/// Checks the API tokens of the requests, keeping track of the requests made with each token in the last minute.
+pub struct Authorizer {
+    tokens: Vec<ApiToken>,
+    requests: HashMap<String, VecDeque<Instant>>
+}
+
+impl Authorizer {
+    pub fn new(tokens: Vec<ApiToken>) -> Self {
+        Authorizer { tokens, requests: HashMap::new() }
+    }
+
+    /// Returns the first known token among the presented ones if it has the role and is within its rate limit, why the
+    /// request to the given path is denied otherwise.
+    pub fn authorize(&mut self, presented: &[&str], role: Role, path: &str, now: Instant) -> Result<&ApiToken, Denial> {
+        let Some(api_token) = self.tokens.iter().find(|api_token| presented.iter().any(|token| constant_time_eq(token, &api_token.token))) else {
+            return Err(Denial::Unauthorized);
+        };
+        if api_token.role < role {
+            log::warn!("Token of {} used for {}, which it does not allow", api_token.name, path);
+            return Err(Denial::Forbidden);
+        }
+        let requests = self.requests.entry(api_token.token.clone()).or_default();
+        while requests.front().is_some_and(|t| now.duration_since(*t) > RATE_LIMIT_WINDOW) {
+            requests.pop_front();
+        }
+        if requests.len() >= api_token.max_per_minute {
+            log::warn!("Rate limit reached for the token of {}", api_token.name);
+            return Err(Denial::TooManyRequests);
+        }
+        requests.push_back(now);
+        Ok(api_token)
+    }
+
+    /// Returns the token of the request if it has the role and is within its rate limit, the response to send otherwise.
+    fn check(&mut self, request: &Request, role: Role) -> Result<&ApiToken, Response<Cursor<Vec<u8>>>> {
+        let path = request.url().split('?').next().unwrap_or("");
+        self.authorize(&presented_tokens(request), role, path, Instant::now()).map_err(|denial| match denial {
+            Denial::Unauthorized => Response::from_string("Unauthorized").with_status_code(401),
+            Denial::Forbidden => Response::from_string("Forbidden").with_status_code(403),
+            Denial::TooManyRequests => Response::from_string("Too many requests").with_status_code(429).with_header(header("Retry-After", "60"))
+        })
+    }
+}
+
+/// Runs the HTTP server exposing the state of the reminder.
+pub fn run(ip_addr: IpAddr, config: HttpConfig, gamification: Option<GamificationConfig>, probe: DiagnosticsProbe, status: Arc<RwLock<ReminderStatus>>, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let pairing_url = pairing::url(ip_addr, &config);
+    let addr = SocketAddr::new(ip_addr, config.port);
+    let server = match (&config.tls_certificate, &config.tls_private_key) {
+        (Some(certificate), Some(private_key)) => {
+            let ssl_config = SslConfig {
+                certificate: std::fs::read(certificate).expect("Could not read TLS certificate"),
+                private_key: std::fs::read(private_key).expect("Could not read TLS private key")
+            };
+            Server::https(addr, ssl_config)
+        }
+        _ => Server::http(addr)
+    }.unwrap_or_else(|e| panic!("Can not listen at {}: {}", addr, e));
+    log::info!("HTTP server running at {}", addr);
+
+    std::thread::spawn(move || {
+        let mut authorizer = Authorizer::new(config.tokens.clone());
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            match server.recv_timeout(POLL_DELAY) {
+                Ok(Some(request)) => handle(request, &config, &mut authorizer, pairing_url.as_deref(), gamification.as_ref(), &probe, &status, &reminder_tx),
+                Ok(None) => (),
+                Err(e) => log::error!("Failed to receive HTTP request: {:?}", e)
+            }
+        }
+    });
+}
+
+fn handle(mut request: Request, config: &HttpConfig, authorizer: &mut Authorizer, pairing_url: Option<&str>, gamification: Option<&GamificationConfig>,
+          probe: &DiagnosticsProbe, status: &Arc<RwLock<ReminderStatus>>, reminder_tx: &Sender<ReminderEvent>) {
+    let url = request.url().to_string();
+    let path = url.split('?').next().unwrap_or("");
+    let response = match (request.method(), path) {
+        // calendar clients cannot send headers, so they subscribe with the token in the query
+        (Method::Get, "/calendar.ics") => match authorizer.check(&request, Role::Viewer) {
+            Ok(_) => {
+                let current_status = status.read().unwrap().clone();
+                match calendar(&current_status) {
+                    Ok(ics) => Response::from_string(ics).with_header(header("Content-Type", "text/calendar; charset=utf-8")),
+                    Err(e) => {
+                        log::error!("Could not build calendar: {:?}", e);
+                        Response::from_string("Could not build calendar").with_status_code(500)
+                    }
+                }
+            }
+            Err(response) => response
+        },
+        (Method::Get, "/healthz") => {
+            let checks = probe.checks(&status.read().unwrap());
+            let code = if health::overall(&checks) == Health::Failed { 503 } else { 200 };
+            let body = serde_json::json!({ "health": health::overall(&checks), "checks": checks });
+            Response::from_string(body.to_string()).with_header(header("Content-Type", "application/json")).with_status_code(code)
+        }
+        (Method::Post, "/assistant/alexa") | (Method::Post, "/assistant/dialogflow") => {
+            if !is_authorized(&request, config.assistant_token.as_deref()) {
+                Response::from_string("Unauthorized").with_status_code(401)
+            } else {
+                let mut body = String::new();
+                let current_status = status.read().unwrap().clone();
+                let result = request.as_reader().read_to_string(&mut body)
+                    .map_err(|e| e.to_string())
+                    .and_then(|_| match path {
+                        "/assistant/alexa" => assistant::alexa(&body, &current_status, reminder_tx),
+                        _ => assistant::dialogflow(&body, &current_status, reminder_tx)
+                    }.map_err(|e| e.to_string()));
+                match result {
+                    Ok(json) => Response::from_string(json.to_string()).with_header(header("Content-Type", "application/json")),
+                    Err(e) => {
+                        log::warn!("Invalid assistant request: {}", e);
+                        Response::from_string("Bad request").with_status_code(400)
+                    }
+                }
+            }
+        }
+        (Method::Post, "/observations") => {
+            if !is_authorized(&request, config.observation_token.as_deref()) {
+                Response::from_string("Unauthorized").with_status_code(401)
+            } else {
+                match serde_json::from_reader::<_, Observation>(request.as_reader()) {
+                    Ok(observation) => {
+                        log::info!("Observation {:?} from {}", observation.kind, observation.source.as_deref().unwrap_or("unknown"));
+                        match reminder_tx.send(observation.into_event()) {
+                            Ok(()) => Response::from_string("").with_status_code(204),
+                            Err(_) => Response::from_string("The reminder stopped").with_status_code(503)
+                        }
+                    }
+                    Err(e) => {
+                        log::warn!("Invalid observation: {}", e);
+                        Response::from_string("Bad request").with_status_code(400)
+                    }
+                }
+            }
+        }
+        (Method::Get, "/status") => match authorizer.check(&request, Role::Viewer) {
+            Ok(_) => {
+                let current_status = status.read().unwrap().clone();
+                Response::from_string(serde_json::to_string(&current_status).unwrap()).with_header(header("Content-Type", "application/json"))
+            }
+            Err(response) => response
+        },
+        (Method::Get, "/leaderboard") => match (authorizer.check(&request, Role::Viewer), gamification) {
+            (Ok(_), Some(gamification)) => match history::load() {
+                Ok(entries) => {
+                    let standings = gamification::leaderboard(gamification, &entries, Utc::now());
+                    Response::from_string(serde_json::to_string(&standings).unwrap()).with_header(header("Content-Type", "application/json"))
+                }
+                Err(e) => {
+                    log::error!("Could not load the history for the leaderboard: {}", e);
+                    Response::from_string("Could not load the history").with_status_code(500)
+                }
+            },
+            (Ok(_), None) => Response::from_string("Not found").with_status_code(404),
+            (Err(response), _) => response
+        },
+        (Method::Post, "/reset") => match authorizer.check(&request, Role::Resetter) {
+            Ok(api_token) => {
+                log::info!("Reset requested with the token of {}", api_token.name);
+                match reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Http, query_parameter(&url, "by"))) {
+                    Ok(()) => Response::from_string("").with_status_code(204),
+                    Err(_) => Response::from_string("The reminder stopped").with_status_code(503)
+                }
+            }
+            Err(response) => response
+        },
+        (Method::Post, "/cleaning-started") => match authorizer.check(&request, Role::Resetter) {
+            Ok(api_token) => {
+                log::info!("Cleaning started with the token of {}", api_token.name);
+                match reminder_tx.send(ReminderEvent::CleaningStarted(ResetSource::Http, query_parameter(&url, "by"))) {
+                    Ok(()) => Response::from_string("").with_status_code(204),
+                    Err(_) => Response::from_string("The reminder stopped").with_status_code(503)
+                }
+            }
+            Err(response) => response
+        },
+        (Method::Post, "/litter-replaced") => match authorizer.check(&request, Role::Resetter) {
+            Ok(api_token) => {
+                log::info!("Litter replacement recorded with the token of {}", api_token.name);
+                match reminder_tx.send(ReminderEvent::LitterReplaced(ResetSource::Http, query_parameter(&url, "by"))) {
+                    Ok(()) => Response::from_string("").with_status_code(204),
+                    Err(_) => Response::from_string("The reminder stopped").with_status_code(503)
+                }
+            }
+            Err(response) => response
+        },
+        (Method::Post, "/bag-opened") => match authorizer.check(&request, Role::Resetter) {
+            Ok(api_token) => {
+                log::info!("New bag of litter recorded with the token of {}", api_token.name);
+                match reminder_tx.send(ReminderEvent::BagOpened(query_parameter(&url, "by"))) {
+                    Ok(()) => Response::from_string("").with_status_code(204),
+                    Err(_) => Response::from_string("The reminder stopped").with_status_code(503)
+                }
+            }
+            Err(response) => response
+        },
+        (Method::Post, "/care") => match authorizer.check(&request, Role::Resetter) {
+            Ok(api_token) => {
+                let name = query_parameter(&url, "name");
+                log::info!("Care reminder {} done, recorded with the token of {}", name.as_deref().unwrap_or("due"), api_token.name);
+                match reminder_tx.send(ReminderEvent::CareDone(name, query_parameter(&url, "by"))) {
+                    Ok(()) => Response::from_string("").with_status_code(204),
+                    Err(_) => Response::from_string("The reminder stopped").with_status_code(503)
+                }
+            }
+            Err(response) => response
+        },
+        (Method::Post, "/litter") => match authorizer.check(&request, Role::Resetter) {
+            Ok(api_token) => match query_parameter(&url, "profile").unwrap_or_default().parse::<LitterProfile>() {
+                Ok(profile) => {
+                    log::info!("Litter changed to {} with the token of {}", profile, api_token.name);
+                    match reminder_tx.send(ReminderEvent::LitterSelected(profile)) {
+                        Ok(()) => Response::from_string("").with_status_code(204),
+                        Err(_) => Response::from_string("The reminder stopped").with_status_code(503)
+                    }
+                }
+                Err(e) => Response::from_string(e).with_status_code(400)
+            },
+            Err(response) => response
+        },
+        (Method::Post, "/guest") => match authorizer.check(&request, Role::Resetter) {
+            Ok(api_token) => {
+                let hours = query_parameter(&url, "hours").map_or(Ok(input::default_guest_hours()), |hours| hours.parse::<i64>());
+                // the duration is capped, so that the time at which the guest mode ends cannot overflow
+                match hours {
+                    Ok(hours) if (0..=MAX_GUEST_HOURS).contains(&hours) => {
+                        log::info!("Guest mode for {} hours requested with the token of {}", hours, api_token.name);
+                        match reminder_tx.send(ReminderEvent::GuestMode(chrono::Duration::hours(hours))) {
+                            Ok(()) => Response::from_string("").with_status_code(204),
+                            Err(_) => Response::from_string("The reminder stopped").with_status_code(503)
+                        }
+                    }
+                    _ => Response::from_string("Bad request").with_status_code(400)
+                }
+            }
+            Err(response) => response
+        },
+        // the page hands over the first token, which may be a resetter token
+        (Method::Get, "/pair") => match authorizer.check(&request, Role::Resetter).map(|_| pairing_url.and_then(pairing::code)) {
+            Ok(Some(code)) => {
+                let html = format!("<!DOCTYPE html><html><head><title>Pair with the Cat Litter Reminder</title></head><body>\
+                    <h1>Scan to pair</h1>{}</body></html>", pairing::svg(&code));
+                Response::from_string(html).with_header(header("Content-Type", "text/html; charset=utf-8"))
+            }
+            Ok(None) => Response::from_string("Could not build the pairing code").with_status_code(500),
+            Err(response) => response
+        },
+        (Method::Get, "/diagnostics") => match authorizer.check(&request, Role::Admin) {
+            Ok(_) => {
+                let diagnostics = probe.run(status.read().unwrap().clone(), log_lines(&url));
+                Response::from_string(serde_json::to_string(&diagnostics).unwrap()).with_header(header("Content-Type", "application/json"))
+            }
+            Err(response) => response
+        },
+        (Method::Get, _) if path.starts_with("/peers/") && path.ends_with("/diagnostics") => match authorizer.check(&request, Role::Admin) {
+            Ok(api_token) => {
+                // only known peers are fetched from, so that the node cannot be used to reach anything else
+                let peer = path["/peers/".len()..path.len() - "/diagnostics".len()].parse::<Ipv4Addr>().ok()
+                    .filter(|peer| probe.peers().values().any(|ips| ips.contains(peer)));
+                match peer {
+                    Some(peer) => match diagnostics::fetch(peer, config, &api_token.token, log_lines(&url)) {
+                        Ok(json) => Response::from_string(json).with_header(header("Content-Type", "application/json")),
+                        Err(e) => {
+                            log::warn!("Could not fetch the diagnostics of {}: {}", peer, e);
+                            Response::from_string(format!("Could not fetch the diagnostics of {}", peer)).with_status_code(502)
+                        }
+                    },
+                    None => Response::from_string("Unknown peer").with_status_code(404)
+                }
+            }
+            Err(response) => response
+        },
+        _ => Response::from_string("Not found").with_status_code(404)
+    };
+    if let Err(e) = request.respond(response) {
+        log::warn!("Failed to send HTTP response: {:?}", e);
+    }
+}
+
+fn is_authorized(request: &Request, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return false;
+    };
+    presented_tokens(request).iter().any(|presented| constant_time_eq(presented, token))
+}
+
+/// The tokens passed with the request, as `Authorization: Bearer` header or as `token` query parameter.
+fn presented_tokens(request: &Request) -> Vec<&str> {
+    let headers = request.headers().iter()
+        .filter(|h| h.field.equiv("Authorization"))
+        .filter_map(|h| h.value.as_str().strip_prefix("Bearer "));
+    let query_parameters = request.url().split('?').nth(1).into_iter()
+        .flat_map(|query| query.split('&'))
+        .filter_map(|param| param.strip_prefix("token="));
+    headers.chain(query_parameters).collect()
+}
+
+/// Compares tokens in a time that does not depend on how much of them matches, so that they cannot be guessed byte by byte.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |difference, (x, y)| difference | (x ^ y)) == 0
+}
+
+fn query_parameter(url: &str, name: &str) -> Option<String> {
+    setup::parse_form(url.split('?').nth(1)?).remove(name)
+}
+
+/// The number of log lines asked for with the `lines` query parameter, within bounds.
+fn log_lines(url: &str) -> usize {
+    query_parameter(url, "lines").and_then(|lines| lines.parse().ok()).unwrap_or(diagnostics::DEFAULT_LOG_LINES).min(diagnostics::MAX_LOG_LINES)
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+/// Builds an iCalendar feed with the past cleanings and the next time at which the litter box will be due.
+fn calendar(status: &ReminderStatus) -> std::io::Result<String> {
+    let now = Utc::now();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//cat-litter-reminder//EN".to_string(),
+        "X-WR-CALNAME:Cat litter".to_string()
+    ];
+
+    for entry in history::cleanings()?.iter() {
+        let summary = match &entry.by {
+            Some(by) => format!("Litter box cleaned by {}", by),
+            None => "Litter box cleaned".to_string()
+        };
+        lines.extend(event(&format!("cleaning-{}", entry.time.timestamp()), entry.time, &summary, now));
+    }
+
+    let due_time = status.due_time;
+    lines.extend(event(&format!("due-{}", status.last_cleaning_time.timestamp()), due_time, "Litter box due", now));
+
+    lines.push("END:VCALENDAR".to_string());
+    Ok(lines.join("\r\n") + "\r\n")
+}
+
+fn event(id: &str, time: DateTime<Utc>, summary: &str, now: DateTime<Utc>) -> Vec<String> {
+    vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@cat-litter-reminder", id),
+        format!("DTSTAMP:{}", now.format(ICS_DATE_FORMAT)),
+        format!("DTSTART:{}", time.format(ICS_DATE_FORMAT)),
+        "DURATION:PT15M".to_string(),
+        format!("SUMMARY:{}", escape_text(summary)),
+        "END:VEVENT".to_string()
+    ]
+}
+
+/// Escapes a TEXT value of the calendar (RFC 5545 §3.3.11), such as the name of a member in a summary.
+pub fn escape_text(value: &str) -> String {
+    value.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\r', "")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+use std::os::unix::ffi::OsStrExt;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use gethostname::gethostname;
+use serde::Deserialize;
+
+use crate::reminder::ReminderStatus;
+
+const POLL_DELAY: Duration = Duration::from_millis(1000);
+const MAX_BUFFERED_LINES: usize = 10_000;
+
+#[derive(Deserialize, Clone)]
+pub struct InfluxConfig {
+    /// Full write URL, e.g. `http://influx.local:8086/api/v2/write?org=home&bucket=cat&precision=s`
+    /// or `http://influx.local:8086/write?db=cat&precision=s`
+    pub url: String,
+    /// API token, sent as `Authorization: Token ...`
+    pub token: Option<String>,
+    #[serde(default = "default_interval_seconds")]
+    pub interval_seconds: u64
+}
+
+fn default_interval_seconds() -> u64 {
+    60
+}
+
+/// Runs the exporter, which periodically writes elapsed-time samples, cleanings and the measured climate in the line protocol.
+/// Lines that could not be written are kept and retried with the next batch.
+pub fn run(config: InfluxConfig, status: Arc<RwLock<ReminderStatus>>, shutdown_flag: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let hostname = gethostname();
+        let host = escape_tag(&String::from_utf8_lossy(hostname.as_bytes()));
+        let mut buffer: VecDeque<String> = VecDeque::new();
+        let mut last_cleaning_time: Option<DateTime<Utc>> = None;
+        let mut last_export: Option<DateTime<Utc>> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let now = Utc::now();
+            if last_export.is_some_and(|t| now.signed_duration_since(t).num_seconds() < config.interval_seconds as i64) {
+                continue;
+            }
+            last_export = Some(now);
+
+            let current = status.read().unwrap().clone();
+            // cleanings are written with their own timestamp, so re-writing the last one after a restart is harmless
+            if last_cleaning_time != Some(current.last_cleaning_time) {
+                buffer.push_back(format!("litter_cleaning,host={} value=1i {}", host, current.last_cleaning_time.timestamp()));
+            }
+            last_cleaning_time = Some(current.last_cleaning_time);
+            let elapsed = now.signed_duration_since(current.last_cleaning_time).num_seconds();
+            buffer.push_back(format!("litter_elapsed,host={} seconds={}i,state=\"{:?}\" {}", host, elapsed, current.state, now.timestamp()));
+            if let Some(climate) = current.climate {
+                buffer.push_back(format!("litter_climate,host={} temperature={},humidity={} {}", host, climate.temperature, climate.humidity, now.timestamp()));
+            }
+            if let Some(cpu_temperature) = current.cpu_temperature {
+                buffer.push_back(format!("node_cpu,host={} temperature={} {}", host, cpu_temperature, now.timestamp()));
+            }
+
+            while buffer.len() > MAX_BUFFERED_LINES {
+                buffer.pop_front();
+            }
+            match write(&config, &buffer) {
+                Ok(()) => buffer.clear(),
+                Err(e) => log::warn!("Could not write to InfluxDB, keeping {} lines for later: {}", buffer.len(), e)
+            }
+        }
+    });
+}
+
+fn write(config: &InfluxConfig, lines: &VecDeque<String>) -> Result<(), Box<ureq::Error>> {
+    let body = lines.iter().map(|l| l.as_str()).collect::<Vec<_>>().join("\n");
+    let mut request = ureq::post(&config.url).set("Content-Type", "text/plain; charset=utf-8");
+    if let Some(token) = &config.token {
+        request = request.set("Authorization", &format!("Token {}", token));
+    }
+    request.send_string(&body)?;
+    Ok(())
+}
+
+fn escape_tag(value: &str) -> String {
+    value.replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ")
+}
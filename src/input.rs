@@ -0,0 +1,269 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::error::{CatReminderError, Result};
+use crate::gpio::{Active, Bias, Chip, Input, Lines, Options};
+use crate::reminder::{ReminderEvent, ResetSource};
+
+const POLL_DELAY: Duration = Duration::from_millis(20);
+
+/// A GPIO input, such as a push button or a capacitive touch pad, triggering an action.
+#[derive(Deserialize, Clone)]
+pub struct InputConfig {
+    pub pin: u32,
+    pub action: InputAction,
+    /// Whether the line is low while the input is pressed, which is the case for a push button pulling the line to
+    /// the ground. Touch pads such as the TTP223 are usually active high.
+    #[serde(default = "default_active_low")]
+    pub active_low: bool,
+    #[serde(default)]
+    pub pull: Pull,
+    #[serde(default)]
+    pub gesture: Gesture,
+    /// Time the input needs to be held for the action to be triggered, to avoid accidental touches, e.g. 2000 to keep
+    /// toddlers and cats from resetting the timer
+    #[serde(default)]
+    pub hold_ms: u64,
+    /// Time within which the second press of a double press needs to follow the first one
+    #[serde(default = "default_double_press_ms")]
+    pub double_press_ms: u64,
+    /// Whether a reset needs to be confirmed: the first gesture only arms it, and a second one within the confirmation
+    /// time resets the timer
+    #[serde(default)]
+    pub confirm: bool,
+    #[serde(default = "default_confirm_seconds")]
+    pub confirm_seconds: u64,
+    /// Snooze duration, for the snooze action
+    #[serde(default = "default_snooze_minutes")]
+    pub snooze_minutes: i64,
+    /// Guest mode duration, for the guest action
+    #[serde(default = "default_guest_hours")]
+    pub guest_hours: i64,
+    /// Care reminder done with the care action, the first one that is due if not set
+    pub care: Option<String>
+}
+
+fn default_active_low() -> bool {
+    true
+}
+
+fn default_snooze_minutes() -> i64 {
+    60
+}
+
+pub(crate) fn default_guest_hours() -> i64 {
+    4
+}
+
+fn default_double_press_ms() -> u64 {
+    600
+}
+
+fn default_confirm_seconds() -> u64 {
+    5
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum InputAction {
+    Reset,
+    /// Marks the cleaning as in progress, such as the first stage of a two-stage button, freezing the escalation
+    /// until a reset confirms it
+    Start,
+    Snooze,
+    /// Pauses the reminder until the input is pressed again
+    Pause,
+    /// Enables the guest mode, or ends it if it is enabled
+    Guest,
+    /// Records that a care reminder was done
+    Care,
+    /// Selects the litter box the next reset applies to, cycling through the boxes of the other zones on a hub
+    #[serde(rename = "select_box")]
+    SelectBox
+}
+
+/// How the input needs to be pressed for the action to be triggered.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Gesture {
+    /// A press held for at least the hold time
+    #[default]
+    Press,
+    /// Two presses in a row, which is hardly done by accident
+    DoublePress
+}
+
+/// Internal bias of the line, to use when there is no external resistor.
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Pull {
+    #[default]
+    Up,
+    Down,
+    None
+}
+
+/// The inputs used when none are configured: a push button on GPIO 5 resetting the state.
+pub fn default_inputs() -> Vec<InputConfig> {
+    vec![InputConfig {
+        pin: 5,
+        action: InputAction::Reset,
+        active_low: true,
+        pull: Pull::Up,
+        gesture: Gesture::Press,
+        hold_ms: 0,
+        double_press_ms: default_double_press_ms(),
+        confirm: false,
+        confirm_seconds: default_confirm_seconds(),
+        snooze_minutes: default_snooze_minutes(),
+        guest_hours: default_guest_hours(),
+        care: None
+    }]
+}
+
+struct InputLine {
+    config: InputConfig,
+    line: Lines<Input>,
+    state: InputState
+}
+
+/// What a poll of an input leads to.
+#[derive(PartialEq, Eq, Debug)]
+pub enum Transition {
+    /// A reset that needs to be confirmed was armed, waiting for its confirmation
+    Armed,
+    /// The gesture is complete, triggering the action
+    Triggered
+}
+
+/// The gesture under way on an input, kept from one poll to the next.
+#[derive(Default)]
+pub struct InputState {
+    /// Time since which the input is pressed, and whether it already triggered its action while pressed
+    pressed_since: Option<(Instant, bool)>,
+    /// Time at which a press that did not trigger the action was released, for double presses
+    released_at: Option<Instant>,
+    /// Time at which a reset was armed, waiting for its confirmation
+    armed_at: Option<Instant>
+}
+
+impl InputState {
+    /// Cancels the armed reset if it was not confirmed in time, returning whether it was.
+    pub fn expire(&mut self, config: &InputConfig, now: Instant) -> bool {
+        if self.armed_at.is_some_and(|t| now.duration_since(t) >= Duration::from_secs(config.confirm_seconds)) {
+            self.armed_at = None;
+            return true;
+        }
+        false
+    }
+
+    /// Advances the gesture with whether the input reads as pressed at the given time, returning what it leads to,
+    /// once per gesture.
+    pub fn poll(&mut self, config: &InputConfig, pressed: bool, now: Instant) -> Option<Transition> {
+        if !pressed {
+            if let Some((since, triggered)) = self.pressed_since.take() {
+                if !triggered && config.gesture == Gesture::Press {
+                    log::debug!("Input {} released after {} ms, before being held for {} ms", config.pin, now.duration_since(since).as_millis(), config.hold_ms);
+                }
+                self.released_at = (!triggered).then_some(now);
+            }
+            return None;
+        }
+        let is_new_press = self.pressed_since.is_none();
+        let (since, triggered) = *self.pressed_since.get_or_insert((now, false));
+        if triggered {
+            return None;
+        }
+        let is_complete = match config.gesture {
+            Gesture::Press => now.duration_since(since) >= Duration::from_millis(config.hold_ms),
+            Gesture::DoublePress => is_new_press && self.released_at.is_some_and(|t| now.duration_since(t) < Duration::from_millis(config.double_press_ms))
+        };
+        if !is_complete {
+            return None;
+        }
+        self.pressed_since = Some((since, true));
+        self.released_at = None;
+        if config.confirm && matches!(config.action, InputAction::Reset) && self.armed_at.take().is_none() {
+            self.armed_at = Some(now);
+            return Some(Transition::Armed);
+        }
+        Some(Transition::Triggered)
+    }
+}
+
+/// Requests the line of the input, which reads as active while the input is pressed.
+pub(crate) fn request(chip: &Chip, config: &InputConfig) -> std::io::Result<Lines<Input>> {
+    let bias = match config.pull {
+        Pull::Up => Bias::PullUp,
+        Pull::Down => Bias::PullDown,
+        Pull::None => Bias::Disable
+    };
+    let active = if config.active_low { Active::Low } else { Active::High };
+    chip.request_lines(Options::input([config.pin]).active(active).bias(bias).consumer("cat-reminder-input"))
+}
+
+/// Runs the input thread, which polls all configured inputs and sends the event matching their action to the
+/// reminder, once per gesture.
+///
+/// # Errors
+///
+/// Fails if the GPIO lines of the inputs cannot be requested, in which case no input is polled.
+pub fn run(configs: Vec<InputConfig>, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) -> Result<()> {
+    let chip = Chip::new("gpiochip0").map_err(CatReminderError::Gpio)?;
+    let mut inputs: Vec<InputLine> = configs.into_iter().map(|config| {
+        let line = request(&chip, &config).map_err(CatReminderError::Gpio)?;
+        Ok(InputLine { config, line, state: InputState::default() })
+    }).collect::<Result<_>>()?;
+
+    std::thread::spawn(move || {
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            for input in inputs.iter_mut() {
+                if input.state.expire(&input.config, Instant::now()) {
+                    log::info!("Reset armed on input {} was not confirmed, cancelling it", input.config.pin);
+                    if reminder_tx.send(ReminderEvent::ResetArmed(false)).is_err() {
+                        return;
+                    }
+                }
+                let pressed = match input.line.get_values([false; 1]) {
+                    Ok([pressed]) => pressed,
+                    Err(e) => {
+                        log::error!("Could not read input {}: {:?}", input.config.pin, e);
+                        continue;
+                    }
+                };
+                match input.state.poll(&input.config, pressed, Instant::now()) {
+                    Some(Transition::Armed) => {
+                        log::info!("Reset armed on input {}, waiting {} seconds for its confirmation", input.config.pin, input.config.confirm_seconds);
+                        if reminder_tx.send(ReminderEvent::ResetArmed(true)).is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                    Some(Transition::Triggered) => (),
+                    None => continue
+                }
+
+                log::info!("Input {} pressed: {:?}", input.config.pin, input.config.action);
+                let event = match input.config.action {
+                    InputAction::Reset => ReminderEvent::ResetRequested(ResetSource::Button, None),
+                    InputAction::Start => ReminderEvent::CleaningStarted(ResetSource::Button, None),
+                    InputAction::Snooze => ReminderEvent::Snooze(chrono::Duration::minutes(input.config.snooze_minutes), None),
+                    InputAction::Pause => ReminderEvent::TogglePause(None),
+                    InputAction::Guest => ReminderEvent::ToggleGuestMode(chrono::Duration::hours(input.config.guest_hours)),
+                    InputAction::Care => ReminderEvent::CareDone(input.config.care.clone(), None),
+                    InputAction::SelectBox => ReminderEvent::SelectNextBox
+                };
+                if reminder_tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(())
+}
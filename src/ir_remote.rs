@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::reminder::{ReminderEvent, ResetSource};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// An IR receiver set up with LIRC, so that the reminder can be controlled with a spare remote.
+#[derive(Deserialize, Clone)]
+pub struct IrRemoteConfig {
+    #[serde(default = "default_socket")]
+    pub socket: String,
+    /// Actions by LIRC button name, e.g. `KEY_RED = "reset"`
+    pub buttons: HashMap<String, IrAction>,
+    #[serde(default = "default_snooze_minutes")]
+    pub snooze_minutes: i64
+}
+
+fn default_socket() -> String {
+    "/var/run/lirc/lircd".to_string()
+}
+
+fn default_snooze_minutes() -> i64 {
+    60
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum IrAction {
+    Reset,
+    Snooze,
+    /// Pauses the reminder until the button is pressed again
+    Pause
+}
+
+/// Runs the IR remote thread, which reads button presses from the LIRC socket and maps them to reminder events.
+/// Repeated codes sent while a button is held down are ignored.
+pub fn run(config: IrRemoteConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            let stream = match UnixStream::connect(&config.socket) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn!("Could not connect to LIRC at {}: {}", config.socket, e);
+                    sleep(RECONNECT_DELAY);
+                    continue;
+                }
+            };
+            stream.set_read_timeout(Some(READ_TIMEOUT)).expect("Could not set LIRC read timeout");
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+
+            while !shutdown_flag.load(Ordering::Relaxed) {
+                match reader.read_line(&mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        // <code> <repeat count> <button> <remote>
+                        let fields: Vec<&str> = line.split_whitespace().collect();
+                        if let [_, "00", button, ..] = fields.as_slice() {
+                            if let Some(action) = config.buttons.get(*button) {
+                                log::info!("IR remote button {} pressed: {:?}", button, action);
+                                let event = match action {
+                                    IrAction::Reset => ReminderEvent::ResetRequested(ResetSource::Remote, None),
+                                    IrAction::Snooze => ReminderEvent::Snooze(chrono::Duration::minutes(config.snooze_minutes), None),
+                                    IrAction::Pause => ReminderEvent::TogglePause(None)
+                                };
+                                if reminder_tx.send(event).is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                        line.clear();
+                    }
+                    // timeouts only give the loop a chance to check the shutdown flag, keeping partial lines
+                    Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => (),
+                    Err(e) => {
+                        log::warn!("Could not read from LIRC: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
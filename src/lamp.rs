@@ -0,0 +1,106 @@
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::config::schedule;
+use crate::notifier::QuietHours;
+use crate::reminder::{LEDStripState, ReminderStatus};
+
+const POLL_DELAY: StdDuration = StdDuration::from_millis(1000);
+const PLUG_TOGGLE_DELAY: StdDuration = StdDuration::from_millis(1000);
+const PLUG_TOGGLE_COUNT: usize = 5;
+
+/// Configuration of a lamp in another room that gets flashed when the strip has been blinking red for too long.
+#[derive(Deserialize, Clone)]
+pub struct LampConfig {
+    /// Time the strip needs to be blinking red before the lamp gets flashed
+    #[serde(default = "default_after_minutes")]
+    pub after_minutes: i64,
+    /// Interval at which the lamp is flashed again as long as the litter box isn't cleaned
+    #[serde(default = "default_repeat_minutes")]
+    pub repeat_minutes: i64,
+    pub hue: Option<HueConfig>,
+    pub plug: Option<PlugConfig>
+}
+
+fn default_after_minutes() -> i64 {
+    30
+}
+
+fn default_repeat_minutes() -> i64 {
+    15
+}
+
+/// A light connected to a Philips Hue bridge
+#[derive(Deserialize, Clone)]
+pub struct HueConfig {
+    pub bridge: String,
+    pub username: String,
+    pub light_id: String
+}
+
+/// A smart plug with a lamp attached, switched through plain HTTP calls (e.g. Shelly or Tasmota)
+#[derive(Deserialize, Clone)]
+pub struct PlugConfig {
+    pub on_url: String,
+    pub off_url: String,
+    /// Whether the lamp is normally on, in which case it is switched back on after flashing instead of being left off
+    #[serde(default)]
+    pub normally_on: bool
+}
+
+/// Runs the lamp escalation, which flashes the configured lamp once the strip has been blinking red for too long.
+pub fn run(config: LampConfig, quiet_hours: Option<QuietHours>, status: Arc<RwLock<ReminderStatus>>, shutdown_flag: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let mut blinking_since: Option<DateTime<Utc>> = None;
+        let mut last_flash: Option<DateTime<Utc>> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let now = Utc::now();
+            let current = status.read().unwrap().clone();
+            if current.state != LEDStripState::BlinkingRed {
+                blinking_since = None;
+                last_flash = None;
+                continue;
+            }
+            let since = *blinking_since.get_or_insert(now);
+            // the lamp stays off along with the strip while the reminder is snoozed or nobody is home
+            let is_quiet = current.quiet || quiet_hours.as_ref().is_some_and(|q| q.contains(now.with_timezone(&schedule().timezone).time()));
+            let is_due = match last_flash {
+                None => now.signed_duration_since(since) >= Duration::minutes(config.after_minutes),
+                Some(t) => now.signed_duration_since(t) >= Duration::minutes(config.repeat_minutes)
+            };
+            if is_due && !is_quiet {
+                log::info!("Litter box overdue for too long, flashing the lamp");
+                last_flash = Some(now);
+                if let Err(e) = flash(&config) {
+                    log::error!("Could not flash the lamp: {}", e);
+                }
+            }
+        }
+    });
+}
+
+fn flash(config: &LampConfig) -> Result<(), Box<ureq::Error>> {
+    if let Some(hue) = &config.hue {
+        // "lselect" makes the light breathe for 15 seconds
+        ureq::put(&format!("http://{}/api/{}/lights/{}/state", hue.bridge, hue.username, hue.light_id))
+            .send_json(serde_json::json!({ "alert": "lselect" }))?;
+    }
+    if let Some(plug) = &config.plug {
+        // the plug ends up in the state it is normally in
+        let (first_url, last_url) = if plug.normally_on { (&plug.off_url, &plug.on_url) } else { (&plug.on_url, &plug.off_url) };
+        for _ in 0..PLUG_TOGGLE_COUNT {
+            ureq::get(first_url).call()?;
+            sleep(PLUG_TOGGLE_DELAY);
+            ureq::get(last_url).call()?;
+            sleep(PLUG_TOGGLE_DELAY);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::thread::sleep;
+use std::time::Duration as StdDuration;
+
+use chrono::Utc;
+use linux_embedded_hal::i2cdev::core::I2CDevice;
+use linux_embedded_hal::i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+use serde::Deserialize;
+
+use crate::display::{Display, DisplayState};
+use crate::reminder::LEDStripState;
+
+// bits of the PCF8574 I²C backpack, connected to the HD44780 in 4-bit mode
+const REGISTER_SELECT: u8 = 0b0000_0001;
+const ENABLE: u8 = 0b0000_0100;
+const BACKLIGHT: u8 = 0b0000_1000;
+
+const CLEAR_DISPLAY: u8 = 0x01;
+const ENTRY_MODE_INCREMENT: u8 = 0x06;
+const DISPLAY_ON: u8 = 0x0C;
+const FUNCTION_4_BIT_2_LINES: u8 = 0x28;
+const SET_DDRAM_ADDRESS: u8 = 0x80;
+const LINE_ADDRESSES: [u8; 2] = [0x00, 0x40];
+const COLUMNS: usize = 16;
+
+#[derive(Deserialize, Clone)]
+pub struct LcdConfig {
+    #[serde(default = "default_i2c_bus")]
+    pub i2c_bus: String,
+    /// I²C address of the PCF8574 backpack, usually 0x27 or 0x3F
+    #[serde(default = "default_address")]
+    pub address: u16
+}
+
+fn default_i2c_bus() -> String {
+    "/dev/i2c-1".to_string()
+}
+
+fn default_address() -> u16 {
+    0x27
+}
+
+/// A 16x2 HD44780 character LCD with a PCF8574 I²C backpack, showing the elapsed hours and the urgency.
+/// The backlight is off during the night and blinks while the litter box is overdue, like the strip.
+pub struct LcdDisplay {
+    device: LinuxI2CDevice,
+    backlight: u8,
+    lines: [String; 2]
+}
+
+impl LcdDisplay {
+    /// Opens and initializes the display.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the I²C device cannot be opened or the display does not respond.
+    pub fn new(config: &LcdConfig) -> Self {
+        let device = LinuxI2CDevice::new(&config.i2c_bus, config.address).expect("Could not open I2C device");
+        let mut lcd = LcdDisplay { device, backlight: BACKLIGHT, lines: [String::new(), String::new()] };
+        lcd.init().expect("Could not initialize LCD");
+        lcd
+    }
+
+    fn init(&mut self) -> Result<(), LinuxI2CError> {
+        // switch to 4-bit mode, following the initialization sequence of the HD44780 datasheet
+        sleep(StdDuration::from_millis(50));
+        for _ in 0..3 {
+            self.write_nibble(0x30, 0)?;
+            sleep(StdDuration::from_millis(5));
+        }
+        self.write_nibble(0x20, 0)?;
+        self.command(FUNCTION_4_BIT_2_LINES)?;
+        self.command(DISPLAY_ON)?;
+        self.command(ENTRY_MODE_INCREMENT)?;
+        self.command(CLEAR_DISPLAY)?;
+        sleep(StdDuration::from_millis(2));
+        Ok(())
+    }
+
+    fn command(&mut self, value: u8) -> Result<(), LinuxI2CError> {
+        self.write_byte(value, 0)
+    }
+
+    fn write_byte(&mut self, value: u8, mode: u8) -> Result<(), LinuxI2CError> {
+        self.write_nibble(value & 0xF0, mode)?;
+        self.write_nibble((value << 4) & 0xF0, mode)
+    }
+
+    fn write_nibble(&mut self, nibble: u8, mode: u8) -> Result<(), LinuxI2CError> {
+        let data = nibble | mode | self.backlight;
+        self.device.smbus_write_byte(data | ENABLE)?;
+        sleep(StdDuration::from_micros(1));
+        self.device.smbus_write_byte(data)?;
+        sleep(StdDuration::from_micros(50));
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: usize, text: &str) -> Result<(), LinuxI2CError> {
+        self.command(SET_DDRAM_ADDRESS | LINE_ADDRESSES[line])?;
+        let padded = format!("{:<width$.width$}", text, width = COLUMNS);
+        for c in padded.chars() {
+            self.write_byte(if c.is_ascii() { c as u8 } else { b'?' }, REGISTER_SELECT)?;
+        }
+        Ok(())
+    }
+}
+
+impl LcdDisplay {
+    fn set_backlight(&mut self, on: bool) -> Result<(), LinuxI2CError> {
+        let backlight = if on { BACKLIGHT } else { 0 };
+        if backlight != self.backlight {
+            self.backlight = backlight;
+            self.device.smbus_write_byte(self.backlight)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for LcdDisplay {
+    fn name(&self) -> &str {
+        "lcd"
+    }
+
+    fn render(&mut self, state: &DisplayState) {
+        let status = &state.status;
+        let backlight = !state.is_dark && state.blink_on;
+        if let Err(e) = self.set_backlight(backlight) {
+            log::error!("Could not change the LCD backlight: {:?}", e);
+        }
+
+        let elapsed = Utc::now().signed_duration_since(status.last_cleaning_time);
+        let lines = [format!("Cleaned {}h ago", elapsed.num_hours()), urgency(status.state).to_string()];
+        for (i, line) in lines.iter().enumerate() {
+            if *line != self.lines[i] {
+                match self.write_line(i, line) {
+                    Ok(()) => self.lines[i] = line.clone(),
+                    Err(e) => log::error!("Could not write to the LCD: {:?}", e)
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        let result = self.set_backlight(false).and_then(|_| self.command(CLEAR_DISPLAY));
+        if let Err(e) = result {
+            log::error!("Could not clear the LCD: {:?}", e);
+        }
+    }
+}
+
+fn urgency(state: LEDStripState) -> &'static str {
+    match state {
+        LEDStripState::LightGreen => "All clean",
+        LEDStripState::DarkGreen => "Still fine",
+        LEDStripState::Orange => "Clean soon",
+        LEDStripState::Red => "Clean now!",
+        LEDStripState::BlinkingRed => "OVERDUE!"
+    }
+}
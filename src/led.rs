@@ -1,4 +1,44 @@
+#[cfg(hardware)]
 use rs_ws281x::*;
+#[cfg(not(hardware))]
+use self::stub::*;
+
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::display::{Display, DisplayState};
+use crate::error::{CatReminderError, Result};
+use crate::reminder::{LEDStripState, Scoops};
+
+const CONFIRMATION_BLINK_DELAY: Duration = Duration::from_millis(200);
+const PULSE_DELAY: Duration = Duration::from_millis(600);
+const SYNC_BLIP_DELAY: Duration = Duration::from_millis(250);
+const STARTUP_SWEEP_DELAY: Duration = Duration::from_millis(40);
+/// Time each state reached during the night is shown when catching up on them in the morning
+const CATCH_UP_DELAY: Duration = Duration::from_millis(800);
+const VERSION_BLINK_DELAY: Duration = Duration::from_millis(300);
+/// Length of the single pulse shown for a minor version of zero
+const VERSION_ZERO_DELAY: Duration = Duration::from_millis(1200);
+const RENDER_ATTEMPTS: usize = 3;
+/// Delay before rendering again after a failed render, doubled on every further failure
+const RENDER_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RENDER_BACKOFF: Duration = Duration::from_secs(60);
+/// Number of failed renders in a row after which the strip is considered failing
+const FAILING_RENDERS: u32 = 5;
+/// Every how many LEDs one is lit by a chasing pattern
+const CHASE_SPACING: usize = 3;
+/// By how much the LEDs beyond the progress are dimmed
+const PROGRESS_DIM_DIVISOR: u8 = 4;
+
+thread_local! {
+    /// The controller of the strip last initialized on the thread, which the panic hook shows the failure on, since the
+    /// DMA channel and the pin cannot be taken by a second controller while it is alive
+    static FAILURE_CONTROLLER: RefCell<Weak<RefCell<Controller>>> = RefCell::new(Weak::new());
+}
 
 pub trait LedController {
 
@@ -7,59 +47,727 @@ pub trait LedController {
     const DARK_GREEN: RawColor = [0, 20, 0, 0];
     const ORANGE: RawColor = [0, 60, 255, 0];
     const RED: RawColor = [0, 0, 255, 0];
+    const BLUE: RawColor = [60, 0, 0, 0];
+    const PURPLE: RawColor = [60, 0, 60, 0];
+    const WHITE: RawColor = [60, 60, 60, 60];
+    const CYAN: RawColor = [60, 60, 0, 0];
 
     /// Sets all the LEDs to the provided [RawColor].
     ///
-    /// # Panics
+    /// # Errors
+    ///
+    /// This function will return an error if the strip could not be rendered.
+    fn set_all_to(&mut self, color: RawColor) -> Result<()>;
+
+    /// Number of LEDs that can be set.
+    fn led_count(&self) -> usize;
+
+    /// Sets the LED at the index to the provided [RawColor] in the frame buffer, without showing it yet. Indices
+    /// past the last LED are ignored.
+    fn set_pixel(&mut self, index: usize, color: RawColor);
+
+    /// Sets the LEDs in the range to the provided [RawColor] in the frame buffer, without showing them yet.
+    fn fill_range(&mut self, range: Range<usize>, color: RawColor) {
+        range.for_each(|index| self.set_pixel(index, color));
+    }
+
+    /// Shows the frame buffer on the strip, unless it already shows it.
+    ///
+    /// # Errors
     ///
-    /// Panics if there is an issue with setting the color.
-    fn set_all_to(&mut self, color: RawColor) -> ();
+    /// This function will return an error if the strip could not be rendered.
+    fn commit(&mut self) -> Result<()>;
+}
+
+/// Corrects the colors for the strip, since cheap strips differ wildly in their color balance. The colors of the
+/// reminder are the intended ones, before the correction.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct Calibration {
+    /// Gamma of the strip, 1 leaving the colors as they are, around 2.2 for most WS2812 strips
+    pub gamma: f64,
+    /// Factors of the red, green and blue channels, e.g. `[0.6, 1.0, 1.0]` for a strip whose red outshines the green
+    pub scale: [f64; 3]
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration { gamma: 1.0, scale: [1.0, 1.0, 1.0] }
+    }
+}
+
+impl Calibration {
+    /// The color to send to the strip for it to show the given one.
+    pub fn apply(&self, color: RawColor) -> RawColor {
+        let [blue, green, red, white] = color;
+        let correct = |value: u8, scale: f64| (255.0 * (value as f64 / 255.0).powf(self.gamma) * scale).round().clamp(0.0, 255.0) as u8;
+        [correct(blue, self.scale[2]), correct(green, self.scale[1]), correct(red, self.scale[0]), correct(white, 1.0)]
+    }
+}
+
+/// Number of bright LEDs on a strip of the given number of LEDs showing the progress toward the next state, from
+/// a single one when the state was just entered to all of them when the next one is about to be.
+pub fn bright_leds(led_count: usize, progress: f64) -> usize {
+    ((progress.clamp(0.0, 1.0) * led_count as f64).ceil() as usize).clamp(led_count.min(1), led_count)
+}
+
+/// Number of LEDs lit green on a strip of the given number of LEDs, split into one segment per cleaning of the daily
+/// target, for the cleanings done so far.
+pub fn scoop_leds(led_count: usize, scoops: &Scoops) -> usize {
+    if scoops.target == 0 {
+        return led_count;
+    }
+    led_count * scoops.done.min(scoops.target) / scoops.target
+}
+
+/// A single dim LED lit briefly every now and then while the strip is dark, so that a dark strip tells that the
+/// reminder is still running.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct HeartbeatConfig {
+    /// Index of the LED, counted like the LEDs shown
+    pub index: usize,
+    /// Color of the LED, in RGB
+    pub color: [u8; 3],
+    pub interval_seconds: u64
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig { index: 0, color: [0, 8, 0], interval_seconds: 30 }
+    }
+}
+
+/// How the strip is mounted: the LEDs of the strip are the ones after the offset that are not masked, e.g. because they
+/// are broken, from the other end if the strip is reversed.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct StripLayout {
+    /// Whether the first LED is at the far end, e.g. for a strip mounted upside down
+    pub reverse: bool,
+    /// Number of LEDs at the start of the strip that stay dark
+    pub offset: usize,
+    /// Indices of LEDs that stay dark, counted from the start of the strip
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub masked: Vec<usize>
+}
+
+impl StripLayout {
+    /// The indices on the strip of the LEDs shown, in order.
+    fn positions(&self, count: usize) -> Vec<usize> {
+        let mut positions: Vec<usize> = (self.offset..count).filter(|index| !self.masked.contains(index)).collect();
+        if self.reverse {
+            positions.reverse();
+        }
+        positions
+    }
+}
+
+/// A second strip, on the other PWM pin of the same controller, e.g. in another room.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct SecondStripConfig {
+    pub led_count: usize,
+    pub layout: StripLayout,
+    /// Zone whose litter box the strip shows on hubs, the same state as the first strip if not set
+    pub zone: Option<String>
+}
+
+impl Default for SecondStripConfig {
+    fn default() -> Self {
+        SecondStripConfig { led_count: 10, layout: StripLayout::default(), zone: None }
+    }
 }
 
 pub struct RPILedController {
-    controller: Controller
+    /// Controller of the strip, shared with the strip on its other channel, if there is one
+    controller: Rc<RefCell<Controller>>,
+    /// Channel of the controller the strip is connected to
+    channel: usize,
+    /// Zone whose litter box the strip shows on hubs, the state shown by the node if not set
+    zone: Option<String>,
+    /// Whether the strip shows the progress toward the next state by dimming some of its LEDs
+    progress: bool,
+    /// Indices on the strip of the LEDs shown, in order, see the [StripLayout]
+    positions: Vec<usize>,
+    /// Color shown by the strip, if it was set
+    color: Option<RawColor>,
+    /// Brightness the strip was set to
+    brightness: u8,
+    is_failing: bool,
+    is_supply_low: bool,
+    /// Colors of the states, in the order of [LEDStripState::ALL]
+    colors: [RawColor; 5],
+    calibration: Calibration,
+    heartbeat: Option<HeartbeatConfig>,
+    /// When the heartbeat LED was last lit
+    last_heartbeat: Option<Instant>,
+    /// LEDs of the channel as last rendered, unset until the next render when what the strip shows is not known
+    rendered: Option<Vec<RawColor>>,
+    /// Number of failed renders in a row
+    failed_renders: u32,
+    /// Time before which the strip is not rendered again, after a failed render
+    retry_at: Option<Instant>
 }
 
 impl LedController for RPILedController {
 
-    fn set_all_to(&mut self, color: RawColor) -> () {
-        let leds = self.controller.leds_mut(0);
-        for led in leds {
-            *led = color
+    fn set_all_to(&mut self, color: RawColor) -> Result<()> {
+        self.fill(|_| color);
+        self.render_if_dirty()?;
+        self.color = Some(color);
+        Ok(())
+    }
+
+    /// Number of LEDs shown, leaving out the offset and the masked ones.
+    fn led_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    fn set_pixel(&mut self, index: usize, color: RawColor) {
+        self.set_led(self.positions.get(index).copied(), color);
+        // the strip does not show a single color anymore
+        self.color = None;
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.render_if_dirty()
+    }
+}
+
+impl Display for RPILedController {
+    fn name(&self) -> &str {
+        if self.channel == 0 { "strip" } else { "second strip" }
+    }
+
+    fn render(&mut self, state: &DisplayState) {
+        // the color is left unset by a failed render, so everything is shown again once the backoff is over
+        if self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return;
+        }
+        // following the ambient light, without ever turning the strip off
+        let full_brightness = if state.is_dimmed { Self::DIMMED_BRIGHTNESS } else { Self::BRIGHTNESS };
+        let brightness = ((full_brightness as f64 * state.brightness).round() as u8).max(1);
+        if brightness != self.brightness {
+            self.brightness = brightness;
+            self.controller.borrow_mut().set_brightness(self.channel, brightness);
+            // the brightness only applies on the next render, even if the LEDs stay the same
+            self.color = None;
+            self.rendered = None;
+        }
+        if state.is_failing != self.is_failing {
+            self.is_failing = state.is_failing;
+            self.color = None;
+        }
+        if state.is_supply_low != self.is_supply_low {
+            self.is_supply_low = state.is_supply_low;
+            self.color = None;
+        }
+        if let Some(steps) = state.snooze_selection {
+            if let Err(e) = self.set_first_to(steps, Self::BLUE) {
+                log::error!("{}", e);
+            }
+            return;
+        }
+        // an armed reset pulses white, even in the dark, until it is confirmed or cancelled
+        let color = if state.is_reset_armed {
+            if state.blink_on { Self::WHITE } else { Self::BLACK }
+        } else if state.is_dark || (state.chase.is_none() && !state.blink_on) {
+            Self::BLACK
+        } else {
+            self.colors[self.shown_state(state) as usize]
+        };
+        if let (Some(heartbeat), true) = (self.heartbeat, color == Self::BLACK && state.is_dark) {
+            let interval = Duration::from_secs(heartbeat.interval_seconds);
+            if self.last_heartbeat.is_none_or(|time| time.elapsed() >= interval) {
+                self.last_heartbeat = Some(Instant::now());
+                let [red, green, blue] = heartbeat.color;
+                // lit until the next render, which turns the strip dark again
+                if let Err(e) = self.set_only(heartbeat.index, [blue, green, red, 0]) {
+                    log::error!("{}", e);
+                }
+                return;
+            }
+        }
+        // a chase moves on every frame, and a failed render leaves the color unset, so that the next refresh tries again
+        let chase = state.chase.filter(|_| color != Self::BLACK && !state.is_reset_armed);
+        let is_lit = chase.is_none() && color != Self::BLACK && !state.is_reset_armed;
+        // the cleanings of the day toward the daily target take precedence over the progress toward the next state
+        let scooped = state.status.scoops.filter(|_| is_lit).map(|scoops| scoop_leds(self.led_count(), &scoops));
+        let bright = (self.progress && is_lit && scooped.is_none()).then(|| bright_leds(self.led_count(), state.progress));
+        if chase.is_some() || bright.is_some() || scooped.is_some() || self.color != Some(color) {
+            // the whole frame is rendered at once: the last LED turns purple while a subsystem keeps failing, the first
+            // one cyan while the litter runs out
+            let dimmed = color.map(|channel| channel / PROGRESS_DIM_DIVISOR);
+            match (chase, scooped, bright) {
+                (Some(offset), _, _) => self.fill(|i| if i % CHASE_SPACING == offset % CHASE_SPACING { color } else { Self::BLACK }),
+                (None, Some(scooped), _) => {
+                    let green = self.colors[LEDStripState::LightGreen as usize];
+                    self.fill(|i| if i < scooped { green } else { dimmed });
+                }
+                (None, None, Some(bright)) => self.fill(|i| if i < bright { color } else { dimmed }),
+                (None, None, None) => self.fill(|_| color)
+            }
+            if self.is_failing && color != Self::BLACK {
+                self.set_pixel(self.led_count().saturating_sub(1), Self::PURPLE);
+            }
+            if self.is_supply_low && color != Self::BLACK {
+                self.set_pixel(0, Self::CYAN);
+            }
+            match self.render_if_dirty() {
+                Ok(()) => self.color = if chase.is_none() && scooped.is_none() && bright.is_none() { Some(color) } else { None },
+                Err(e) => log::error!("{}", e)
+            }
+        }
+    }
+
+    fn start(&mut self) {
+        let minor: usize = env!("CARGO_PKG_VERSION_MINOR").parse().unwrap_or(0);
+        let result = self.sweep(Self::BLUE)
+            .map(|_| std::thread::sleep(VERSION_BLINK_DELAY))
+            .and_then(|_| self.blink_count(minor, Self::BLUE));
+        if let Err(e) = result {
+            log::error!("{}", e);
+        }
+    }
+
+    fn confirm(&mut self) {
+        for _ in 0..3 {
+            let result = self.set_all_to(self.colors[LEDStripState::LightGreen as usize])
+                .map(|_| std::thread::sleep(CONFIRMATION_BLINK_DELAY))
+                .and_then(|_| self.set_all_to(Self::BLACK));
+            if let Err(e) = result {
+                log::error!("{}", e);
+                return;
+            }
+            std::thread::sleep(CONFIRMATION_BLINK_DELAY);
+        }
+    }
+
+    fn catch_up(&mut self, states: &[LEDStripState]) {
+        for state in states {
+            if let Err(e) = self.set_all_to(self.colors[*state as usize]) {
+                log::error!("{}", e);
+                return;
+            }
+            std::thread::sleep(CATCH_UP_DELAY);
+        }
+        if let Err(e) = self.set_all_to(Self::BLACK) {
+            log::error!("{}", e);
+        }
+        std::thread::sleep(CONFIRMATION_BLINK_DELAY);
+    }
+
+    fn pulse(&mut self, color: [u8; 3]) {
+        let [red, green, blue] = color;
+        for _ in 0..2 {
+            let result = self.set_all_to([blue, green, red, 0])
+                .map(|_| std::thread::sleep(PULSE_DELAY))
+                .and_then(|_| self.set_all_to(Self::BLACK));
+            if let Err(e) = result {
+                log::error!("{}", e);
+                return;
+            }
+            std::thread::sleep(PULSE_DELAY);
+        }
+    }
+
+    fn synced(&mut self) {
+        let Some(position) = self.positions.last().copied() else {
+            return;
+        };
+        // a cyan blip on the last LED, which then shows what it showed before
+        let shown = self.controller.borrow_mut().leds_mut(self.channel)[position];
+        self.set_led(Some(position), Self::CYAN);
+        let result = self.commit()
+            .map(|_| std::thread::sleep(SYNC_BLIP_DELAY))
+            .and_then(|_| {
+                self.controller.borrow_mut().leds_mut(self.channel)[position] = shown;
+                self.render_if_dirty()
+            });
+        if let Err(e) = result {
+            log::error!("{}", e);
+            self.color = None;
+        }
+    }
+
+    fn is_failing(&self) -> bool {
+        self.failed_renders >= FAILING_RENDERS
+    }
+
+    fn clear(&mut self) {
+        if let Err(e) = self.set_all_to(Self::BLACK) {
+            log::error!("{}", e);
         }
-        self.controller.render().expect("Failed to change LED strip color");
+    }
+}
+
+/// Shows alternating blue and white LEDs on the first channel of the controller of the strip initialized on the current
+/// thread, if it is still alive. Meant for the panic hook, so that nothing in here panics: failures are logged instead.
+pub fn show_failure() {
+    let Some(controller) = FAILURE_CONTROLLER.try_with(|controller| controller.borrow().upgrade()).ok().flatten() else {
+        log::error!("Could not show the failure on the strip, which is not initialized");
+        return;
+    };
+    // the panic may have happened while the strip was being rendered
+    let Ok(mut controller) = controller.try_borrow_mut() else {
+        log::error!("Could not show the failure on the strip, which is in use");
+        return;
+    };
+    for (i, led) in controller.leds_mut(0).iter_mut().enumerate() {
+        *led = if i % 2 == 0 { RPILedController::BLUE } else { RPILedController::WHITE };
+    }
+    if let Err(e) = controller.render() {
+        log::error!("Could not show the failure on the strip: {:?}", e);
+    }
+}
+
+impl Default for RPILedController {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl RPILedController {
 
-    const NUM_LEDS: i32 = 10;
+    const NUM_LEDS: usize = 10;
     const LED_PIN: i32 = 18;
+    /// Pin of the second channel, which has its own PWM
+    const SECOND_LED_PIN: i32 = 13;
+    const BRIGHTNESS: u8 = 50;
+    const DIMMED_BRIGHTNESS: u8 = 10;
+
+    /// Sets the first LEDs to the provided [RawColor], turning the others off.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the strip could not be rendered.
+    pub fn set_first_to(&mut self, count: usize, color: RawColor) -> Result<()> {
+        self.fill(|_| Self::BLACK);
+        self.fill_range(0..count, color);
+        self.commit()
+    }
+
+    /// Sets the LED at the index to the provided [RawColor], turning the others off.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the strip could not be rendered.
+    pub fn set_only(&mut self, index: usize, color: RawColor) -> Result<()> {
+        self.fill(|_| Self::BLACK);
+        self.set_pixel(index, color);
+        self.commit()
+    }
+
+    /// Sets every few LEDs to the provided [RawColor], starting at the offset, turning the others off.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the strip could not be rendered.
+    pub fn set_chase(&mut self, offset: usize, color: RawColor) -> Result<()> {
+        self.fill(|i| if i % CHASE_SPACING == offset % CHASE_SPACING { color } else { Self::BLACK });
+        self.commit()
+    }
+
+    /// Lights the LEDs one after the other, from the first to the last, turning the strip off afterwards.
+    fn sweep(&mut self, color: RawColor) -> Result<()> {
+        for index in 0..self.led_count() {
+            self.set_only(index, color)?;
+            std::thread::sleep(STARTUP_SWEEP_DELAY);
+        }
+        self.set_all_to(Self::BLACK)
+    }
+
+    /// Blinks the whole strip as many times as the count, a count of zero being shown by a single long pulse.
+    fn blink_count(&mut self, count: usize, color: RawColor) -> Result<()> {
+        if count == 0 {
+            self.set_all_to(color)?;
+            std::thread::sleep(VERSION_ZERO_DELAY);
+            return self.set_all_to(Self::BLACK);
+        }
+        for _ in 0..count {
+            self.set_all_to(color)?;
+            std::thread::sleep(VERSION_BLINK_DELAY);
+            self.set_all_to(Self::BLACK)?;
+            std::thread::sleep(VERSION_BLINK_DELAY);
+        }
+        Ok(())
+    }
+
+    /// Sets the LED at the position on the strip, if any, to the corrected color, without rendering it yet.
+    fn set_led(&mut self, position: Option<usize>, color: RawColor) {
+        if let Some(position) = position {
+            self.controller.borrow_mut().leds_mut(self.channel)[position] = self.calibration.apply(color);
+        }
+    }
+
+    /// Sets the LEDs shown to the corrected color of their index in the frame buffer, turning the others off. The
+    /// color of the strip is unset until the caller knows what it shows.
+    fn fill(&mut self, color_of: impl Fn(usize) -> RawColor) {
+        self.color = None;
+        let mut controller = self.controller.borrow_mut();
+        let leds = controller.leds_mut(self.channel);
+        leds.iter_mut().for_each(|led| *led = Self::BLACK);
+        for (i, position) in self.positions.iter().enumerate() {
+            leds[*position] = self.calibration.apply(color_of(i));
+        }
+    }
+
+    /// Renders the LEDs if they changed since the last render, so that the strip is only sent what it does not show
+    /// yet. A render is retried a few times before giving up, after which the strip backs off from rendering for
+    /// longer and longer.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the strip could not be rendered.
+    pub fn render_if_dirty(&mut self) -> Result<()> {
+        let frame = self.controller.borrow_mut().leds_mut(self.channel).to_vec();
+        if self.rendered.as_ref() == Some(&frame) {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        for _ in 0..RENDER_ATTEMPTS {
+            result = self.controller.borrow_mut().render().map_err(|e| CatReminderError::Led(e.to_string()));
+            if result.is_ok() {
+                break;
+            }
+        }
+        match result {
+            Ok(()) => {
+                self.rendered = Some(frame);
+                self.failed_renders = 0;
+                self.retry_at = None;
+            }
+            Err(_) => {
+                let backoff = RENDER_BACKOFF.saturating_mul(2u32.saturating_pow(self.failed_renders)).min(MAX_RENDER_BACKOFF);
+                self.failed_renders = self.failed_renders.saturating_add(1);
+                self.retry_at = Some(Instant::now() + backoff);
+                self.color = None;
+            }
+        }
+        result
+    }
 
     pub fn new() -> Self {
-        RPILedController {
-            controller: ControllerBuilder::new()
+        Self::with_led_count(Self::NUM_LEDS)
+    }
+
+    pub fn with_led_count(led_count: usize) -> Self {
+        let controller = ControllerBuilder::new()
             .freq(800_000)
             .dma(10)
             .channel(
                 0, // Channel Index
                 ChannelBuilder::new()
                     .pin(Self::LED_PIN)
-                    .count(Self::NUM_LEDS)
+                    .count(led_count as i32)
+                    .strip_type(StripType::Ws2812)
+                    .brightness(Self::BRIGHTNESS) // default: 255
+                    .build(),
+            )
+            .build()
+            .expect("Could not initialize LED controller");
+        Self::on_channel(Rc::new(RefCell::new(controller)), 0, led_count)
+    }
+
+    /// Initializes the strips on both channels of the controller, which share their brightness and are rendered
+    /// together.
+    pub fn with_led_counts(led_count: usize, second_led_count: usize) -> (Self, Self) {
+        let controller = ControllerBuilder::new()
+            .freq(800_000)
+            .dma(10)
+            .channel(
+                0,
+                ChannelBuilder::new()
+                    .pin(Self::LED_PIN)
+                    .count(led_count as i32)
+                    .strip_type(StripType::Ws2812)
+                    .brightness(Self::BRIGHTNESS)
+                    .build(),
+            )
+            .channel(
+                1,
+                ChannelBuilder::new()
+                    .pin(Self::SECOND_LED_PIN)
+                    .count(second_led_count as i32)
                     .strip_type(StripType::Ws2812)
-                    .brightness(50) // default: 255
+                    .brightness(Self::BRIGHTNESS)
                     .build(),
             )
             .build()
-            .expect("Could not initialize LED controller")
+            .expect("Could not initialize LED controller");
+        let controller = Rc::new(RefCell::new(controller));
+        (Self::on_channel(controller.clone(), 0, led_count), Self::on_channel(controller, 1, second_led_count))
+    }
+
+    fn on_channel(controller: Rc<RefCell<Controller>>, channel: usize, led_count: usize) -> Self {
+        let _ = FAILURE_CONTROLLER.try_with(|failure_controller| *failure_controller.borrow_mut() = Rc::downgrade(&controller));
+        RPILedController {
+            positions: (0..led_count).collect(),
+            controller,
+            channel,
+            zone: None,
+            progress: false,
+            color: None,
+            brightness: Self::BRIGHTNESS,
+            is_failing: false,
+            is_supply_low: false,
+            colors: [Self::LIGHT_GREEN, Self::DARK_GREEN, Self::ORANGE, Self::RED, Self::RED],
+            calibration: Calibration::default(),
+            heartbeat: None,
+            last_heartbeat: None,
+            rendered: None,
+            failed_renders: 0,
+            retry_at: None
+        }
+    }
+
+    /// Shows the progress toward the next state, with fewer LEDs dimmed the closer the next state is.
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Shows the litter box of the zone on hubs, rather than the most urgent of all.
+    pub fn with_zone(mut self, zone: Option<String>) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    /// The state of the litter box shown by the strip, the node's own one if the zone is not known.
+    fn shown_state(&self, state: &DisplayState) -> LEDStripState {
+        match &self.zone {
+            Some(zone) => state.status.zones.get(zone).copied().unwrap_or(state.own_state),
+            None => state.status.state
         }
     }
 
+    /// Lights a single LED every now and then while the strip is dark.
+    pub fn with_heartbeat(mut self, heartbeat: Option<HeartbeatConfig>) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Shows the LEDs as laid out on the strip.
+    pub fn with_layout(mut self, layout: &StripLayout) -> Self {
+        let count = self.controller.borrow_mut().leds_mut(self.channel).len();
+        self.positions = layout.positions(count);
+        self
+    }
+
+    /// Corrects the colors sent to the strip.
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// Shows the states in the given RGB colors, in the order of [LEDStripState::ALL].
+    pub fn with_colors(mut self, colors: [[u8; 3]; 5]) -> Self {
+        self.colors = colors.map(|[red, green, blue]| [blue, green, red, 0]);
+        self
+    }
+
 }
 
 impl Drop for RPILedController {
     fn drop(&mut self) {
-        self.set_all_to(RPILedController::BLACK);
+        if let Err(e) = self.set_all_to(RPILedController::BLACK) {
+            log::error!("{}", e);
+        }
     }
 
-}
\ No newline at end of file
+}
+
+/// Stands in for the strip and the matrix on machines without them, logging the colors they would show.
+#[cfg(not(hardware))]
+pub(crate) mod stub {
+    pub type RawColor = [u8; 4];
+
+    pub enum StripType {
+        Ws2812
+    }
+
+    pub struct ChannelBuilder {
+        count: i32
+    }
+
+    impl ChannelBuilder {
+        pub fn new() -> Self {
+            ChannelBuilder { count: 0 }
+        }
+
+        pub fn pin(&mut self, _pin: i32) -> &mut Self {
+            self
+        }
+
+        pub fn count(&mut self, count: i32) -> &mut Self {
+            self.count = count;
+            self
+        }
+
+        pub fn strip_type(&mut self, _strip_type: StripType) -> &mut Self {
+            self
+        }
+
+        pub fn brightness(&mut self, _brightness: u8) -> &mut Self {
+            self
+        }
+
+        pub fn build(&mut self) -> i32 {
+            self.count
+        }
+    }
+
+    pub struct ControllerBuilder {
+        counts: Vec<i32>
+    }
+
+    impl ControllerBuilder {
+        pub fn new() -> Self {
+            ControllerBuilder { counts: Vec::new() }
+        }
+
+        pub fn freq(&mut self, _freq: u32) -> &mut Self {
+            self
+        }
+
+        pub fn dma(&mut self, _dma: i32) -> &mut Self {
+            self
+        }
+
+        pub fn channel(&mut self, index: usize, count: i32) -> &mut Self {
+            if self.counts.len() <= index {
+                self.counts.resize(index + 1, 0);
+            }
+            self.counts[index] = count;
+            self
+        }
+
+        pub fn build(&mut self) -> Result<Controller, String> {
+            Ok(Controller { leds: self.counts.iter().map(|count| vec![[0; 4]; *count as usize]).collect(), rendered: Vec::new() })
+        }
+    }
+
+    pub struct Controller {
+        leds: Vec<Vec<RawColor>>,
+        rendered: Vec<Vec<RawColor>>
+    }
+
+    impl Controller {
+        pub fn leds_mut(&mut self, channel: usize) -> &mut [RawColor] {
+            &mut self.leds[channel]
+        }
+
+        pub fn set_brightness(&mut self, channel: usize, brightness: u8) {
+            log::info!("Simulated strip brightness on channel {}: {}", channel, brightness);
+        }
+
+        pub fn render(&mut self) -> Result<(), String> {
+            if self.rendered != self.leds {
+                log::info!("Simulated strip: {:?}", self.leds);
+                self.rendered = self.leds.clone();
+            }
+            Ok(())
+        }
+    }
+}
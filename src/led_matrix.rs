@@ -0,0 +1,193 @@
+#[cfg(hardware)]
+use rs_ws281x::*;
+#[cfg(not(hardware))]
+use crate::led::stub::*;
+
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::display::{Display, DisplayState};
+
+const BRIGHTNESS: u8 = 30;
+const DIMMED_BRIGHTNESS: u8 = 6;
+const BLACK: RawColor = [0, 0, 0, 0];
+const WHITE: RawColor = [60, 60, 60, 0];
+/// Color of the digits, a dim white not to outshine the border
+const DIGIT_COLOR: RawColor = [30, 30, 30, 0];
+const DIGIT_WIDTH: usize = 3;
+const DIGIT_HEIGHT: usize = 5;
+/// Width of two digits, with a blank column between them
+const NUMBER_WIDTH: usize = 2 * DIGIT_WIDTH + 1;
+/// Rows of the 3x5 digits, the bits of each row from left to right
+const FONT: [[u8; DIGIT_HEIGHT]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111]
+];
+
+/// A matrix of WS2812 LEDs, such as the 8x8 Unicorn HAT or a 16x16 panel.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct MatrixConfig {
+    pub width: usize,
+    pub height: usize,
+    /// GPIO pin of the data line, the one of the strip by default, in which case the strip has to be disabled
+    pub pin: i32,
+    /// Whether every other row runs backwards, as on most matrices, the Unicorn HAT included
+    pub serpentine: bool
+}
+
+impl Default for MatrixConfig {
+    fn default() -> Self {
+        MatrixConfig { width: 8, height: 8, pin: 18, serpentine: true }
+    }
+}
+
+/// An LED matrix showing the hours since the last cleaning, capped at 99, inside a border in the color of the state,
+/// which follows the blink pattern. Like the strip, the matrix is dark during the night.
+pub struct MatrixDisplay {
+    controller: Controller,
+    width: usize,
+    height: usize,
+    serpentine: bool,
+    /// Colors of the states, in the order of [crate::reminder::LEDStripState::ALL]
+    colors: [RawColor; 5],
+    brightness: u8,
+    shown: Option<Vec<RawColor>>
+}
+
+impl MatrixDisplay {
+    /// Initializes the matrix, showing the states in the given RGB colors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the controller of the matrix cannot be initialized.
+    pub fn new(config: &MatrixConfig, colors: [[u8; 3]; 5]) -> Self {
+        let controller = ControllerBuilder::new()
+            .freq(800_000)
+            .dma(10)
+            .channel(
+                0,
+                ChannelBuilder::new()
+                    .pin(config.pin)
+                    .count((config.width * config.height) as i32)
+                    .strip_type(StripType::Ws2812)
+                    .brightness(BRIGHTNESS)
+                    .build(),
+            )
+            .build()
+            .expect("Could not initialize LED matrix controller");
+        MatrixDisplay {
+            controller,
+            width: config.width,
+            height: config.height,
+            serpentine: config.serpentine,
+            colors: colors.map(|[red, green, blue]| [blue, green, red, 0]),
+            brightness: BRIGHTNESS,
+            shown: None
+        }
+    }
+
+    /// Index on the wiring of the pixel at the given column and row.
+    fn index(&self, x: usize, y: usize) -> usize {
+        if self.serpentine && y % 2 == 1 {
+            y * self.width + self.width - 1 - x
+        } else {
+            y * self.width + x
+        }
+    }
+
+    fn show(&mut self, pixels: Vec<RawColor>) {
+        if self.shown.as_ref() == Some(&pixels) {
+            return;
+        }
+        let indices: Vec<usize> = (0..pixels.len()).map(|i| self.index(i % self.width, i / self.width)).collect();
+        let leds = self.controller.leds_mut(0);
+        for (index, color) in indices.into_iter().zip(&pixels) {
+            leds[index] = *color;
+        }
+        match self.controller.render() {
+            Ok(()) => self.shown = Some(pixels),
+            Err(e) => log::error!("Could not update the LED matrix: {:?}", e)
+        }
+    }
+}
+
+impl Display for MatrixDisplay {
+    fn name(&self) -> &str {
+        "matrix"
+    }
+
+    fn render(&mut self, state: &DisplayState) {
+        let full_brightness = if state.is_dimmed { DIMMED_BRIGHTNESS } else { BRIGHTNESS };
+        let brightness = ((full_brightness as f64 * state.brightness).round() as u8).max(1);
+        if brightness != self.brightness {
+            self.brightness = brightness;
+            self.controller.set_brightness(0, brightness);
+            // the brightness only applies on the next render
+            self.shown = None;
+        }
+        // an armed reset blinks the border white, even in the dark
+        let border = if state.is_reset_armed {
+            state.blink_on.then_some(WHITE)
+        } else {
+            state.blink_on.then_some(self.colors[state.status.state as usize])
+        };
+        let pixels = if state.is_dark && !state.is_reset_armed {
+            vec![BLACK; self.width * self.height]
+        } else {
+            let hours = Utc::now().signed_duration_since(state.status.last_cleaning_time).num_hours();
+            frame(self.width, self.height, hours, border)
+        };
+        self.show(pixels);
+    }
+
+    fn clear(&mut self) {
+        self.show(vec![BLACK; self.width * self.height]);
+    }
+}
+
+/// Pixels showing the hours inside the border, row by row from the top left. The digits are scaled up as far as they
+/// fit inside the border, matrices too small for that keeping the border to the top and bottom rows.
+fn frame(width: usize, height: usize, hours: i64, border: Option<RawColor>) -> Vec<RawColor> {
+    let mut pixels = vec![BLACK; width * height];
+    if width == 0 || height == 0 {
+        return pixels;
+    }
+    let scale = (width.saturating_sub(2) / NUMBER_WIDTH).min(height.saturating_sub(2) / DIGIT_HEIGHT);
+    if let Some(color) = border {
+        pixels[..width].fill(color);
+        pixels[(height - 1) * width..].fill(color);
+        if scale > 0 {
+            for y in 0..height {
+                pixels[y * width] = color;
+                pixels[y * width + width - 1] = color;
+            }
+        }
+    }
+    let scale = scale.max(1);
+    let hours = hours.clamp(0, 99) as usize;
+    let left = width.saturating_sub(NUMBER_WIDTH * scale) / 2;
+    let top = height.saturating_sub(DIGIT_HEIGHT * scale) / 2;
+    for (i, digit) in [hours / 10, hours % 10].into_iter().enumerate() {
+        for (row, bits) in FONT[digit].iter().enumerate() {
+            for column in (0..DIGIT_WIDTH).filter(|column| (bits >> (DIGIT_WIDTH - 1 - column)) & 1 == 1) {
+                for (dx, dy) in (0..scale).flat_map(|dx| (0..scale).map(move |dy| (dx, dy))) {
+                    let x = left + (i * (DIGIT_WIDTH + 1) + column) * scale + dx;
+                    let y = top + row * scale + dy;
+                    if x < width && y < height {
+                        pixels[y * width + x] = DIGIT_COLOR;
+                    }
+                }
+            }
+        }
+    }
+    pixels
+}
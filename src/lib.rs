@@ -0,0 +1,356 @@
+//! The Cat Litter Reminder, an annoying Raspberry PI with a LED Strip that signals when the cat litter box should be cleaned.
+//!
+//! Main features:
+//! - LEDs have different colors depending on how urgent it is to clean the litter box
+//! - start to be really annoying when a full day has passed (blink in red)
+//! - don't display any lights during the night
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, mpsc, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+
+use animation::Animation;
+use care::CareReminders;
+use clock::{Clock, ScaledClock, SystemClock};
+use diagnostics::DiagnosticsProbe;
+use health::HealthProbe;
+use history::HistoryEvent;
+use holiday::Holidays;
+use display::Display;
+use eink::EinkDisplay;
+use lcd::LcdDisplay;
+use led::RPILedController;
+use led_matrix::MatrixDisplay;
+use logging::LogFiles;
+use oled::OledDisplay;
+use pwm_led::PwmLedDisplay;
+use segment::SegmentDisplay;
+use terminal::TerminalDisplay;
+use reminder::{LEDStripState, Reminder, ReminderStatus};
+use supervisor::Supervisor;
+use transport::{NodeRole, Zoning};
+
+pub mod display;
+pub mod error;
+pub mod gpio;
+pub mod led;
+pub mod led_matrix;
+pub mod transport;
+pub mod protocol;
+pub mod discovery;
+pub mod network;
+pub mod pairing;
+pub mod presence;
+pub mod provisioning;
+pub mod reminder;
+pub mod clock;
+pub mod config;
+pub mod control;
+pub mod dbus;
+pub mod diagnostics;
+pub mod crash;
+pub mod health;
+pub mod selftest;
+pub mod setup;
+pub mod history;
+pub mod replay;
+pub mod holiday;
+pub mod logging;
+pub mod state;
+pub mod storage;
+pub mod supplies;
+pub mod supervisor;
+pub mod notifier;
+pub mod http;
+pub mod assistant;
+pub mod animation;
+pub mod calibrate;
+pub mod care;
+pub mod observation;
+pub mod influx;
+pub mod lamp;
+pub mod websocket;
+pub mod sound;
+pub mod relay;
+pub mod oled;
+pub mod palette;
+pub mod pwm_led;
+pub mod eink;
+pub mod encoder;
+pub mod input;
+pub mod ir_remote;
+pub mod segment;
+pub mod sensor;
+pub mod lcd;
+pub mod litter;
+pub mod terminal;
+pub mod thermal;
+pub mod ups;
+pub mod update;
+pub mod users;
+pub mod cli;
+pub mod export;
+pub mod gamification;
+pub mod backup;
+
+pub use led::LedController;
+pub use network::NetworkNode;
+pub use protocol::Message as Protocol;
+pub use reminder::Reminder as ReminderEngine;
+
+/// Runs the reminder with the configuration file in the working directory, until the process receives a termination signal.
+/// A node that does not get online asks for Wi-Fi credentials first, and a node that was never set up serves the setup
+/// wizard.
+/// The demo cycles through all states on a fast schedule instead, leaving the state, the history and the network alone,
+/// as does a time scale, which runs the clock of the reminder that many times faster.
+pub fn run(demo: bool, time_scale: Option<f64>) {
+    let dry_run = demo || time_scale.is_some();
+    let started = Instant::now();
+    let mut config = config::load();
+    if config.local_only {
+        for name in config.outbound_integrations() {
+            log::warn!("Local-only mode, leaving out the {} section", name);
+        }
+        config.disable_outbound_integrations();
+    }
+    let ip_addr = provisioning::ensure_online(&config.provisioning);
+    if !dry_run && setup::is_first_boot() {
+        setup::run();
+    }
+
+    let state_file = config::state_file().to_path_buf();
+    crash::install_hook(state_file.with_file_name(crash::CRASH_LOG_FILE_NAME), config.displays.strip);
+    let pairing_url = config.http.as_ref().and_then(|http_config| pairing::url(ip_addr, http_config));
+    let mut displays = match config.network.role {
+        NodeRole::Sensor => Vec::new(),
+        _ => displays(&config, pairing_url.as_deref())
+    };
+    if config.displays.startup_animation {
+        log::info!("Starting version {}", env!("CARGO_PKG_VERSION"));
+        displays.iter_mut().for_each(|d| d.start());
+    }
+    // a state left on the tmpfs is more recent than the flushed one, unless the node rebooted
+    let mut state = match &config.storage.hot_state_file {
+        Some(hot_state_file) if hot_state_file.exists() => state::load(hot_state_file),
+        _ => state::load(&state_file)
+    };
+    let last_cleaning_time: DateTime<Utc> = state.last_cleaning_time();
+    for (name, box_state) in &state.boxes {
+        if let Some(profile) = box_state.litter {
+            litter::select(name, profile);
+        }
+    }
+
+    let (reminder_tx, reminder_rx) = mpsc::channel();
+
+    let shutdown_flag = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, shutdown_flag.clone()).unwrap();
+    signal_hook::flag::register(signal_hook::consts::SIGINT, shutdown_flag.clone()).unwrap();
+    signal_hook::flag::register(signal_hook::consts::SIGQUIT, shutdown_flag.clone()).unwrap();
+    logging::reload_on_hangup(shutdown_flag.clone());
+
+    // the integrations talking to anything but the other nodes, as they get started
+    let mut outbound_started = Vec::new();
+    let (event_tx, event_rx) = mpsc::channel();
+    match config.websocket {
+        Some(websocket_config) => {
+            outbound_started.push("websocket");
+            websocket::run(ip_addr, websocket_config, event_rx, shutdown_flag.clone())
+        }
+        None => drop(event_rx)
+    }
+
+    let status = Arc::new(RwLock::new(ReminderStatus { last_cleaning_time, state: LEDStripState::LightGreen,
+        due_time: config::schedule().due_time(state::DEFAULT_BOX, last_cleaning_time), assignee: None, climate: None, cpu_temperature: None,
+        snoozed_until: None, quiet: false, guest_until: None, in_progress_since: None, litter: Default::default(), next_full_change: None, bag_runs_out: None,
+        care_due: Vec::new(), zones: BTreeMap::new(), failing: BTreeSet::new(), scoops: None }));
+
+    let mut supervisor = Supervisor::new(shutdown_flag.clone());
+    let peer_count = Arc::new(AtomicUsize::new(0));
+    let node = NetworkNode { ip_addr, discovery_port: 5200, transport_port: 5300, peer_cache: state_file.with_file_name(discovery::PEER_CACHE_FILE_NAME),
+        encoding: config.network.encoding, zoning: Zoning { zone: config.network.zone.clone(), hub: config.network.hub }, role: config.network.role };
+    let transport_tx = if dry_run {
+        mpsc::channel().0
+    } else {
+        node.start(reminder_tx.clone(), event_tx.clone(), status.clone(), peer_count.clone(), &mut supervisor)
+    };
+    supervisor.run(reminder_tx.clone());
+    if let Some(http_config) = &config.http {
+        let probe = DiagnosticsProbe {
+            health: HealthProbe { strip: config.displays.strip, state_file: state_file.clone(), peer_count: Some(peer_count.clone()) },
+            // without log files, the journal is read
+            log_files: config.logging.file.is_some().then(|| LogFiles::new(&config)),
+            peer_cache: state_file.with_file_name(discovery::PEER_CACHE_FILE_NAME),
+            started
+        };
+        outbound_started.push("http");
+        http::run(ip_addr, http_config.clone(), config.gamification.clone(), probe, status.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    control::run(control::socket_path(&config), status.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    if let Some(dbus_config) = config.dbus {
+        dbus::run(dbus_config, status.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(influx_config) = config.influxdb {
+        outbound_started.push("influxdb");
+        influx::run(influx_config, status.clone(), shutdown_flag.clone());
+    }
+    if let Some(lamp_config) = config.lamp {
+        outbound_started.push("lamp");
+        lamp::run(lamp_config, config.notifications.quiet_hours.clone(), status.clone(), shutdown_flag.clone());
+    }
+    if let Some(relay_config) = config.relay {
+        relay::run(relay_config, status.clone(), shutdown_flag.clone());
+    }
+    if let Some(sound_config) = config.sounds {
+        sound::run(sound_config, config.notifications.quiet_hours.clone(), status.clone(), shutdown_flag.clone());
+    }
+
+    let (notifier_tx, notifier_rx) = mpsc::channel();
+    // without notifiers, the notifier thread only consumes the events
+    if !config.notifiers.is_empty() {
+        outbound_started.push("notifiers");
+    }
+    notifier::run(config.notifications, config.notifiers, config.gamification.clone(), reminder_tx.clone(), notifier_rx, shutdown_flag.clone());
+
+    sensor::run(&config.sensors, reminder_tx.clone(), shutdown_flag.clone());
+    // display-only nodes have no button
+    if config.network.role != NodeRole::Display {
+        let inputs = if config.inputs.is_empty() { input::default_inputs() } else { config.inputs.clone() };
+        if let Err(e) = input::run(inputs, reminder_tx.clone(), shutdown_flag.clone()) {
+            log::error!("The inputs are not available: {}", e);
+        }
+    }
+    if let Some(thermal_config) = &config.thermal {
+        thermal::run(thermal_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    let ups = config.ups.as_ref().map(|ups_config| ups::run(ups_config.clone(), reminder_tx.clone(), shutdown_flag.clone()));
+    if let Some(encoder_config) = &config.encoder {
+        encoder::run(encoder_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(ir_remote_config) = &config.ir_remote {
+        ir_remote::run(ir_remote_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(presence_config) = &config.presence {
+        outbound_started.push("presence");
+        presence::run(presence_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(update_config) = &config.update {
+        outbound_started.push("update");
+        update::run(update_config.clone(), shutdown_flag.clone());
+    }
+    if config.local_only {
+        if outbound_started.is_empty() {
+            log::info!("Local-only mode, only talking to the other nodes over mDNS and the transport");
+        } else {
+            log::error!("Local-only mode, but started {}", outbound_started.join(", "));
+        }
+    }
+
+    if let Err(e) = history::migrate() {
+        log::error!("Could not move the history next to the state: {}", e);
+    }
+    match history::prune(&config.history, Utc::now()) {
+        Ok(0) => (),
+        Ok(count) => log::info!("Dropped {} history entries past the retention period", count),
+        Err(e) => log::error!("Could not prune the history: {}", e)
+    }
+    let history = history::load().unwrap_or_default();
+    let rotation_index = state.rotation_index.unwrap_or_else(|| history.iter()
+        .filter(|e| e.event == HistoryEvent::Cleaned)
+        .fold(0, |index, e| reminder::next_rotation_index(&config.chores.members, index, e.by.as_deref())));
+    let snoozed_until = if state.paused { Some(DateTime::<Utc>::MAX_UTC) } else { state.snoozed_until };
+    let guest_until = state.guest_until;
+    // care reminders are due after their first interval
+    for care_config in &config.care {
+        state.care.entry(care_config.name.clone()).or_insert_with(Utc::now);
+    }
+    // the reminder only keeps the ones of the current day
+    let scoops = history.iter().filter(|e| e.event == HistoryEvent::Cleaned && Utc::now() - e.time < chrono::Duration::days(1)).map(|e| e.time).collect();
+    let visits = history.iter().rev().take_while(|e| e.event != HistoryEvent::Cleaned).filter(|e| e.event == HistoryEvent::CatVisited).count();
+    let visits_per_escalation = config.sensors.pir.as_ref().and_then(|pir| pir.visits_per_escalation);
+    let blending = config.sensors.ammonia.as_ref().map(|ammonia| ammonia.blending).unwrap_or_default();
+
+    let (storage_tx, storage_rx) = mpsc::channel();
+    let storage = storage::run(config.storage.clone(), state_file, storage_rx);
+
+    let clock: Arc<dyn Clock> = match time_scale {
+        Some(scale) => {
+            log::info!("Running the clock {} times faster", scale);
+            Arc::new(ScaledClock::new(scale))
+        }
+        None => Arc::new(SystemClock)
+    };
+    let mut reminder = Reminder {
+        displays, storage_tx, state, reminder_rx, transport_tx, notifier_tx, event_tx, last_cleaning_time, last_state: None, status, peer_count,
+        members: config.chores.members, rotation_index, visits, visits_per_escalation,
+        smell: None, blending, climate: None, ambient_light: None, cpu_temperature: None, throttling: None, on_battery: false, nobody_home: false,
+        snoozed_until, guest_until, snooze_selection: None, selected_box: None, reset_armed: false, full_change_due: false, supplies: config.supplies, supplies_low: false,
+        care: CareReminders::new(config.care), animation: Animation::new(config.displays.patterns.clone()), overnight_states: None, in_progress_since: None, failing_subsystems: BTreeSet::new(), scoops,
+        holidays: Holidays::new(config.holidays.unwrap_or_default()), clock,
+        demo_since: demo.then(Utc::now), dry_run
+    };
+    reminder.run(shutdown_flag.clone());
+    // dropping the reminder closes the storage channel, upon which everything pending gets flushed
+    drop(reminder);
+    storage.join().expect("Storage thread panicked");
+    // the node is only shut down on low battery once everything pending was written
+    if let Some(command) = ups.and_then(|ups| ups.join().expect("UPS thread panicked")) {
+        ups::shut_down(&command);
+    }
+}
+
+/// Initializes the displays enabled in the configuration.
+pub(crate) fn displays(config: &config::Config, pairing_url: Option<&str>) -> Vec<Box<dyn Display>> {
+    let mut displays: Vec<Box<dyn Display>> = Vec::new();
+    if config.displays.strip {
+        let colors = palette::state_colors(config.displays.palette, &config.displays.colors);
+        let (strip, second_strip) = match &config.displays.second_strip {
+            Some(second) => {
+                let (strip, second_strip) = RPILedController::with_led_counts(config.displays.led_count, second.led_count);
+                (strip, Some(second_strip.with_layout(&second.layout).with_zone(second.zone.clone())))
+            }
+            None => (RPILedController::with_led_count(config.displays.led_count), None)
+        };
+        displays.push(Box::new(strip.with_colors(colors)
+            .with_calibration(config.displays.calibration)
+            .with_layout(&config.displays.layout)
+            .with_heartbeat(config.displays.heartbeat)
+            .with_zone(config.displays.zone.clone())
+            .with_progress(config.displays.progress)));
+        if let Some(second_strip) = second_strip {
+            displays.push(Box::new(second_strip.with_colors(colors)
+                .with_calibration(config.displays.calibration)
+                .with_heartbeat(config.displays.heartbeat)
+                .with_progress(config.displays.progress)));
+        }
+    }
+    if config.displays.terminal {
+        displays.push(Box::new(TerminalDisplay::default()));
+    }
+    if let Some(oled_config) = &config.oled {
+        displays.push(Box::new(OledDisplay::new(oled_config, pairing_url)));
+    }
+    if let Some(eink_config) = &config.eink {
+        displays.push(Box::new(EinkDisplay::new(eink_config)));
+    }
+    if let Some(lcd_config) = &config.lcd {
+        displays.push(Box::new(LcdDisplay::new(lcd_config)));
+    }
+    if let Some(segment_config) = &config.segment {
+        displays.push(Box::new(SegmentDisplay::new(segment_config)));
+    }
+    if let Some(matrix_config) = &config.led_matrix {
+        let colors = palette::state_colors(config.displays.palette, &config.displays.colors);
+        displays.push(Box::new(MatrixDisplay::new(matrix_config, colors)));
+    }
+    if let Some(pwm_led_config) = &config.pwm_led {
+        let colors = palette::state_colors(config.displays.palette, &config.displays.colors);
+        displays.push(Box::new(PwmLedDisplay::new(pwm_led_config, colors)));
+    }
+    log::info!("Displays: {}", displays.iter().map(|d| d.name()).collect::<Vec<_>>().join(", "));
+    displays
+}
+
@@ -0,0 +1,77 @@
+//! Litter profiles: how often the litter box needs to be scooped and the litter replaced entirely depends a lot on the
+//! litter. The profile of a box is configured in the schedule, and can be changed at runtime, e.g. from the HTTP API,
+//! in which case it is kept in the state.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+/// The profiles selected at runtime, by box, overriding the configured ones.
+static SELECTED: RwLock<BTreeMap<String, LitterProfile>> = RwLock::new(BTreeMap::new());
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LitterProfile {
+    #[default]
+    ClumpingClay,
+    /// Silica crystals, which absorb the urine so that only the solids need to be scooped
+    Silica,
+    /// Wood or paper pellets, which fall apart to sawdust that needs to be sifted out
+    Pellets
+}
+
+impl LitterProfile {
+    /// Factor applied to the thresholds of the schedule, which are meant for clumping clay.
+    pub fn scoop_factor(&self) -> f64 {
+        match self {
+            LitterProfile::ClumpingClay => 1.0,
+            LitterProfile::Silica => 1.5,
+            LitterProfile::Pellets => 0.75
+        }
+    }
+
+    /// Days after which the litter needs to be replaced entirely.
+    pub fn full_change_days(&self) -> i64 {
+        match self {
+            LitterProfile::ClumpingClay => 14,
+            LitterProfile::Silica => 28,
+            LitterProfile::Pellets => 7
+        }
+    }
+}
+
+impl fmt::Display for LitterProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LitterProfile::ClumpingClay => write!(f, "clumping_clay"),
+            LitterProfile::Silica => write!(f, "silica"),
+            LitterProfile::Pellets => write!(f, "pellets")
+        }
+    }
+}
+
+impl FromStr for LitterProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clumping_clay" => Ok(LitterProfile::ClumpingClay),
+            "silica" => Ok(LitterProfile::Silica),
+            "pellets" => Ok(LitterProfile::Pellets),
+            _ => Err(format!("Unknown litter {}, expected clumping_clay, silica or pellets", s))
+        }
+    }
+}
+
+/// Selects the profile of the box, overriding the configured one.
+pub fn select(name: &str, profile: LitterProfile) {
+    SELECTED.write().unwrap().insert(name.to_string(), profile);
+}
+
+/// The profile selected at runtime for the box, if any.
+pub fn selected(name: &str) -> Option<LitterProfile> {
+    SELECTED.read().unwrap().get(name).copied()
+}
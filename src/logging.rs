@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::Deserialize;
+use tracing::Subscriber;
+use tracing_subscriber::{EnvFilter, fmt, Layer, reload, Registry};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::config::Config;
+use crate::state::STATE_FILE_PATH;
+
+const RELOAD_POLL_DELAY: Duration = Duration::from_millis(1000);
+const FOLLOW_POLL_DELAY: Duration = Duration::from_millis(500);
+/// Kept in the log directory, the rotated files get a `.1`, `.2`, ... suffix
+const LOG_FILE_NAME: &str = "cat-reminder.log";
+
+/// Log levels and format. Levels can be set per module, e.g. `"cat_reminder::transport" = "debug"`, and are reloaded
+/// on `SIGHUP`.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub level: String,
+    pub modules: BTreeMap<String, String>,
+    pub format: LogFormat,
+    /// Also writes the logs to rotating files, which survive reboots unlike a volatile journal
+    pub file: Option<LogFileConfig>
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig { level: "info".to_string(), modules: BTreeMap::new(), format: LogFormat::Text, file: None }
+    }
+}
+
+/// Log files are rotated when they reach the maximum size, and on the first write of a new day if enabled.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct LogFileConfig {
+    /// Directory of the log files, `logs` next to the state file by default
+    pub directory: Option<PathBuf>,
+    pub max_size_kb: u64,
+    pub rotate_daily: bool,
+    /// Number of rotated files kept besides the current one
+    pub keep: usize
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        LogFileConfig { directory: None, max_size_kb: 1024, rotate_daily: true, keep: 7 }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    Text,
+    /// One JSON document per line, for log shippers
+    Json
+}
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Builds the filter from the configured levels. The `RUST_LOG` environment variable, if set, takes precedence.
+fn filter(config: &LoggingConfig) -> EnvFilter {
+    if let Ok(filter) = EnvFilter::try_from_default_env() {
+        return filter;
+    }
+    let directives = std::iter::once(config.level.clone())
+        .chain(config.modules.iter().map(|(module, level)| format!("{}={}", module, level)))
+        .collect::<Vec<_>>()
+        .join(",");
+    EnvFilter::try_new(&directives).unwrap_or_else(|e| {
+        eprintln!("Invalid log levels '{}': {}", directives, e);
+        EnvFilter::new("info")
+    })
+}
+
+/// Formats the records for the writer.
+fn layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static
+{
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi).with_thread_names(true);
+    match format {
+        LogFormat::Text => Box::new(layer),
+        LogFormat::Json => Box::new(layer.json())
+    }
+}
+
+/// Installs the subscriber, which also receives the records of the `log` macros. The logs are written to the
+/// standard error, and to the log files if enabled.
+pub fn init(config: &Config) {
+    let logging = &config.logging;
+    let (filter, handle) = reload::Layer::new(filter(logging));
+    let mut layers = vec![layer(logging.format, io::stderr, true)];
+    if let Some(file_config) = &logging.file {
+        match RotatingFile::open(directory(config), file_config) {
+            Ok(file) => layers.push(layer(logging.format, Mutex::new(file), false)),
+            Err(e) => eprintln!("Could not open the log file in {}: {}", directory(config).display(), e)
+        }
+    }
+    if tracing_subscriber::registry().with(filter).with(layers).try_init().is_ok() {
+        let _ = FILTER.set(handle);
+    }
+}
+
+/// The directory of the log files.
+pub fn directory(config: &Config) -> PathBuf {
+    config.logging.file.as_ref().and_then(|file| file.directory.clone()).unwrap_or_else(|| {
+        config.state_file.clone().unwrap_or_else(|| PathBuf::from(STATE_FILE_PATH)).with_file_name("logs")
+    })
+}
+
+/// Reloads the levels from the configuration file whenever the process receives `SIGHUP`. The format is kept.
+pub fn reload_on_hangup(shutdown_flag: Arc<AtomicBool>) {
+    let hangup = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, hangup.clone()).expect("Could not register SIGHUP handler");
+    std::thread::Builder::new().name("logging".to_string()).spawn(move || {
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(RELOAD_POLL_DELAY);
+            if hangup.swap(false, Ordering::Relaxed) {
+                if let Some(handle) = FILTER.get() {
+                    match handle.reload(filter(&crate::config::load().logging)) {
+                        Ok(()) => tracing::info!("Reloaded the log levels"),
+                        Err(e) => tracing::error!("Could not reload the log levels: {}", e)
+                    }
+                }
+            }
+        }
+    }).expect("Could not start logging thread");
+}
+
+/// A log file that is rotated by size and day, dropping the oldest files beyond the number to keep.
+struct RotatingFile {
+    directory: PathBuf,
+    max_size: u64,
+    rotate_daily: bool,
+    keep: usize,
+    file: File,
+    size: u64,
+    opened: NaiveDate
+}
+
+impl RotatingFile {
+    fn open(directory: PathBuf, config: &LogFileConfig) -> io::Result<RotatingFile> {
+        fs::create_dir_all(&directory)?;
+        let file = OpenOptions::new().create(true).append(true).open(directory.join(LOG_FILE_NAME))?;
+        let metadata = file.metadata()?;
+        // a file left from an earlier day gets rotated on the first write
+        let opened = metadata.modified().map(|modified| DateTime::<Local>::from(modified).date_naive()).unwrap_or_else(|_| Local::now().date_naive());
+        Ok(RotatingFile { directory, max_size: config.max_size_kb * 1024, rotate_daily: config.rotate_daily, keep: config.keep, file, size: metadata.len(), opened })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let path = self.directory.join(LOG_FILE_NAME);
+        let _ = fs::remove_file(rotated(&path, self.keep));
+        for index in (1..self.keep).rev() {
+            let from = rotated(&path, index);
+            if from.exists() {
+                fs::rename(from, rotated(&path, index + 1))?;
+            }
+        }
+        if self.keep > 0 {
+            fs::rename(&path, rotated(&path, 1))?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&path)?;
+        self.size = 0;
+        self.opened = Local::now().date_naive();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let full = self.size > 0 && self.size + buf.len() as u64 > self.max_size;
+        let new_day = self.rotate_daily && Local::now().date_naive() != self.opened;
+        if full || new_day {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn rotated(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// The current and rotated log files.
+pub struct LogFiles {
+    pub path: PathBuf,
+    pub keep: usize
+}
+
+impl LogFiles {
+    pub fn new(config: &Config) -> Self {
+        LogFiles { path: directory(config).join(LOG_FILE_NAME), keep: config.logging.file.as_ref().map(|file| file.keep).unwrap_or_default() }
+    }
+
+    /// The last lines of the logs, including the rotated files if the current one is shorter.
+    pub fn last_lines(&self, lines: usize) -> io::Result<Vec<String>> {
+        let mut last: VecDeque<String> = VecDeque::with_capacity(lines);
+        let files = (1..=self.keep).rev().map(|index| rotated(&self.path, index)).chain(std::iter::once(self.path.clone()));
+        for file in files.filter(|file| file.exists()) {
+            for line in BufReader::new(File::open(file)?).lines() {
+                if last.len() == lines {
+                    last.pop_front();
+                }
+                if lines > 0 {
+                    last.push_back(line?);
+                }
+            }
+        }
+        Ok(last.into())
+    }
+}
+
+/// Prints the last lines of the logs, including the rotated files if the current one is shorter. When following,
+/// the lines written afterwards are printed as well, across rotations, until the process is interrupted.
+pub fn tail(config: &Config, lines: usize, follow: bool) -> io::Result<()> {
+    let log_files = LogFiles::new(config);
+    let path = &log_files.path;
+    let mut stdout = io::stdout().lock();
+    for line in log_files.last_lines(lines)? {
+        writeln!(stdout, "{}", line)?;
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    let mut position = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or_default();
+    loop {
+        stdout.flush()?;
+        sleep(FOLLOW_POLL_DELAY);
+        let Ok(mut file) = File::open(&path) else { continue };
+        let size = file.metadata()?.len();
+        if size < position {
+            // the file was rotated, the new one is printed from the start
+            position = 0;
+        }
+        if size > position {
+            file.seek(SeekFrom::Start(position))?;
+            position += io::copy(&mut (&mut file).take(size - position), &mut stdout)?;
+        }
+    }
+}
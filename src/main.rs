@@ -1,79 +1,101 @@
-use std::fs;
-use std::path::Path;
-use std::io::Error;
-use std::io::ErrorKind::InvalidData;
-use std::sync::{Arc, mpsc};
-use std::sync::atomic::AtomicBool;
+use chrono::Utc;
+use clap::Parser;
 
-use chrono::{DateTime, Utc};
-use gpiod::{Chip};
+use cat_reminder::{backup, calibrate, config, control, export, gamification, health, history, logging, pairing, replay, selftest};
+use cat_reminder::control::ControlCommand;
+use cat_reminder::cli::{Cli, Command};
 
-use led::RPILedController;
-use reminder::Reminder;
-
-mod led;
-mod transport;
-mod protocol;
-mod discovery;
-mod reminder;
-
-const STATE_FILE_PATH: &str = "cat_reminder_state";
-
-/// The Cat Litter Reminder, an annoying Raspberry PI with a LED Strip that signals when the cat litter box should be cleaned.
-///
-/// Main features:
-/// - LEDs have different colors depending on how urgent it is to clean the litter box
-/// - start to be really annoying when a full day has passed (blink in red)
-/// - don't display any lights during the night
 fn main() {
-    env_logger::init();
-
-    let chip: Chip = Chip::new("gpiochip0").expect("Cannot open GPIO");
-    let controller = RPILedController::new();
-    let last_cleaning_time: DateTime<Utc> = load_state();
-
-    let ip_addr = local_ip_address::local_ip().expect("Could not resolve local IP address");
-
-    let (reminder_tx, reminder_rx) = mpsc::channel();
-    let (transport_tx, transport_rx) = mpsc::channel();
-
-    let shutdown_flag = Arc::new(AtomicBool::new(false));
-    signal_hook::flag::register(signal_hook::consts::SIGTERM, shutdown_flag.clone()).unwrap();
-    signal_hook::flag::register(signal_hook::consts::SIGINT, shutdown_flag.clone()).unwrap();
-    signal_hook::flag::register(signal_hook::consts::SIGQUIT, shutdown_flag.clone()).unwrap();
-
-    discovery::run(ip_addr, 5200, transport_tx.clone(), shutdown_flag.clone());
-    transport::run(ip_addr, 5300, reminder_tx, transport_rx, last_cleaning_time, shutdown_flag.clone());
-
-    let mut reminder = Reminder { chip, controller, reminder_rx, transport_tx, last_cleaning_time, is_strip_on: false };
-    reminder.run(shutdown_flag.clone());
-}
-
-
-
-
-/// Loads the cat litter state (i.e. the last time at which the cat litter has been cleaned) from a file.
-fn load_state() -> DateTime<Utc> {
-    if Path::new(STATE_FILE_PATH).exists() {
-        let time_str = fs::read_to_string(STATE_FILE_PATH);
-
-        let parsed_time = time_str
-            .and_then(|str| DateTime::parse_from_rfc3339(&*str).map_err(|e| Error::new(InvalidData, e)))
-            .map(|t| t.with_timezone(&Utc));
-
-        parsed_time.unwrap_or_else(|err| {
-            log::error!("Error reading time from state: {:?}", err);
-            Utc::now().to_owned()
-        })
-    } else {
-        reset_state()
+    logging::init(&config::load());
+
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => cat_reminder::run(cli.demo, cli.time_scale),
+        Command::Export { format, since, output } => {
+            if let Err(e) = export::run(format, since, output) {
+                eprintln!("Export failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Replay { file, since } => {
+            if let Err(e) = replay::run(file.as_deref(), since) {
+                eprintln!("Replay failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Health => {
+            if !health::report() {
+                std::process::exit(1);
+            }
+        }
+        Command::Selftest => {
+            if !selftest::report() {
+                std::process::exit(1);
+            }
+        }
+        Command::Calibrate => calibrate::run(),
+        Command::Backup { file } => {
+            if let Err(e) = backup::backup(&file) {
+                eprintln!("Backup failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Restore { file } => {
+            match backup::restore(&file) {
+                Ok(created) => eprintln!("Restored the backup from {}", created.to_rfc3339()),
+                Err(e) => {
+                    eprintln!("Restore failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Status => send(ControlCommand::Status),
+        Command::Reset { by } => send(ControlCommand::Reset { by }),
+        Command::Snooze { minutes, by } => send(ControlCommand::Snooze { minutes, by }),
+        Command::Pause { by } => send(ControlCommand::Pause { by }),
+        Command::Resume { by } => send(ControlCommand::Resume { by }),
+        Command::SetTime { time, by } => send(ControlCommand::SetTime { time, by }),
+        Command::Update { fleet } => send(ControlCommand::Update { fleet }),
+        Command::Guest { hours } => send(ControlCommand::Guest { hours }),
+        Command::LitterReplaced { by } => send(ControlCommand::LitterReplaced { by }),
+        Command::Litter { profile } => send(ControlCommand::Litter { profile }),
+        Command::BagOpened { by } => send(ControlCommand::BagOpened { by }),
+        Command::CareDone { name, by } => send(ControlCommand::CareDone { name, by }),
+        Command::Pair => {
+            let config = config::load();
+            let ip_addr = local_ip_address::local_ip().ok();
+            let url = config.http.as_ref().zip(ip_addr).and_then(|(http_config, ip_addr)| pairing::url(ip_addr, http_config));
+            match url.as_deref().and_then(pairing::code) {
+                Some(code) => println!("{}\n{}", pairing::terminal(&code), url.unwrap_or_default()),
+                None => {
+                    eprintln!("Pairing needs the HTTP server with a token, and a network address");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Leaderboard => {
+            let gamification = config::load().gamification.unwrap_or_default();
+            match history::load() {
+                Ok(entries) => println!("{}", gamification::format(&gamification::leaderboard(&gamification, &entries, Utc::now()))),
+                Err(e) => {
+                    eprintln!("Could not load the history: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Logs { lines, follow } => {
+            if let Err(e) = logging::tail(&config::load(), lines, follow) {
+                eprintln!("Could not read the logs: {}", e);
+                std::process::exit(1);
+            }
+        }
     }
 }
 
-/// Resets the state, i.e. sets the time at which the cat litter has been cleaned to now.
-pub fn reset_state() -> DateTime<Utc> {
-    let now = Utc::now();
-    fs::write(STATE_FILE_PATH, now.to_rfc3339()).unwrap();
-    now
+/// Sends a command to the running reminder through the control socket.
+fn send(command: ControlCommand) {
+    if let Err(e) = control::send(&control::socket_path(&config::load()), &command) {
+        eprintln!("Could not control the reminder: {}", e);
+        std::process::exit(1);
+    }
 }
-
@@ -0,0 +1,63 @@
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, mpsc, Mutex, RwLock};
+use std::sync::atomic::AtomicUsize;
+use std::sync::mpsc::Sender;
+
+use serde::Deserialize;
+
+use crate::discovery;
+use crate::protocol::Encoding;
+use crate::reminder::{ReminderEvent, ReminderStatus};
+use crate::supervisor::Supervisor;
+use crate::transport::{self, NodeRole, TransportEvent, Zoning};
+use crate::websocket::StreamEvent;
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Encoding of the messages sent to nodes that have not sent anything yet, `json` for nodes not written in Rust
+    pub encoding: Encoding,
+    /// Zone of the node, e.g. `upstairs`, which only shows and syncs the litter box of the nodes of the same zone
+    pub zone: Option<String>,
+    /// Whether the node shows the litter boxes of all zones, whichever is the most urgent, along with its own
+    pub hub: bool,
+    /// `display` for a node without a button, which leaves it to the other nodes to answer for the state, or `sensor`
+    /// for a node without displays
+    pub role: NodeRole
+}
+
+/// A node of the reminder network, which discovers the other nodes over mDNS and keeps the cleaning time in sync with them.
+pub struct NetworkNode {
+    pub ip_addr: IpAddr,
+    pub discovery_port: u16,
+    pub transport_port: u16,
+    /// File in which the last known nodes are kept across restarts
+    pub peer_cache: PathBuf,
+    pub encoding: Encoding,
+    pub zoning: Zoning,
+    pub role: NodeRole
+}
+
+impl NetworkNode {
+    /// Starts the discovery and the transport under the supervisor, returning the sender through which local resets
+    /// are propagated. The cleaning times received from other nodes are sent to the reminder.
+    pub fn start(self, reminder_tx: Sender<ReminderEvent>, event_tx: Sender<StreamEvent>, status: Arc<RwLock<ReminderStatus>>, peer_count: Arc<AtomicUsize>, supervisor: &mut Supervisor) -> Sender<TransportEvent> {
+        let (transport_tx, transport_rx) = mpsc::channel();
+        let transport_rx = Arc::new(Mutex::new(transport_rx));
+        let shutdown_flag = supervisor.shutdown_flag();
+
+        let discovery_tx = transport_tx.clone();
+        let discovery_shutdown_flag = shutdown_flag.clone();
+        let discovery_zoning = self.zoning.clone();
+        supervisor.supervise("discovery", move || {
+            discovery::run(self.ip_addr, self.discovery_port, self.peer_cache.clone(), discovery_zoning.clone(), self.role, discovery_tx.clone(), peer_count.clone(), discovery_shutdown_flag.clone())
+        });
+        supervisor.supervise("transport", move || {
+            // a restarted transport starts from the current cleaning time
+            let last_cleaning_time = status.read().unwrap().last_cleaning_time;
+            transport::run(self.ip_addr, self.transport_port, reminder_tx.clone(), event_tx.clone(), transport_rx.clone(), last_cleaning_time, self.encoding, self.zoning.clone(), self.role, shutdown_flag.clone())
+        });
+        transport_tx
+    }
+}
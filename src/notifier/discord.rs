@@ -0,0 +1,155 @@
+use std::error::Error;
+use std::sync::mpsc::Sender;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::gamification::{self, GamificationConfig};
+use crate::history;
+use crate::reminder::{LEDStripState, ReminderEvent, ResetSource};
+use crate::users;
+
+use super::{format_duration, Notification, Notifier};
+
+const API_URL: &str = "https://discord.com/api/v10";
+
+#[derive(Deserialize, Clone)]
+pub struct DiscordConfig {
+    pub bot_token: String,
+    pub channel_id: String,
+    /// Interval at which the channel is polled for commands
+    #[serde(default = "default_poll_interval_seconds")]
+    pub poll_interval_seconds: i64
+}
+
+fn default_poll_interval_seconds() -> i64 {
+    5
+}
+
+#[derive(Deserialize)]
+struct DiscordMessage {
+    id: String,
+    content: String,
+    author: DiscordUser
+}
+
+#[derive(Deserialize)]
+struct DiscordUser {
+    username: String,
+    global_name: Option<String>,
+    #[serde(default)]
+    bot: bool
+}
+
+/// Discord bot posting urgency changes to a channel and answering the `!litter status` and `!litter done`
+/// commands posted to it, and `!litter leaderboard` if gamification is enabled. Requires the message content intent
+/// to be enabled for the bot.
+pub struct DiscordNotifier {
+    config: DiscordConfig,
+    reminder_tx: Sender<ReminderEvent>,
+    gamification: Option<GamificationConfig>,
+    current: Option<(LEDStripState, DateTime<Utc>)>,
+    last_message_id: Option<u64>,
+    last_poll: Option<DateTime<Utc>>
+}
+
+impl DiscordNotifier {
+    pub fn new(config: DiscordConfig, reminder_tx: Sender<ReminderEvent>, gamification: Option<GamificationConfig>) -> Self {
+        DiscordNotifier { config, reminder_tx, gamification, current: None, last_message_id: None, last_poll: None }
+    }
+
+    fn post(&self, content: &str) -> Result<(), Box<dyn Error>> {
+        ureq::post(&format!("{}/channels/{}/messages", API_URL, self.config.channel_id))
+            .set("Authorization", &format!("Bot {}", self.config.bot_token))
+            .send_json(serde_json::json!({ "content": content }))?;
+        Ok(())
+    }
+
+    /// Fetches the messages posted since the last poll and handles the commands among them.
+    fn poll_commands(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut request = ureq::get(&format!("{}/channels/{}/messages", API_URL, self.config.channel_id))
+            .set("Authorization", &format!("Bot {}", self.config.bot_token));
+        request = match self.last_message_id {
+            Some(id) => request.query("after", &id.to_string()).query("limit", "50"),
+            // don't replay commands posted before the start
+            None => request.query("limit", "1")
+        };
+        let mut messages: Vec<DiscordMessage> = request.call()?.into_json()?;
+        messages.sort_by_key(|m| m.id.parse::<u64>().unwrap_or(0));
+        let is_first_poll = self.last_message_id.is_none();
+        self.last_message_id = messages.last().and_then(|m| m.id.parse().ok()).or(self.last_message_id).or(Some(0));
+
+        if is_first_poll {
+            return Ok(());
+        }
+        for message in messages.iter().filter(|m| !m.author.bot) {
+            let user = users::by_chat_handle(&message.author.username).map(str::to_string)
+                .or_else(|| message.author.global_name.clone())
+                .unwrap_or_else(|| message.author.username.clone());
+            match message.content.trim() {
+                "!litter status" => self.post(&self.status())?,
+                "!litter leaderboard" => {
+                    if let Some(gamification) = &self.gamification {
+                        let standings = gamification::leaderboard(gamification, &history::load()?, Utc::now());
+                        self.post(&format!("**Leaderboard**\n{}", gamification::format(&standings)))?;
+                    }
+                }
+                "!litter done" => {
+                    log::info!("Reset requested by Discord user {}", user);
+                    self.reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Chat, Some(user.clone())))?;
+                    self.post(&format!("Thanks {}, the litter box has been marked as clean!", user))?;
+                }
+                _ => ()
+            }
+        }
+        Ok(())
+    }
+
+    fn status(&self) -> String {
+        match self.current {
+            Some((state, last_cleaning_time)) => format!("{} The litter box is {:?}, it was last cleaned {} ago.",
+                                                         emoji(state), state, format_duration(&Utc::now().signed_duration_since(last_cleaning_time))),
+            None => "The state of the litter box is not known yet.".to_string()
+        }
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        self.post(&format!("**{}**\n{}", notification.subject, notification.message))
+    }
+
+    fn state_changed(&mut self, state: LEDStripState, last_cleaning_time: DateTime<Utc>) {
+        let is_startup = self.current.is_none();
+        self.current = Some((state, last_cleaning_time));
+        if !is_startup {
+            if let Err(e) = self.post(&self.status()) {
+                log::error!("Could not post state change to Discord: {}", e);
+            }
+        }
+    }
+
+    fn tick(&mut self, now: DateTime<Utc>) {
+        if self.last_poll.is_some_and(|t| now.signed_duration_since(t) < Duration::seconds(self.config.poll_interval_seconds)) {
+            return;
+        }
+        self.last_poll = Some(now);
+        if let Err(e) = self.poll_commands() {
+            log::error!("Could not poll Discord commands: {}", e);
+        }
+    }
+}
+
+fn emoji(state: LEDStripState) -> &'static str {
+    match state {
+        LEDStripState::LightGreen => "🟩",
+        LEDStripState::DarkGreen => "🟢",
+        LEDStripState::Orange => "🟧",
+        LEDStripState::Red => "🟥",
+        LEDStripState::BlinkingRed => "🚨"
+    }
+}
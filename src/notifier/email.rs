@@ -0,0 +1,131 @@
+use std::error::Error;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use lettre::{Message, SmtpTransport, Transport};
+use lettre::transport::smtp::authentication::Credentials;
+use serde::Deserialize;
+
+use crate::config::schedule;
+use crate::history::{self, HistoryEntry, HistoryEvent};
+
+use super::{format_duration, Notification, Notifier};
+
+/// Configuration of the SMTP notification channel.
+#[derive(Deserialize, Clone)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub recipients: Vec<String>,
+    /// Whether to send a daily summary of cleanings and overdue periods
+    #[serde(default)]
+    pub daily_digest: bool,
+    /// Local time at which the daily digest is sent
+    #[serde(default = "default_digest_time")]
+    pub digest_time: NaiveTime
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_digest_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(20, 0, 0).unwrap()
+}
+
+/// Sends notifications by email and, if enabled, a daily digest at the configured time.
+pub struct EmailNotifier {
+    config: EmailConfig,
+    last_digest_date: NaiveDate
+}
+
+impl EmailNotifier {
+    pub fn new(config: EmailConfig) -> Self {
+        EmailNotifier { config, last_digest_date: Utc::now().with_timezone(&schedule().timezone).date_naive() }
+    }
+
+    /// Sends a plain-text email to all configured recipients.
+    fn send(&self, subject: &str, body: String) -> Result<(), Box<dyn Error>> {
+        let mut builder = Message::builder()
+            .from(self.config.from.parse()?)
+            .subject(subject);
+        for recipient in &self.config.recipients {
+            builder = builder.to(recipient.parse()?);
+        }
+        let message = builder.body(body)?;
+
+        let mut transport = SmtpTransport::starttls_relay(&self.config.smtp_host)?.port(self.config.smtp_port);
+        if let (Some(username), Some(password)) = (&self.config.username, &self.config.password) {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        transport.build().send(&message)?;
+        Ok(())
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        self.send(&notification.subject, notification.message.clone())
+    }
+
+    fn tick(&mut self, now: DateTime<Utc>) {
+        let local_now = now.with_timezone(&schedule().timezone);
+        if self.config.daily_digest && local_now.date_naive() > self.last_digest_date && local_now.time() >= self.config.digest_time {
+            self.last_digest_date = local_now.date_naive();
+            let result = history::load()
+                .map_err(|e| e.into())
+                .and_then(|entries| self.send("Daily litter box digest", digest(&entries, now - Duration::days(1), now)));
+            if let Err(e) = result {
+                log::error!("Could not send the daily digest: {}", e);
+            }
+        }
+    }
+}
+
+/// Summarizes the cleanings, with who cleaned, the snoozes and pauses, and the overdue periods between `from` and `to`.
+fn digest(entries: &[HistoryEntry], from: DateTime<Utc>, to: DateTime<Utc>) -> String {
+    let cleanings: Vec<&HistoryEntry> = entries.iter()
+        .filter(|e| e.event == HistoryEvent::Cleaned && e.time >= from && e.time <= to)
+        .collect();
+
+    let overdue_periods = history::overdue_periods(entries, from, to);
+
+    let snoozes: Vec<&HistoryEntry> = entries.iter()
+        .filter(|e| matches!(e.event, HistoryEvent::Snoozed(_) | HistoryEvent::Paused | HistoryEvent::Resumed) && e.time >= from && e.time <= to)
+        .collect();
+    let by = |entry: &HistoryEntry| entry.by.as_ref().map(|by| format!(" by {}", by)).unwrap_or_default();
+
+    let mut body = format!("Cleanings in the last 24 hours: {}\n", cleanings.len());
+    for cleaning in &cleanings {
+        body.push_str(&format!("  - {}{}\n", cleaning.time.with_timezone(&schedule().timezone).format("%H:%M"), by(cleaning)));
+    }
+    if let Some(counts) = history::count_by_member(cleanings.iter().copied()) {
+        body.push_str(&format!("By member: {}\n", counts));
+    }
+    if !snoozes.is_empty() {
+        body.push_str(&format!("\nSnoozes and pauses: {}\n", snoozes.len()));
+        for snooze in snoozes {
+            let what = match snooze.event {
+                HistoryEvent::Snoozed(until) => format!("snoozed until {}", until.with_timezone(&schedule().timezone).format("%H:%M")),
+                HistoryEvent::Paused => "paused".to_string(),
+                _ => "resumed".to_string()
+            };
+            body.push_str(&format!("  - {} {}{}\n", snooze.time.with_timezone(&schedule().timezone).format("%H:%M"), what, by(snooze)));
+        }
+    }
+    body.push_str(&format!("\nOverdue periods: {}\n", overdue_periods.len()));
+    for (start, end) in overdue_periods {
+        body.push_str(&format!("  - {} to {} ({})\n",
+                               start.with_timezone(&schedule().timezone).format("%H:%M"),
+                               end.with_timezone(&schedule().timezone).format("%H:%M"),
+                               format_duration(&(end - start))));
+    }
+    body
+}
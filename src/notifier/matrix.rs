@@ -0,0 +1,58 @@
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+use super::{Notification, Notifier};
+
+#[derive(Deserialize, Clone)]
+pub struct MatrixConfig {
+    /// Base URL of the homeserver. For end-to-end encrypted rooms, point this at an encryption-aware
+    /// proxy such as pantalaimon.
+    pub homeserver_url: String,
+    pub access_token: String,
+    pub room_id: String
+}
+
+/// Sends notifications as text messages to a Matrix room through the client-server API.
+pub struct MatrixNotifier {
+    config: MatrixConfig,
+    transaction_id: u64
+}
+
+impl MatrixNotifier {
+    pub fn new(config: MatrixConfig) -> Self {
+        // transaction ids need to be unique per access token, also across restarts
+        let transaction_id = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        MatrixNotifier { config, transaction_id }
+    }
+}
+
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &str {
+        "matrix"
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        self.transaction_id += 1;
+        let url = format!("{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+                          self.config.homeserver_url.trim_end_matches('/'),
+                          encode_path_segment(&self.config.room_id),
+                          self.transaction_id);
+        ureq::put(&url)
+            .set("Authorization", &format!("Bearer {}", self.config.access_token))
+            .send_json(serde_json::json!({
+                "msgtype": "m.text",
+                "body": format!("{}\n{}", notification.subject, notification.message)
+            }))?;
+        Ok(())
+    }
+}
+
+/// Percent-encodes everything but unreserved characters, as room ids contain `!` and `:`.
+fn encode_path_segment(segment: &str) -> String {
+    segment.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b)
+    }).collect()
+}
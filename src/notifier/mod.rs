@@ -0,0 +1,400 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc};
+use serde::Deserialize;
+
+use crate::config::schedule;
+use crate::gamification::GamificationConfig;
+use crate::history;
+use crate::reminder::{LEDStripState, ReminderEvent, ResetSource};
+use crate::sensor::Climate;
+
+mod discord;
+mod email;
+mod matrix;
+mod mqtt;
+mod signal;
+mod slack;
+mod speech;
+mod summary;
+mod telegram;
+mod webhook;
+
+const POLL_DELAY: StdDuration = StdDuration::from_millis(1000);
+
+pub enum NotifierEvent {
+    /// The new state, with the time of the last cleaning and whose turn it is, if the chores rotate
+    StateChanged(LEDStripState, DateTime<Utc>, Option<String>),
+    ClimateMeasured(Climate),
+    /// The litter box was cleaned, by whom and through what, if known
+    Cleaned(Option<String>, Option<ResetSource>),
+    /// No notifications are sent until the given time
+    Snoozed(DateTime<Utc>),
+    /// Whether someone is home, notifications waiting while nobody is
+    PresenceChanged(bool),
+    /// The litter is due to be replaced entirely, with the time at which it last was
+    FullChangeDue(DateTime<Utc>),
+    /// The bag of litter is about to run out, at the given time
+    SuppliesLow(DateTime<Utc>),
+    /// The care reminder with the given name is due
+    CareDue(String),
+    /// The states reached during the night, with the times they were reached, once it is over
+    Overnight(Vec<(LEDStripState, DateTime<Utc>)>),
+    /// The cat-sitter notified instead of the household during a trip, none once it is over
+    Sitter(Option<String>),
+    /// The time at which the litter box becomes overdue changed
+    DueTimeChanged(DateTime<Utc>)
+}
+
+/// An alert about the litter box, handed to every configured [Notifier].
+pub struct Notification {
+    pub state: LEDStripState,
+    pub last_cleaning_time: DateTime<Utc>,
+    pub subject: String,
+    pub message: String
+}
+
+/// A channel through which the household gets notified.
+pub trait Notifier: Send {
+    fn name(&self) -> &str;
+
+    /// Delivers the notification.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the notification could not be delivered.
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>>;
+
+    /// Called on every state change, regardless of the escalation policy.
+    fn state_changed(&mut self, _state: LEDStripState, _last_cleaning_time: DateTime<Utc>) {}
+
+    /// Called whenever the temperature and humidity are measured, for notifiers that publish them.
+    fn climate_measured(&mut self, _climate: Climate) {}
+
+    /// Called whenever the time at which the litter box becomes overdue changes, for notifiers that publish it.
+    fn due_time_changed(&mut self, _due_time: DateTime<Utc>) {}
+
+    /// Called periodically by the notifier thread, for notifiers that have scheduled work of their own.
+    fn tick(&mut self, _now: DateTime<Utc>) {}
+}
+
+/// Shared escalation policy deciding when notifications are sent.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct EscalationPolicy {
+    /// First state for which notifications are sent
+    pub notify_from: LEDStripState,
+    /// Interval at which notifications are repeated while the state doesn't improve, if any
+    pub renotify_interval_minutes: Option<i64>,
+    /// Local time range during which no notifications are sent. Notifications are delayed until the end.
+    pub quiet_hours: Option<QuietHours>,
+    /// Sends a summary of the week, regardless of the state and the quiet hours
+    pub weekly_summary: Option<summary::WeeklySummary>,
+    /// Minutes during which only the notifiers of the member whose turn it is are used, before the whole household
+    /// gets notified. Everyone is notified right away if not set, or if the member has no notifier of their own.
+    pub assignee_grace_minutes: Option<i64>,
+    /// Sends a summary of what happened during the night in the morning, if the state changed
+    pub morning_summary: bool
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        EscalationPolicy {
+            notify_from: LEDStripState::Red,
+            renotify_interval_minutes: None,
+            quiet_hours: Some(QuietHours {
+                start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(7, 0, 0).unwrap()
+            }),
+            weekly_summary: None,
+            assignee_grace_minutes: None,
+            morning_summary: false
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Configuration of a single notifier, as listed in the `[[notifiers]]` tables of the configuration file.
+#[derive(Deserialize, Clone)]
+pub struct NotifierConfig {
+    /// Maximum number of notifications this notifier sends per hour
+    pub max_per_hour: Option<usize>,
+    /// States for which this notifier is used, all states allowed by the escalation policy if not set
+    pub states: Option<Vec<LEDStripState>>,
+    /// Household member whose personal channel this is, notified first when it is their turn
+    pub user: Option<String>,
+    #[serde(flatten)]
+    pub kind: NotifierKind
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NotifierKind {
+    Email(email::EmailConfig),
+    Webhook(webhook::WebhookConfig),
+    Telegram(telegram::TelegramConfig),
+    Mqtt(mqtt::MqttConfig),
+    Matrix(matrix::MatrixConfig),
+    Signal(signal::SignalConfig),
+    Discord(discord::DiscordConfig),
+    Slack(slack::SlackConfig),
+    Speech(speech::SpeechConfig)
+}
+
+/// Creates the notifier described by the configuration.
+fn create(config: NotifierConfig, reminder_tx: Sender<ReminderEvent>, gamification: Option<GamificationConfig>) -> ConfiguredNotifier {
+    let notifier: Box<dyn Notifier> = match config.kind {
+        NotifierKind::Email(email_config) => Box::new(email::EmailNotifier::new(email_config)),
+        NotifierKind::Webhook(webhook_config) => Box::new(webhook::WebhookNotifier::new(webhook_config)),
+        NotifierKind::Telegram(telegram_config) => Box::new(telegram::TelegramNotifier::new(telegram_config)),
+        NotifierKind::Mqtt(mqtt_config) => Box::new(mqtt::MqttNotifier::new(mqtt_config)),
+        NotifierKind::Matrix(matrix_config) => Box::new(matrix::MatrixNotifier::new(matrix_config)),
+        NotifierKind::Signal(signal_config) => Box::new(signal::SignalNotifier::new(signal_config)),
+        NotifierKind::Discord(discord_config) => Box::new(discord::DiscordNotifier::new(discord_config, reminder_tx, gamification)),
+        NotifierKind::Slack(slack_config) => Box::new(slack::SlackNotifier::new(slack_config)),
+        NotifierKind::Speech(speech_config) => Box::new(speech::SpeechNotifier::new(speech_config))
+    };
+    ConfiguredNotifier { notifier, max_per_hour: config.max_per_hour, states: config.states, user: config.user, sent: VecDeque::new() }
+}
+
+/// A notifier along with the restrictions configured for it.
+struct ConfiguredNotifier {
+    notifier: Box<dyn Notifier>,
+    max_per_hour: Option<usize>,
+    states: Option<Vec<LEDStripState>>,
+    user: Option<String>,
+    sent: VecDeque<DateTime<Utc>>
+}
+
+impl ConfiguredNotifier {
+    fn notify(&mut self, notification: &Notification, now: DateTime<Utc>) {
+        if self.states.as_ref().is_some_and(|states| !states.contains(&notification.state)) {
+            return;
+        }
+        while self.sent.front().is_some_and(|t| now.signed_duration_since(*t) > Duration::hours(1)) {
+            self.sent.pop_front();
+        }
+        if self.max_per_hour.is_some_and(|max| self.sent.len() >= max) {
+            log::warn!("Rate limit reached for notifier {}, dropping notification", self.notifier.name());
+            return;
+        }
+        match self.notifier.notify(notification) {
+            Ok(()) => self.sent.push_back(now),
+            Err(e) => log::error!("Notifier {} failed: {}", self.notifier.name(), e)
+        }
+    }
+
+    /// Delivers a summary or a reminder that is not about the state, which every notifier sends whatever the state.
+    fn summarize(&mut self, notification: &Notification) {
+        if let Err(e) = self.notifier.notify(notification) {
+            log::error!("Notifier {} could not send {}: {}", self.notifier.name(), notification.subject.to_lowercase(), e);
+        }
+    }
+}
+
+/// Runs the notifier thread, which applies the escalation policy to state changes and dispatches
+/// notifications to all configured notifiers.
+pub fn run(policy: EscalationPolicy, configs: Vec<NotifierConfig>, gamification: Option<GamificationConfig>, reminder_tx: Sender<ReminderEvent>,
+           rx: Receiver<NotifierEvent>, shutdown_flag: Arc<AtomicBool>) {
+    std::thread::Builder::new().name("notifier".to_string()).spawn(move || {
+        let mut notifiers: Vec<ConfiguredNotifier> = configs.into_iter().map(|c| create(c, reminder_tx.clone(), gamification.clone())).collect();
+        let mut current: Option<(LEDStripState, DateTime<Utc>)> = None;
+        let mut last_notified: Option<DateTime<Utc>> = None;
+        let mut pending = false;
+        let mut snoozed_until: Option<DateTime<Utc>> = None;
+        let mut last_cleaning: (Option<String>, Option<ResetSource>) = (None, None);
+        let mut last_summary_date = Utc::now().with_timezone(&schedule().timezone).date_naive();
+        let mut assignee: Option<String> = None;
+        // since when notifications are due, and whether they went beyond the member whose turn it is
+        let mut due_since: Option<DateTime<Utc>> = None;
+        let mut household_notified = false;
+        let mut nobody_home = false;
+        let mut full_change_due: Option<DateTime<Utc>> = None;
+        let mut bag_runs_out: Option<DateTime<Utc>> = None;
+        let mut care_due: Vec<String> = Vec::new();
+        let mut overnight: Vec<(LEDStripState, DateTime<Utc>)> = Vec::new();
+        let mut sitter: Option<String> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            match rx.recv_timeout(POLL_DELAY) {
+                Ok(NotifierEvent::StateChanged(state, last_cleaning_time, new_assignee)) => {
+                    current = Some((state, last_cleaning_time));
+                    assignee = new_assignee;
+                    notifiers.iter_mut().for_each(|n| n.notifier.state_changed(state, last_cleaning_time));
+                    pending = state >= policy.notify_from;
+                    if pending {
+                        due_since.get_or_insert(Utc::now());
+                    } else {
+                        last_notified = None;
+                        due_since = None;
+                        household_notified = false;
+                    }
+                }
+                Ok(NotifierEvent::ClimateMeasured(climate)) => {
+                    notifiers.iter_mut().for_each(|n| n.notifier.climate_measured(climate));
+                }
+                Ok(NotifierEvent::Cleaned(by, source)) => last_cleaning = (by, source),
+                Ok(NotifierEvent::Snoozed(until)) => snoozed_until = Some(until),
+                Ok(NotifierEvent::PresenceChanged(home)) => {
+                    nobody_home = !home;
+                    // whoever comes home gets a single notification if the litter box is due, however many were sent before
+                    if home {
+                        pending = current.is_some_and(|(state, _)| state >= policy.notify_from);
+                    }
+                }
+                Ok(NotifierEvent::FullChangeDue(last_full_change)) => full_change_due = Some(last_full_change),
+                Ok(NotifierEvent::SuppliesLow(runs_out)) => bag_runs_out = Some(runs_out),
+                Ok(NotifierEvent::CareDue(name)) => care_due.push(name),
+                Ok(NotifierEvent::Overnight(changes)) if policy.morning_summary => overnight = changes,
+                Ok(NotifierEvent::Overnight(_)) => (),
+                Ok(NotifierEvent::Sitter(new_sitter)) => sitter = new_sitter,
+                Ok(NotifierEvent::DueTimeChanged(due_time)) => {
+                    notifiers.iter_mut().for_each(|n| n.notifier.due_time_changed(due_time));
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => break
+            }
+
+            let now = Utc::now();
+            notifiers.iter_mut().for_each(|n| n.notifier.tick(now));
+
+            let local_now = now.with_timezone(&schedule().timezone);
+            let summary_due = policy.weekly_summary.as_ref().is_some_and(|summary| {
+                local_now.weekday() == summary.day && local_now.time() >= summary.time && local_now.date_naive() > last_summary_date
+            });
+            if summary_due {
+                last_summary_date = local_now.date_naive();
+                match history::load() {
+                    Ok(entries) => {
+                        let (state, last_cleaning_time) = current.unwrap_or((LEDStripState::LightGreen, now));
+                        let notification = Notification { state, last_cleaning_time, subject: "Weekly litter box summary".to_string(),
+                            message: summary::weekly(&entries, now) };
+                        notifiers.iter_mut().for_each(|n| n.summarize(&notification));
+                    }
+                    Err(e) => log::error!("Could not load the history for the weekly summary: {}", e)
+                }
+            }
+
+            let is_quiet = policy.quiet_hours.as_ref().is_some_and(|q| q.contains(now.with_timezone(&schedule().timezone).time()))
+                || snoozed_until.is_some_and(|until| now < until)
+                || nobody_home;
+            if let (Some(last_full_change), false) = (full_change_due, is_quiet) {
+                full_change_due = None;
+                let (state, last_cleaning_time) = current.unwrap_or((LEDStripState::LightGreen, now));
+                let days = now.signed_duration_since(last_full_change).num_days();
+                let notification = Notification { state, last_cleaning_time, subject: "Time to replace the litter".to_string(),
+                    message: format!("The litter was last replaced {} days ago, time to replace it entirely!", days) };
+                notifiers.iter_mut().for_each(|n| n.summarize(&notification));
+            }
+            if let (Some(runs_out), false) = (bag_runs_out, is_quiet) {
+                bag_runs_out = None;
+                let (state, last_cleaning_time) = current.unwrap_or((LEDStripState::LightGreen, now));
+                let notification = Notification { state, last_cleaning_time, subject: "Litter running out".to_string(),
+                    message: format!("The bag of litter is predicted to run out on {}, time to buy a new one!",
+                                     runs_out.with_timezone(&schedule().timezone).format("%a %d %b")) };
+                notifiers.iter_mut().for_each(|n| n.summarize(&notification));
+            }
+            if !overnight.is_empty() && !is_quiet {
+                let (state, last_cleaning_time) = current.unwrap_or((LEDStripState::LightGreen, now));
+                let notification = Notification { state, last_cleaning_time, subject: "Overnight summary".to_string(),
+                    message: overnight_summary(&overnight) };
+                notifiers.iter_mut().for_each(|n| n.summarize(&notification));
+                overnight.clear();
+            }
+            if !is_quiet {
+                let (state, last_cleaning_time) = current.unwrap_or((LEDStripState::LightGreen, now));
+                for name in care_due.drain(..) {
+                    let notification = Notification { state, last_cleaning_time, subject: format!("Time for the {}", name),
+                        message: format!("The {} is due.", name) };
+                    notifiers.iter_mut().for_each(|n| n.summarize(&notification));
+                }
+            }
+            if let Some((state, last_cleaning_time)) = current {
+                let renotify = state >= policy.notify_from && match (last_notified, policy.renotify_interval_minutes) {
+                    (Some(t), Some(interval)) => now.signed_duration_since(t) >= Duration::minutes(interval),
+                    _ => false
+                };
+                // during trips, the cat-sitter is notified instead of the household, if they have a notifier of their own
+                let sitter_only = sitter.clone().filter(|sitter| notifiers.iter().any(|n| n.user.as_ref() == Some(sitter)));
+                // the grace period only applies if the member whose turn it is has a notifier of their own
+                let grace = policy.assignee_grace_minutes
+                    .filter(|_| sitter_only.is_none() && assignee.is_some() && notifiers.iter().any(|n| n.user == assignee));
+                let assignee_only = grace.is_some_and(|grace| due_since.is_some_and(|since| now.signed_duration_since(since) < Duration::minutes(grace)));
+                let escalate = grace.is_some() && !assignee_only && !household_notified && due_since.is_some();
+                if (pending || renotify || escalate) && !is_quiet {
+                    let notification = notification(state, last_cleaning_time, &last_cleaning, assignee.as_deref(), now);
+                    if assignee_only {
+                        log::info!("Notifying {} only, whose turn it is", assignee.as_deref().unwrap_or_default());
+                    }
+                    notifiers.iter_mut()
+                        .filter(|n| match &sitter_only {
+                            Some(_) => n.user == sitter_only,
+                            None => !assignee_only || n.user == assignee
+                        })
+                        .for_each(|n| n.notify(&notification, now));
+                    household_notified |= !assignee_only;
+                    pending = false;
+                    last_notified = Some(now);
+                }
+            }
+        }
+    }).expect("Could not start notifier thread");
+}
+
+fn notification(state: LEDStripState, last_cleaning_time: DateTime<Utc>, last_cleaning: &(Option<String>, Option<ResetSource>), assignee: Option<&str>,
+                now: DateTime<Utc>) -> Notification {
+    let elapsed = format_duration(&now.signed_duration_since(last_cleaning_time));
+    let subject = if state >= LEDStripState::Red {
+        "The litter box is overdue".to_string()
+    } else {
+        format!("The litter box is getting dirty ({:?})", state)
+    };
+    let cleaned = match last_cleaning {
+        (Some(by), Some(source)) => format!("{} cleaned the litter box through {}", by, source),
+        (Some(by), None) => format!("{} cleaned the litter box", by),
+        (None, Some(source)) => format!("The litter box was reset through {}", source),
+        (None, None) => "The litter box was last cleaned".to_string()
+    };
+    let local_time = last_cleaning_time.with_timezone(&schedule().timezone);
+    // the day is only named if the cleaning was not today
+    let format = if local_time.date_naive() == now.with_timezone(&schedule().timezone).date_naive() { "%H:%M" } else { "%a %H:%M" };
+    let turn = assignee.map(|assignee| format!(" It is {}'s turn.", assignee)).unwrap_or_default();
+    let message = format!("{} at {}, {} ago, time to clean it!{}", cleaned, local_time.format(format), elapsed, turn);
+    Notification { state, last_cleaning_time, subject, message }
+}
+
+/// What happened during the night, e.g. `Overnight, the litter box turned Orange at 01:10 and Red at 02:30.`
+fn overnight_summary(changes: &[(LEDStripState, DateTime<Utc>)]) -> String {
+    let changes: Vec<String> = changes.iter()
+        .map(|(state, time)| format!("{:?} at {}", state, time.with_timezone(&schedule().timezone).format("%H:%M")))
+        .collect();
+    let changes = match changes.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => changes.join("")
+    };
+    format!("Overnight, the litter box turned {}.", changes)
+}
+
+pub fn format_duration(duration: &Duration) -> String {
+    format!("{}h {}m", duration.num_hours(), duration.num_minutes() % 60)
+}
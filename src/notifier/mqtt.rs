@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rumqttc::{Client, MqttOptions, QoS};
+use serde::Deserialize;
+
+use crate::config::schedule;
+use crate::reminder::LEDStripState;
+use crate::sensor::Climate;
+use crate::state::DEFAULT_BOX;
+
+use super::{Notification, Notifier};
+
+#[derive(Deserialize, Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    /// Topic to which temperature and humidity are published as retained messages
+    #[serde(default = "default_climate_topic")]
+    pub climate_topic: String,
+    /// Topic to which the state, the time of the last cleaning and the time at which the litter box becomes overdue
+    /// are published as retained messages whenever the state or the due time changes
+    #[serde(default = "default_status_topic")]
+    pub status_topic: String
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_topic() -> String {
+    "cat-reminder/notification".to_string()
+}
+
+fn default_climate_topic() -> String {
+    "cat-reminder/climate".to_string()
+}
+
+fn default_status_topic() -> String {
+    "cat-reminder/status".to_string()
+}
+
+/// Publishes notifications as JSON documents to an MQTT topic.
+pub struct MqttNotifier {
+    config: MqttConfig,
+    client: Client,
+    /// The latest state, with the time of the last cleaning
+    status: Option<(LEDStripState, DateTime<Utc>)>,
+    /// The due time worked out by the reminder, once it differs from the one of the schedule alone
+    due_time: Option<DateTime<Utc>>
+}
+
+impl MqttNotifier {
+    pub fn new(config: MqttConfig) -> Self {
+        let mut options = MqttOptions::new(format!("cat-reminder-{}", std::process::id()), config.host.clone(), config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            options.set_credentials(username.clone(), password.clone());
+        }
+        let (client, mut connection) = Client::new(options, 10);
+
+        // the connection needs to be polled for the client to make progress
+        std::thread::spawn(move || {
+            for notification in connection.iter() {
+                if let Err(e) = notification {
+                    log::warn!("MQTT connection error: {:?}", e);
+                    std::thread::sleep(Duration::from_secs(5));
+                }
+            }
+        });
+
+        MqttNotifier { config, client, status: None, due_time: None }
+    }
+
+    fn due_time_for(&self, last_cleaning_time: DateTime<Utc>) -> DateTime<Utc> {
+        self.due_time.unwrap_or_else(|| schedule().due_time(DEFAULT_BOX, last_cleaning_time))
+    }
+
+    /// Publishes the latest status as a retained message, once the state is known.
+    fn publish_status(&mut self) {
+        let Some((state, last_cleaning_time)) = self.status else {
+            return;
+        };
+        let result = serde_json::to_vec(&serde_json::json!({
+            "state": state,
+            "last_cleaning_time": last_cleaning_time,
+            "due_time": self.due_time_for(last_cleaning_time)
+        })).map_err(|e| e.to_string())
+            .and_then(|payload| self.client.publish(self.config.status_topic.as_str(), QoS::AtLeastOnce, true, payload).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::warn!("Could not publish the status over MQTT: {}", e);
+        }
+    }
+}
+
+impl Notifier for MqttNotifier {
+    fn name(&self) -> &str {
+        "mqtt"
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        let payload = serde_json::to_vec(&serde_json::json!({
+            "state": notification.state,
+            "last_cleaning_time": notification.last_cleaning_time,
+            "due_time": self.due_time_for(notification.last_cleaning_time),
+            "message": notification.message
+        }))?;
+        self.client.publish(self.config.topic.as_str(), QoS::AtLeastOnce, false, payload)?;
+        Ok(())
+    }
+
+    fn state_changed(&mut self, state: LEDStripState, last_cleaning_time: DateTime<Utc>) {
+        self.status = Some((state, last_cleaning_time));
+        self.publish_status();
+    }
+
+    fn due_time_changed(&mut self, due_time: DateTime<Utc>) {
+        self.due_time = Some(due_time);
+        self.publish_status();
+    }
+
+    fn climate_measured(&mut self, climate: Climate) {
+        let result = serde_json::to_vec(&climate).map_err(|e| e.to_string())
+            .and_then(|payload| self.client.publish(self.config.climate_topic.as_str(), QoS::AtLeastOnce, true, payload).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::warn!("Could not publish climate over MQTT: {}", e);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::error::Error;
+
+use serde::Deserialize;
+
+use super::{Notification, Notifier};
+
+#[derive(Deserialize, Clone)]
+pub struct SignalConfig {
+    /// URL of the JSON-RPC endpoint of a `signal-cli daemon --http`, e.g. `http://localhost:8080/api/v1/rpc`
+    pub rpc_url: String,
+    /// Phone number of the registered account, when the daemon serves multiple accounts
+    pub account: Option<String>,
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    pub group_id: Option<String>
+}
+
+/// Sends notifications through a signal-cli daemon, to individual recipients or to a group.
+pub struct SignalNotifier {
+    config: SignalConfig
+}
+
+impl SignalNotifier {
+    pub fn new(config: SignalConfig) -> Self {
+        SignalNotifier { config }
+    }
+}
+
+impl Notifier for SignalNotifier {
+    fn name(&self) -> &str {
+        "signal"
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        let mut params = serde_json::json!({
+            "message": format!("{}\n{}", notification.subject, notification.message)
+        });
+        if let Some(account) = &self.config.account {
+            params["account"] = account.clone().into();
+        }
+        if let Some(group_id) = &self.config.group_id {
+            params["groupId"] = group_id.clone().into();
+        } else {
+            params["recipient"] = self.config.recipients.clone().into();
+        }
+        let response: serde_json::Value = ureq::post(&self.config.rpc_url)
+            .send_json(serde_json::json!({ "jsonrpc": "2.0", "method": "send", "params": params, "id": 1 }))?
+            .into_json()?;
+        match response.get("error") {
+            Some(error) => Err(format!("signal-cli error: {}", error).into()),
+            None => Ok(())
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::error::Error;
+
+use serde::Deserialize;
+
+use crate::reminder::LEDStripState;
+
+use super::{Notification, Notifier};
+
+#[derive(Deserialize, Clone)]
+pub struct SlackConfig {
+    pub webhook_url: String
+}
+
+/// Posts notifications to a Slack incoming webhook, as attachments colored like the LED strip.
+pub struct SlackNotifier {
+    config: SlackConfig
+}
+
+impl SlackNotifier {
+    pub fn new(config: SlackConfig) -> Self {
+        SlackNotifier { config }
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        ureq::post(&self.config.webhook_url).send_json(serde_json::json!({
+            "text": notification.subject,
+            "attachments": [{
+                "color": color(notification.state),
+                "text": notification.message,
+                "ts": notification.last_cleaning_time.timestamp()
+            }]
+        }))?;
+        Ok(())
+    }
+}
+
+fn color(state: LEDStripState) -> &'static str {
+    match state {
+        LEDStripState::LightGreen => "#7cfc00",
+        LEDStripState::DarkGreen => "#006400",
+        LEDStripState::Orange => "#ffa500",
+        LEDStripState::Red => "#ff0000",
+        LEDStripState::BlinkingRed => "#8b0000"
+    }
+}
@@ -0,0 +1,59 @@
+use std::error::Error;
+use std::process::Command;
+
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::reminder::LEDStripState;
+
+use super::{Notification, Notifier};
+
+#[derive(Deserialize, Clone)]
+pub struct SpeechConfig {
+    /// Text-to-speech command playing on the default audio output, the text is passed as last argument.
+    /// Bluetooth speakers work when they are the default PulseAudio/PipeWire sink.
+    #[serde(default = "default_command")]
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>
+}
+
+fn default_command() -> String {
+    "espeak-ng".to_string()
+}
+
+/// Announces notifications through a text-to-speech command.
+pub struct SpeechNotifier {
+    config: SpeechConfig
+}
+
+impl SpeechNotifier {
+    pub fn new(config: SpeechConfig) -> Self {
+        SpeechNotifier { config }
+    }
+}
+
+impl Notifier for SpeechNotifier {
+    fn name(&self) -> &str {
+        "speech"
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        let overdue_by = Utc::now().signed_duration_since(notification.last_cleaning_time) - LEDStripState::Red.starts_after();
+        let text = if overdue_by.num_hours() >= 1 {
+            format!("The litter box is overdue by {} hours", overdue_by.num_hours())
+        } else if overdue_by.num_minutes() >= 1 {
+            format!("The litter box is overdue by {} minutes", overdue_by.num_minutes())
+        } else if notification.state >= LEDStripState::Red {
+            "The litter box is overdue".to_string()
+        } else {
+            "The litter box should be cleaned soon".to_string()
+        };
+        let status = Command::new(&self.config.command).args(&self.config.args).arg(text).status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{} exited with {}", self.config.command, status).into())
+        }
+    }
+}
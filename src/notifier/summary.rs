@@ -0,0 +1,47 @@
+use chrono::{DateTime, Duration, NaiveTime, Utc, Weekday};
+use serde::Deserialize;
+
+use crate::config::schedule;
+use crate::history::{self, HistoryEntry, HistoryEvent};
+
+use super::format_duration;
+
+/// When the weekly summary is sent through all notifiers, in local time.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct WeeklySummary {
+    pub day: Weekday,
+    pub time: NaiveTime
+}
+
+impl Default for WeeklySummary {
+    fn default() -> Self {
+        WeeklySummary { day: Weekday::Sun, time: NaiveTime::from_hms_opt(19, 0, 0).unwrap() }
+    }
+}
+
+/// Summarizes the week before `to`: the number of cleanings, the average interval between them, the longest overdue
+/// stretch and, if cleanings are attributed, the number of cleanings per member.
+pub fn weekly(entries: &[HistoryEntry], to: DateTime<Utc>) -> String {
+    let from = to - Duration::days(7);
+    let cleanings: Vec<&HistoryEntry> = entries.iter().filter(|e| e.event == HistoryEvent::Cleaned && e.time <= to).collect();
+    let first = cleanings.iter().position(|e| e.time >= from).unwrap_or(cleanings.len());
+    let week = &cleanings[first..];
+
+    let mut body = format!("Cleanings this week: {}\n", week.len());
+    // the first interval of the week starts with the last cleaning before it
+    let intervals: Vec<Duration> = cleanings[first.saturating_sub(1)..].windows(2).map(|pair| pair[1].time - pair[0].time).collect();
+    if !intervals.is_empty() {
+        let average = intervals.iter().fold(Duration::zero(), |sum, interval| sum + *interval) / intervals.len() as i32;
+        body.push_str(&format!("Average interval: {}\n", format_duration(&average)));
+    }
+    match history::overdue_periods(entries, from, to).into_iter().max_by_key(|(start, end)| *end - *start) {
+        Some((start, end)) => body.push_str(&format!("Worst overdue stretch: {} from {}\n", format_duration(&(end - start)),
+                                                     start.with_timezone(&schedule().timezone).format("%a %H:%M"))),
+        None => body.push_str("Never overdue, well done!\n")
+    }
+    if let Some(counts) = history::count_by_member(week.iter().copied()) {
+        body.push_str(&format!("By member: {}\n", counts));
+    }
+    body
+}
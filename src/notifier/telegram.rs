@@ -0,0 +1,37 @@
+use std::error::Error;
+
+use serde::Deserialize;
+
+use super::{Notification, Notifier};
+
+#[derive(Deserialize, Clone)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub chat_id: String
+}
+
+/// Sends notifications to a Telegram chat through the Bot API.
+pub struct TelegramNotifier {
+    config: TelegramConfig
+}
+
+impl TelegramNotifier {
+    pub fn new(config: TelegramConfig) -> Self {
+        TelegramNotifier { config }
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.config.bot_token);
+        ureq::post(&url).send_json(serde_json::json!({
+            "chat_id": self.config.chat_id,
+            "text": format!("{}\n{}", notification.subject, notification.message)
+        }))?;
+        Ok(())
+    }
+}
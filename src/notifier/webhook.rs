@@ -0,0 +1,37 @@
+use std::error::Error;
+
+use serde::Deserialize;
+
+use super::{Notification, Notifier};
+
+#[derive(Deserialize, Clone)]
+pub struct WebhookConfig {
+    pub url: String
+}
+
+/// Posts notifications as JSON documents to an HTTP endpoint.
+pub struct WebhookNotifier {
+    config: WebhookConfig
+}
+
+impl WebhookNotifier {
+    pub fn new(config: WebhookConfig) -> Self {
+        WebhookNotifier { config }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<(), Box<dyn Error>> {
+        ureq::post(&self.config.url).send_json(serde_json::json!({
+            "state": notification.state,
+            "last_cleaning_time": notification.last_cleaning_time,
+            "subject": notification.subject,
+            "message": notification.message
+        }))?;
+        Ok(())
+    }
+}
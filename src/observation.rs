@@ -0,0 +1,30 @@
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::reminder::ReminderEvent;
+
+/// An observation about the litter box made by an external process, such as computer vision on a camera feed,
+/// e.g. `{"kind": "box_scooped", "source": "camera"}`.
+#[derive(Deserialize)]
+pub struct Observation {
+    pub kind: ObservationKind,
+    /// What made the observation, recorded in the history
+    pub source: Option<String>
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationKind {
+    CatUsedBox,
+    BoxScooped
+}
+
+impl Observation {
+    /// The event feeding the observation to the same logic as the physical sensors.
+    pub fn into_event(self) -> ReminderEvent {
+        match self.kind {
+            ObservationKind::CatUsedBox => ReminderEvent::CatVisited(Utc::now()),
+            ObservationKind::BoxScooped => ReminderEvent::CleaningDetected(self.source.unwrap_or_else(|| "External observer".to_string()))
+        }
+    }
+}
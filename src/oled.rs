@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Baseline, Text};
+
+use chrono::Utc;
+use linux_embedded_hal::I2cdev;
+use serde::Deserialize;
+use ssd1306::mode::BufferedGraphicsMode;
+use ssd1306::prelude::*;
+use ssd1306::{I2CDisplayInterface, Ssd1306};
+
+use crate::display::{Display, DisplayState};
+use crate::notifier::format_duration;
+use crate::pairing;
+
+const LINE_HEIGHT: i32 = 12;
+const WIDTH: i32 = 128;
+const HEIGHT: i32 = 64;
+/// Time after the start during which the pairing QR code is shown
+const PAIRING_DURATION: Duration = Duration::from_secs(120);
+
+#[derive(Deserialize, Clone)]
+pub struct OledConfig {
+    #[serde(default = "default_i2c_bus")]
+    pub i2c_bus: String,
+    /// I²C address of the display, usually 0x3C or 0x3D
+    #[serde(default = "default_address")]
+    pub address: u8
+}
+
+fn default_i2c_bus() -> String {
+    "/dev/i2c-1".to_string()
+}
+
+fn default_address() -> u8 {
+    0x3C
+}
+
+type Ssd1306Display = Ssd1306<I2CInterface<I2cdev>, DisplaySize128x64, BufferedGraphicsMode<DisplaySize128x64>>;
+
+/// A 128x64 SSD1306 OLED display connected over I²C, showing the time since the last cleaning, the assignee and the
+/// network status. The display is blank during the night. If phones can pair with the HTTP API, the pairing QR code
+/// is shown for a while after the start instead.
+pub struct OledDisplay {
+    display: Ssd1306Display,
+    lines: Vec<String>,
+    /// Modules of the pairing QR code, with its width, and the time until which it is shown
+    pairing: Option<(Vec<bool>, usize, Instant)>,
+    is_showing_pairing: bool
+}
+
+impl OledDisplay {
+    /// Opens and initializes the display.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the I²C bus cannot be opened or the display does not respond.
+    pub fn new(config: &OledConfig, pairing_url: Option<&str>) -> Self {
+        let i2c = I2cdev::new(&config.i2c_bus).expect("Could not open I2C bus");
+        let interface = I2CDisplayInterface::new_custom_address(i2c, config.address);
+        let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0).into_buffered_graphics_mode();
+        display.init().expect("Could not initialize OLED display");
+        let pairing = pairing_url.and_then(pairing::code).map(|code| (pairing::modules(&code), code.width(), Instant::now() + PAIRING_DURATION));
+        OledDisplay { display, lines: Vec::new(), pairing, is_showing_pairing: false }
+    }
+
+    /// Shows the lines of text, only redrawing the display if they changed.
+    fn show(&mut self, lines: Vec<String>) {
+        if lines == self.lines && !self.is_showing_pairing {
+            return;
+        }
+        let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+        self.display.clear_buffer();
+        for (i, line) in lines.iter().enumerate() {
+            // drawing into the buffer cannot fail
+            let _ = Text::with_baseline(line, Point::new(0, i as i32 * LINE_HEIGHT), style, Baseline::Top).draw(&mut self.display);
+        }
+        match self.display.flush() {
+            Ok(()) => {
+                self.lines = lines;
+                self.is_showing_pairing = false;
+            }
+            Err(e) => log::error!("Could not update OLED display: {:?}", e)
+        }
+    }
+
+    /// Draws the pairing QR code as large as it fits, dark modules on a lit background with the quiet zone around it.
+    fn show_pairing(&mut self) {
+        let Some((modules, width, _)) = &self.pairing else { return };
+        if self.is_showing_pairing {
+            return;
+        }
+        let size = *width as i32 + 2;
+        let scale = (HEIGHT / size).max(1);
+        let origin = Point::new((WIDTH - size * scale) / 2, (HEIGHT - size * scale) / 2);
+        self.display.clear_buffer();
+        let square = |position: Point, modules: i32, color: BinaryColor| {
+            Rectangle::new(position, Size::new((modules * scale) as u32, (modules * scale) as u32)).into_styled(PrimitiveStyle::with_fill(color))
+        };
+        // drawing into the buffer cannot fail
+        let _ = square(origin, size, BinaryColor::On).draw(&mut self.display);
+        for (i, _) in modules.iter().enumerate().filter(|(_, dark)| **dark) {
+            let position = origin + Point::new((i % width + 1) as i32 * scale, (i / width + 1) as i32 * scale);
+            let _ = square(position, 1, BinaryColor::Off).draw(&mut self.display);
+        }
+        match self.display.flush() {
+            Ok(()) => self.is_showing_pairing = true,
+            Err(e) => log::error!("Could not show the pairing code on the OLED display: {:?}", e)
+        }
+    }
+}
+
+impl Display for OledDisplay {
+    fn name(&self) -> &str {
+        "oled"
+    }
+
+    fn render(&mut self, state: &DisplayState) {
+        if !state.is_dark && self.pairing.as_ref().is_some_and(|(_, _, until)| Instant::now() < *until) {
+            self.show_pairing();
+            return;
+        }
+        let mut lines = Vec::new();
+        if !state.is_dark {
+            lines.push("Last cleaned:".to_string());
+            lines.push(format!("{} ago", format_duration(&Utc::now().signed_duration_since(state.status.last_cleaning_time))));
+            if let Some(assignee) = &state.status.assignee {
+                lines.push(format!("Turn: {}", assignee));
+            }
+            lines.push(match state.peer_count {
+                0 => "Network: no peers".to_string(),
+                1 => "Network: 1 peer".to_string(),
+                n => format!("Network: {} peers", n)
+            });
+        }
+        self.show(lines);
+    }
+
+    fn clear(&mut self) {
+        self.show(Vec::new());
+    }
+}
@@ -0,0 +1,33 @@
+//! Pairing phones with the HTTP API: a QR code holding the address of the node and the token, so that pairing is a
+//! scan instead of typing addresses and secrets.
+
+use std::net::IpAddr;
+
+use qrcode::render::{svg, unicode};
+use qrcode::{Color, QrCode};
+
+use crate::http::HttpConfig;
+
+/// The URL encoded in the QR code, with the first API token, if there is one.
+pub fn url(ip_addr: IpAddr, config: &HttpConfig) -> Option<String> {
+    let token = &config.tokens.first()?.token;
+    Some(format!("{}://{}:{}/status?token={}", config.scheme(), ip_addr, config.port, token))
+}
+
+pub fn code(url: &str) -> Option<QrCode> {
+    QrCode::new(url.as_bytes()).map_err(|e| log::error!("Could not encode the pairing QR code: {}", e)).ok()
+}
+
+pub fn svg(code: &QrCode) -> String {
+    code.render::<svg::Color>().min_dimensions(240, 240).build()
+}
+
+/// The QR code drawn with block characters, for printing in a terminal.
+pub fn terminal(code: &QrCode) -> String {
+    code.render::<unicode::Dense1x2>().dark_color(unicode::Dense1x2::Light).light_color(unicode::Dense1x2::Dark).build()
+}
+
+/// The modules of the QR code, row by row, dark ones being true.
+pub fn modules(code: &QrCode) -> Vec<bool> {
+    code.to_colors().into_iter().map(|color| color == Color::Dark).collect()
+}
@@ -0,0 +1,46 @@
+//! Colors of the states on the LED strip. The standard green to red progression cannot be told apart by everyone, so
+//! other palettes can be selected, and each color can be set in the configuration.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::reminder::LEDStripState;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Palette {
+    /// Light and dark green, orange and red
+    #[default]
+    Standard,
+    /// Blue, orange and reddish purple, which can be told apart without telling red from green
+    Deuteranopia,
+    /// Bright and dim blue, then dim and bright yellow
+    HighContrast
+}
+
+impl Palette {
+    /// The RGB color of the state.
+    pub fn color(&self, state: LEDStripState) -> [u8; 3] {
+        match (self, state) {
+            (Palette::Standard, LEDStripState::LightGreen) => [0, 60, 0],
+            (Palette::Standard, LEDStripState::DarkGreen) => [0, 20, 0],
+            (Palette::Standard, LEDStripState::Orange) => [255, 60, 0],
+            (Palette::Standard, LEDStripState::Red | LEDStripState::BlinkingRed) => [255, 0, 0],
+            (Palette::Deuteranopia, LEDStripState::LightGreen) => [0, 80, 160],
+            (Palette::Deuteranopia, LEDStripState::DarkGreen) => [0, 25, 60],
+            (Palette::Deuteranopia, LEDStripState::Orange) => [230, 120, 0],
+            (Palette::Deuteranopia, LEDStripState::Red | LEDStripState::BlinkingRed) => [200, 40, 140],
+            (Palette::HighContrast, LEDStripState::LightGreen) => [0, 0, 255],
+            (Palette::HighContrast, LEDStripState::DarkGreen) => [0, 0, 50],
+            (Palette::HighContrast, LEDStripState::Orange) => [60, 50, 0],
+            (Palette::HighContrast, LEDStripState::Red | LEDStripState::BlinkingRed) => [255, 220, 0]
+        }
+    }
+}
+
+/// The RGB colors of all states, in the order of [LEDStripState::ALL], the configured ones taking precedence over the
+/// ones of the palette.
+pub fn state_colors(palette: Palette, colors: &BTreeMap<LEDStripState, [u8; 3]>) -> [[u8; 3]; 5] {
+    LEDStripState::ALL.map(|state| colors.get(&state).copied().unwrap_or_else(|| palette.color(state)))
+}
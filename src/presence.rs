@@ -0,0 +1,145 @@
+//! Presence detection: while nobody is home, the strip stays dark and notifications wait, catching up with a single
+//! notification when someone returns. Phones are looked up on the local network by their MAC address, or presence is
+//! read from an MQTT topic, e.g. one published by Home Assistant.
+
+use std::fs;
+use std::net::Ipv4Addr;
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use rumqttc::{Client, Event, Incoming, MqttOptions, QoS};
+use serde::Deserialize;
+
+use crate::reminder::ReminderEvent;
+
+const ARP_TABLE_PATH: &str = "/proc/net/arp";
+const MQTT_RETRY_DELAY: Duration = Duration::from_secs(5);
+const POLL_DELAY: Duration = Duration::from_millis(1000);
+
+/// Presence detection is enabled by adding the section.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct PresenceConfig {
+    /// MAC addresses of the phones of the household, e.g. `"a4:5e:60:12:34:56"`
+    pub devices: Vec<String>,
+    /// Interval at which the phones are looked for
+    pub poll_seconds: u64,
+    /// Time without any phone on the network after which nobody is considered home, since phones drop off the
+    /// network while they sleep
+    pub away_after_minutes: u64,
+    pub mqtt: Option<PresenceMqttConfig>
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        PresenceConfig { devices: Vec::new(), poll_seconds: 60, away_after_minutes: 15, mqtt: None }
+    }
+}
+
+/// A topic telling whether someone is home, with `home`, `on`, `true` or `1` for someone being home and anything else
+/// for nobody.
+#[derive(Deserialize, Clone)]
+pub struct PresenceMqttConfig {
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub topic: String
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+/// Runs the presence detection, which reports to the reminder whenever someone comes home or the last one leaves.
+pub fn run(config: PresenceConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    if let Some(mqtt_config) = config.mqtt.clone() {
+        subscribe(mqtt_config, reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if config.devices.is_empty() {
+        return;
+    }
+    std::thread::Builder::new().name("presence".to_string()).spawn(move || {
+        let devices: Vec<String> = config.devices.iter().map(|mac| mac.to_lowercase()).collect();
+        let away_after = Duration::from_secs(config.away_after_minutes * 60);
+        let mut last_seen = Instant::now();
+        let mut home = true;
+        let mut last_poll: Option<Instant> = None;
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            if last_poll.is_some_and(|t| t.elapsed() < Duration::from_secs(config.poll_seconds)) {
+                continue;
+            }
+            last_poll = Some(Instant::now());
+            if is_any_device_present(&devices) {
+                last_seen = Instant::now();
+            }
+            let is_home = last_seen.elapsed() < away_after;
+            if is_home != home {
+                home = is_home;
+                log::info!("{}", if home { "Someone came home" } else { "Nobody is home" });
+                if reminder_tx.send(ReminderEvent::PresenceChanged(home)).is_err() {
+                    break;
+                }
+            }
+        }
+    }).expect("Could not start presence thread");
+}
+
+/// Whether one of the devices answers a ping at the address the ARP table knows for it.
+fn is_any_device_present(devices: &[String]) -> bool {
+    let table = fs::read_to_string(ARP_TABLE_PATH).unwrap_or_else(|e| {
+        log::warn!("Could not read {}: {}", ARP_TABLE_PATH, e);
+        String::new()
+    });
+    // IP address, HW type, flags, HW address, mask and device
+    table.lines().skip(1)
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            let ip_addr: Ipv4Addr = columns.first()?.parse().ok()?;
+            devices.contains(&columns.get(3)?.to_lowercase()).then_some(ip_addr)
+        })
+        .any(|ip_addr| Command::new("ping").args(["-c", "1", "-W", "1", &ip_addr.to_string()]).output().is_ok_and(|output| output.status.success()))
+}
+
+fn subscribe(config: PresenceMqttConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let mut options = MqttOptions::new(format!("cat-reminder-presence-{}", std::process::id()), config.host.clone(), config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        options.set_credentials(username.clone(), password.clone());
+    }
+    let (client, mut connection) = Client::new(options, 10);
+    std::thread::Builder::new().name("presence-mqtt".to_string()).spawn(move || {
+        for event in connection.iter() {
+            if shutdown_flag.load(Ordering::Relaxed) {
+                break;
+            }
+            match event {
+                // subscribing again on every connection, as the session is not kept by the broker
+                Ok(Event::Incoming(Incoming::ConnAck(_))) => {
+                    if let Err(e) = client.subscribe(config.topic.as_str(), QoS::AtLeastOnce) {
+                        log::warn!("Could not subscribe to {}: {}", config.topic, e);
+                    }
+                }
+                Ok(Event::Incoming(Incoming::Publish(publish))) => {
+                    let payload = String::from_utf8_lossy(&publish.payload).trim().to_lowercase();
+                    let home = matches!(payload.as_str(), "home" | "on" | "true" | "1");
+                    log::info!("Presence from MQTT: {}", payload);
+                    if reminder_tx.send(ReminderEvent::PresenceChanged(home)).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    log::warn!("MQTT presence connection error: {:?}", e);
+                    sleep(MQTT_RETRY_DELAY);
+                }
+            }
+        }
+    }).expect("Could not start presence MQTT thread");
+}
@@ -1,9 +1,145 @@
+use bincode::Options;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
-use chrono::serde::ts_seconds_option;
+use chrono::serde::{ts_seconds, ts_seconds_option};
 
-#[derive(Serialize, Deserialize)]
+use crate::error::{CatReminderError, Result};
+
+/// Starts every frame, so that packets of other applications on the port are told apart right away
+const MAGIC: [u8; 4] = *b"CATR";
+/// Incremented on incompatible changes of the messages, frames of other versions are rejected
+pub const PROTOCOL_VERSION: u8 = 1;
+/// Magic bytes, version, encoding and payload length
+const HEADER_LENGTH: usize = 10;
+const CHECKSUM_LENGTH: usize = 4;
+/// Far more than any message needs, while bounding what a corrupted length can make the decoder allocate
+const MAX_PAYLOAD_LENGTH: usize = 1024;
+
+/// Interval at which companion nodes send a [Message::Heartbeat].
+///
+/// Companion nodes are microcontrollers, such as an ESP32 with a few LEDs, that join the network as additional
+/// displays. They implement a subset of the protocol, in the JSON encoding:
+/// - they register the `_cat._udp.local.` mDNS service on the transport port, with `profile=companion` in the TXT record
+/// - they answer [Message::RequestState] with [Message::UpdateState], with `null` as time if they do not know any
+/// - they show the time of the last cleaning received with [Message::UpdateState], and may send one when reset
+/// - they send a [Message::Heartbeat] every interval, to which the full nodes answer with [Message::UpdateState], so
+///   that a missed update is caught up, followed by [Message::DueTime], which they may show or ignore
+pub const COMPANION_HEARTBEAT_INTERVAL_SECONDS: u64 = 30;
+
+/// How the messages are serialized. Every node decodes both encodings, and answers a node in the encoding it uses.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[default]
+    Bincode,
+    /// For nodes not written in Rust, e.g. `"RequestState"`, `{"UpdateState": [1717236000, "Anna", "kitchen"]}` with
+    /// the time in seconds since the epoch, or `"GoingDown"`
+    Json
+}
+
+impl Encoding {
+    fn tag(self) -> u8 {
+        match self {
+            Encoding::Bincode => 0,
+            Encoding::Json => 1
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Encoding> {
+        match tag {
+            0 => Some(Encoding::Bincode),
+            1 => Some(Encoding::Json),
+            _ => None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub enum Message {
     RequestState,
-    UpdateState(#[serde(with = "ts_seconds_option")] Option<DateTime<Utc>>)
-}
\ No newline at end of file
+    /// The time of the last cleaning, with the name of who cleaned, if known, and the name of the node on which it was reset
+    UpdateState(#[serde(with = "ts_seconds_option")] Option<DateTime<Utc>>, Option<String>, Option<String>),
+    /// The sending node is shutting down
+    GoingDown,
+    /// Sent periodically by companion nodes, which get the current state in return
+    Heartbeat,
+    /// Asks the receiving node to check for an update right away
+    CheckForUpdate,
+    /// Like [Message::UpdateState], for the litter box of the given zone, sent by nodes in a zone and by hubs. Nodes
+    /// only take the ones of their own zone, while hubs keep track of all of them.
+    ZoneState(Option<String>, #[serde(with = "ts_seconds_option")] Option<DateTime<Utc>>, Option<String>, Option<String>),
+    /// The time at which the litter box becomes overdue, sent to the other nodes whenever it changes and to companion
+    /// nodes in answer to their heartbeat, so that they can show when it is due without knowing the schedule. Full nodes
+    /// work it out themselves.
+    DueTime(#[serde(with = "ts_seconds")] DateTime<Utc>),
+    /// The time at which the reminder was last snoozed, paused or resumed, with the end of the snooze, none while it is
+    /// paused until further notice. Sent to the other nodes on every change and along with the answer to a
+    /// [Message::RequestState], so that a restarted node stays dark until the snooze ends.
+    Snooze(#[serde(with = "ts_seconds")] DateTime<Utc>, #[serde(with = "ts_seconds_option")] Option<DateTime<Utc>>),
+    /// A time of the last cleaning set deliberately, e.g. moved back with the `set-time` command, with the zone, the
+    /// time at which it was set, who cleaned and the node. Nodes take it if it was set after the latest reset they
+    /// know of, even though the time itself is older, where they would take an [Message::UpdateState] for outdated.
+    CorrectedState(Option<String>, #[serde(with = "ts_seconds")] DateTime<Utc>, #[serde(with = "ts_seconds")] DateTime<Utc>, Option<String>, Option<String>)
+}
+
+/// Messages are sent in frames made of the magic bytes, the protocol version, the encoding (0 for bincode, 1 for
+/// JSON), the big-endian length of the payload, the payload and the big-endian CRC-32 of everything before it.
+impl Message {
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        self.encode_as(Encoding::Bincode)
+    }
+
+    pub fn encode_as(&self, encoding: Encoding) -> Result<Vec<u8>> {
+        let payload = match encoding {
+            Encoding::Bincode => options().serialize(self)?,
+            Encoding::Json => serde_json::to_vec(self).map_err(|e| CatReminderError::Frame(e.to_string()))?
+        };
+        if payload.len() > MAX_PAYLOAD_LENGTH {
+            return Err(CatReminderError::Frame(format!("payload of {} bytes is too long", payload.len())));
+        }
+        let mut frame = Vec::with_capacity(HEADER_LENGTH + payload.len() + CHECKSUM_LENGTH);
+        frame.extend_from_slice(&MAGIC);
+        frame.push(PROTOCOL_VERSION);
+        frame.push(encoding.tag());
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&payload);
+        frame.extend_from_slice(&crc32fast::hash(&frame).to_be_bytes());
+        Ok(frame)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Message> {
+        Ok(Self::decode_with_encoding(data)?.0)
+    }
+
+    /// Decodes a frame, returning the encoding used by the sender along with the message.
+    pub fn decode_with_encoding(data: &[u8]) -> Result<(Message, Encoding)> {
+        if data.len() < HEADER_LENGTH + CHECKSUM_LENGTH || data[..4] != MAGIC {
+            return Err(CatReminderError::Frame("not a frame".to_string()));
+        }
+        if data[4] != PROTOCOL_VERSION {
+            return Err(CatReminderError::Frame(format!("unsupported protocol version {}", data[4])));
+        }
+        let encoding = Encoding::from_tag(data[5]).ok_or_else(|| CatReminderError::Frame(format!("unknown encoding {}", data[5])))?;
+        let length = u32::from_be_bytes([data[6], data[7], data[8], data[9]]) as usize;
+        if length > MAX_PAYLOAD_LENGTH || data.len() != HEADER_LENGTH + length + CHECKSUM_LENGTH {
+            return Err(CatReminderError::Frame(format!("length {} does not match the {} bytes received", length, data.len())));
+        }
+        let (content, checksum) = data.split_at(HEADER_LENGTH + length);
+        if crc32fast::hash(content).to_be_bytes() != checksum {
+            return Err(CatReminderError::Frame("checksum mismatch".to_string()));
+        }
+        let payload = &content[HEADER_LENGTH..];
+        let message = match encoding {
+            Encoding::Bincode => options().deserialize(payload)?,
+            Encoding::Json => serde_json::from_slice(payload).map_err(|e| CatReminderError::Frame(e.to_string()))?
+        };
+        Ok((message, encoding))
+    }
+}
+
+/// The encoding of `bincode::serialize`, rejecting trailing bytes and oversized payloads.
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(MAX_PAYLOAD_LENGTH as u64)
+}
@@ -0,0 +1,160 @@
+//! Wi-Fi provisioning: a node that does not get online opens a temporary access point with a page asking for the
+//! credentials of the home network, which are then applied through NetworkManager.
+
+use std::io::Read;
+use std::net::IpAddr;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use gethostname::gethostname;
+use serde::Deserialize;
+use tiny_http::{Method, Request, Server};
+
+use crate::setup::{self, escape, parse_form};
+
+const ACCESS_POINT_CONNECTION: &str = "cat-reminder-provisioning";
+const NETWORK_POLL_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct ProvisioningConfig {
+    /// Whether the access point is opened, off by default so that a node briefly offline does not serve an open page
+    pub enabled: bool,
+    /// Wireless interface on which the access point is opened
+    pub interface: String,
+    /// Name of the access point, `CatReminder-<hostname>` if not set
+    pub ssid: Option<String>,
+    /// WPA2 passphrase of the access point, of at least 8 characters. The access point is open if not set, in which
+    /// case the credentials of the home network are sent in the clear to the node.
+    pub password: Option<String>,
+    /// Time given to the network to come up on boot before the access point is opened
+    pub wait_seconds: u64
+}
+
+impl Default for ProvisioningConfig {
+    fn default() -> Self {
+        ProvisioningConfig { enabled: false, interface: "wlan0".to_string(), ssid: None, password: None, wait_seconds: 60 }
+    }
+}
+
+/// Waits for the node to get an address, opening the access point to ask for Wi-Fi credentials if it does not get
+/// one in time, and returns the address.
+///
+/// # Panics
+///
+/// Panics if the node does not get online and provisioning is disabled, or if the access point cannot be served.
+pub fn ensure_online(config: &ProvisioningConfig) -> IpAddr {
+    let deadline = Instant::now() + Duration::from_secs(config.wait_seconds);
+    loop {
+        match local_ip_address::local_ip() {
+            Ok(ip_addr) => return ip_addr,
+            Err(e) if Instant::now() >= deadline => {
+                if !config.enabled {
+                    panic!("Could not resolve local IP address: {}", e);
+                }
+                break;
+            }
+            Err(_) => sleep(NETWORK_POLL_DELAY)
+        }
+    }
+    while let Err(e) = provision(config) {
+        log::error!("Wi-Fi provisioning failed, trying again: {}", e);
+        sleep(NETWORK_POLL_DELAY);
+    }
+    local_ip_address::local_ip().expect("Could not resolve local IP address after provisioning")
+}
+
+/// Serves the credentials page on the access point until the node is connected to the home network.
+fn provision(config: &ProvisioningConfig) -> Result<(), String> {
+    let ssid = config.ssid.clone().unwrap_or_else(|| format!("CatReminder-{}", gethostname().to_string_lossy()));
+    // the interface cannot scan while it is an access point
+    let networks = networks(config);
+    start_access_point(config, &ssid)?;
+    log::info!("Not online, opened the access point {} to ask for the Wi-Fi credentials", ssid);
+
+    let server = Server::http(("0.0.0.0", setup::SETUP_PORT)).map_err(|e| format!("Can not listen at port {}: {}", setup::SETUP_PORT, e))?;
+    let mut error: Option<String> = None;
+    for mut request in server.incoming_requests() {
+        let Some((network, password)) = credentials(&mut request) else {
+            setup::respond(request, page(&networks, error.as_deref()));
+            continue;
+        };
+        setup::respond(request, "<!DOCTYPE html><html><body><h1>Cat Litter Reminder</h1><p>Connecting to the network, \
+            the access point closes.</p></body></html>".to_string());
+        // give the page a moment to reach the phone before the access point goes away
+        sleep(NETWORK_POLL_DELAY);
+        stop_access_point();
+        match nmcli(&["device", "wifi", "connect", network.as_str(), "password", password.as_str(), "ifname", config.interface.as_str()]) {
+            Ok(()) => {
+                log::info!("Connected to the Wi-Fi network {}", network);
+                return Ok(());
+            }
+            Err(e) => {
+                log::warn!("Could not connect to the Wi-Fi network {}: {}", network, e);
+                error = Some(format!("Could not connect to {}, check the password", network));
+                start_access_point(config, &ssid)?;
+            }
+        }
+    }
+    Err("the provisioning server stopped".to_string())
+}
+
+/// The network and password submitted with the form, if the request submits it.
+fn credentials(request: &mut Request) -> Option<(String, String)> {
+    if *request.method() != Method::Post {
+        return None;
+    }
+    let mut body = String::new();
+    request.as_reader().read_to_string(&mut body).ok()?;
+    let mut fields = parse_form(&body);
+    let network = fields.remove("network").filter(|network| !network.is_empty())?;
+    Some((network, fields.remove("password").unwrap_or_default()))
+}
+
+fn page(networks: &[String], error: Option<&str>) -> String {
+    let options: String = networks.iter().map(|network| format!("<option value=\"{0}\">{0}</option>", escape(network))).collect();
+    let error = error.map(|e| format!("<p style=\"color: red\">{}</p>", escape(e))).unwrap_or_default();
+    format!("<!DOCTYPE html><html><head><meta name=\"viewport\" content=\"width=device-width\"><title>Cat Litter Reminder Wi-Fi</title></head>\
+        <body><h1>Cat Litter Reminder</h1>{}<form method=\"post\" action=\"/\">\
+        <p><label>Network<br><input name=\"network\" list=\"networks\" required><datalist id=\"networks\">{}</datalist></label></p>\
+        <p><label>Password<br><input name=\"password\" type=\"password\"></label></p>\
+        <p><button type=\"submit\">Connect</button></p></form></body></html>", error, options)
+}
+
+/// Names of the networks in range, as scanned by NetworkManager.
+fn networks(config: &ProvisioningConfig) -> Vec<String> {
+    let output = Command::new("nmcli").args(["--terse", "--fields", "SSID", "device", "wifi", "list", "ifname", config.interface.as_str()]).output();
+    let mut networks: Vec<String> = output.map(|output| String::from_utf8_lossy(&output.stdout).lines()
+        .map(|line| line.replace("\\:", ":"))
+        .filter(|line| !line.is_empty())
+        .collect()).unwrap_or_default();
+    networks.sort();
+    networks.dedup();
+    networks
+}
+
+/// Opens the access point, sharing the connection so that NetworkManager hands out addresses to the phones.
+fn start_access_point(config: &ProvisioningConfig, ssid: &str) -> Result<(), String> {
+    stop_access_point();
+    let mut args = vec!["connection", "add", "type", "wifi", "ifname", config.interface.as_str(), "con-name", ACCESS_POINT_CONNECTION,
+        "autoconnect", "no", "ssid", ssid, "802-11-wireless.mode", "ap", "ipv4.method", "shared"];
+    if let Some(password) = &config.password {
+        args.extend(["wifi-sec.key-mgmt", "wpa-psk", "wifi-sec.psk", password.as_str()]);
+    }
+    nmcli(&args)?;
+    nmcli(&["connection", "up", ACCESS_POINT_CONNECTION])
+}
+
+fn stop_access_point() {
+    // fails when there is no access point yet
+    let _ = nmcli(&["connection", "delete", ACCESS_POINT_CONNECTION]);
+}
+
+fn nmcli(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("nmcli").args(args).output().map_err(|e| format!("Could not run nmcli: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(())
+}
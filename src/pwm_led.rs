@@ -0,0 +1,119 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::display::{Display, DisplayState};
+
+const BLACK: [u8; 3] = [0, 0, 0];
+const WHITE: [u8; 3] = [60, 60, 60];
+/// Share of the brightness left while the SoC is too hot or the node runs on battery
+const DIMMED_FACTOR: f64 = 0.2;
+
+/// A single RGB LED whose legs are driven by three PWM channels of the kernel, such as the ones of a PCA9685 board or
+/// of the `pwm-gpio` overlay turning any GPIO into a PWM channel.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct PwmLedConfig {
+    /// Directory of the PWM chip in sysfs
+    pub chip: String,
+    /// Channels of the red, green and blue legs
+    pub channels: [u32; 3],
+    pub period_ns: u64,
+    /// Whether the legs share their anode, in which case a leg is lit while its channel is low
+    pub common_anode: bool
+}
+
+impl Default for PwmLedConfig {
+    fn default() -> Self {
+        PwmLedConfig { chip: "/sys/class/pwm/pwmchip0".to_string(), channels: [0, 1, 2], period_ns: 1_000_000, common_anode: false }
+    }
+}
+
+/// A PWM channel exported through sysfs.
+struct Channel {
+    path: PathBuf
+}
+
+impl Channel {
+    /// Exports the channel, if it is not yet, and enables it with a zero duty cycle.
+    fn open(chip: &Path, channel: u32, period_ns: u64) -> io::Result<Self> {
+        let path = chip.join(format!("pwm{}", channel));
+        if !path.exists() {
+            fs::write(chip.join("export"), channel.to_string())?;
+        }
+        // the duty cycle may never exceed the period, even while the period changes
+        fs::write(path.join("duty_cycle"), "0")?;
+        fs::write(path.join("period"), period_ns.to_string())?;
+        fs::write(path.join("enable"), "1")?;
+        Ok(Channel { path })
+    }
+
+    fn set(&self, duty_cycle_ns: u64) -> io::Result<()> {
+        fs::write(self.path.join("duty_cycle"), duty_cycle_ns.to_string())
+    }
+}
+
+/// A single RGB LED for builds without an addressable strip, lit in the color of the state and following its blink
+/// pattern. Like the strip, it is dark during the night.
+pub struct PwmLedDisplay {
+    channels: Vec<Channel>,
+    period_ns: u64,
+    common_anode: bool,
+    /// RGB colors of the states, in the order of [crate::reminder::LEDStripState::ALL]
+    colors: [[u8; 3]; 5],
+    shown: Option<[u8; 3]>
+}
+
+impl PwmLedDisplay {
+    /// Opens the PWM channels, showing the states in the given RGB colors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a PWM channel cannot be exported or enabled.
+    pub fn new(config: &PwmLedConfig, colors: [[u8; 3]; 5]) -> Self {
+        let chip = Path::new(&config.chip);
+        let channels = config.channels.iter()
+            .map(|channel| Channel::open(chip, *channel, config.period_ns).expect("Could not open PWM channel"))
+            .collect();
+        PwmLedDisplay { channels, period_ns: config.period_ns, common_anode: config.common_anode, colors, shown: None }
+    }
+
+    fn show(&mut self, color: [u8; 3]) {
+        if self.shown == Some(color) {
+            return;
+        }
+        let result = self.channels.iter().zip(color).try_for_each(|(channel, value)| {
+            let duty_cycle = self.period_ns * value as u64 / 255;
+            channel.set(if self.common_anode { self.period_ns - duty_cycle } else { duty_cycle })
+        });
+        match result {
+            Ok(()) => self.shown = Some(color),
+            Err(e) => log::error!("Could not update the RGB LED: {}", e)
+        }
+    }
+}
+
+impl Display for PwmLedDisplay {
+    fn name(&self) -> &str {
+        "rgb_led"
+    }
+
+    fn render(&mut self, state: &DisplayState) {
+        // an armed reset blinks white, even in the dark
+        let color = if state.is_reset_armed {
+            if state.blink_on { WHITE } else { BLACK }
+        } else if state.is_dark || !state.blink_on {
+            BLACK
+        } else {
+            self.colors[state.status.state as usize]
+        };
+        let factor = state.brightness * if state.is_dimmed { DIMMED_FACTOR } else { 1.0 };
+        self.show(color.map(|value| (value as f64 * factor).round() as u8));
+    }
+
+    fn clear(&mut self) {
+        self.show(BLACK);
+    }
+}
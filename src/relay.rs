@@ -0,0 +1,71 @@
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::gpio::{Chip, Options};
+use crate::reminder::{LEDStripState, ReminderStatus};
+
+const POLL_DELAY: StdDuration = StdDuration::from_millis(1000);
+
+/// A relay switching an appliance, such as an air purifier or an exhaust fan, while the litter box is overdue.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct RelayConfig {
+    pub pin: u32,
+    /// Whether the relay is switched on by pulling the line low, which is the case for most relay boards
+    pub active_low: bool,
+    /// State from which the relay is switched on
+    pub on_from: LEDStripState,
+    /// Safety cutoff, after which the relay is switched off until the next cleaning
+    pub max_runtime_minutes: i64
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        RelayConfig { pin: 23, active_low: true, on_from: LEDStripState::Red, max_runtime_minutes: 120 }
+    }
+}
+
+/// Runs the relay thread, which switches the relay on once the state reaches [RelayConfig::on_from] and off after
+/// a cleaning, or when it has been on for longer than the maximum runtime.
+///
+/// # Panics
+///
+/// Panics if the GPIO line of the relay cannot be requested.
+pub fn run(config: RelayConfig, status: Arc<RwLock<ReminderStatus>>, shutdown_flag: Arc<AtomicBool>) {
+    let chip = Chip::new("gpiochip0").expect("Cannot open GPIO");
+    let output = chip.request_lines(Options::output([config.pin]).values([config.active_low]).consumer("cat-reminder-relay"))
+        .expect("Could not request relay line");
+    std::thread::spawn(move || {
+        let mut on_since: Option<DateTime<Utc>> = None;
+        // the cleaning after which the relay reached its maximum runtime
+        let mut cut_off_for: Option<DateTime<Utc>> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let current = status.read().unwrap().clone();
+            let now = Utc::now();
+            if on_since.is_some_and(|since| now.signed_duration_since(since) >= Duration::minutes(config.max_runtime_minutes)) {
+                log::warn!("Relay reached its maximum runtime, switching it off until the next cleaning");
+                cut_off_for = Some(current.last_cleaning_time);
+            }
+
+            let should_be_on = current.state >= config.on_from && cut_off_for != Some(current.last_cleaning_time);
+            if should_be_on != on_since.is_some() {
+                log::info!("Switching relay {}", if should_be_on { "on" } else { "off" });
+                match output.set_values([should_be_on != config.active_low]) {
+                    Ok(()) => on_since = if should_be_on { Some(now) } else { None },
+                    Err(e) => log::error!("Could not switch relay: {:?}", e)
+                }
+            }
+        }
+
+        if let Err(e) = output.set_values([config.active_low]) {
+            log::error!("Could not switch relay off: {:?}", e);
+        }
+    });
+}
@@ -1,27 +1,144 @@
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread::sleep;
 use chrono::{DateTime, Duration, Utc};
-use chrono_tz::Europe::Vienna;
 use chrono::Timelike;
+use serde::{Deserialize, Serialize};
 
-use gpiod::{Chip, Options};
-use rs_ws281x::RawColor;
-use crate::led::{LedController, RPILedController};
+use crate::animation::Animation;
+use crate::care::{self, CareReminders};
+use crate::clock::Clock;
+use crate::config::schedule;
+use crate::history::{HistoryEntry, HistoryEvent};
+use crate::holiday::Holidays;
+use crate::litter::{self, LitterProfile};
+use crate::display::{Display, DisplayState};
+use crate::error::CatReminderError;
+use crate::notifier::NotifierEvent;
+use crate::sensor::Climate;
+use crate::state::{BoxState, State, DEFAULT_BOX};
+use crate::supplies::{self, BagState, SuppliesConfig};
+use crate::storage::StorageEvent;
+use crate::sensor::ammonia::Blending;
+use crate::thermal::ThermalAction;
 use crate::transport::TransportEvent;
+use crate::update;
+use crate::users;
+use crate::websocket::StreamEvent;
 
 
-const BLINK_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
 const LOOP_DELAY: std::time::Duration = std::time::Duration::from_millis(1000);
-const GPIO_BUTTON_PIN: u32 = 5;
+/// How long each state is shown in the demo
+const DEMO_STATE_SECONDS: i64 = 5;
+/// Minutes for which a cleaning in progress freezes the escalation without being confirmed
+const IN_PROGRESS_MINUTES: i64 = 15;
+/// Longest snooze, in minutes, pausing being the way to silence the reminder for longer
+pub const MAX_SNOOZE_MINUTES: i64 = 7 * 24 * 60;
+/// Longest guest mode, in hours
+pub const MAX_GUEST_HOURS: i64 = 31 * 24;
 
 pub enum ReminderEvent {
-    CleaningTimeUpdated(DateTime<Utc>)
+    /// A cleaning on another node, with the name of who cleaned, if known, and the name of the node
+    CleaningTimeUpdated(DateTime<Utc>, Option<String>, String),
+    /// A cleaning in another zone, received by hubs, with the name of the zone, who cleaned, if known, and the node
+    ZoneCleaningTimeUpdated(String, DateTime<Utc>, Option<String>, String),
+    /// A reset triggered by an input or from outside of the node, with the name of whoever triggered it, if known
+    ResetRequested(ResetSource, Option<String>),
+    /// A reset waits for its confirmation by a second press, or no longer does because it was not confirmed in time
+    ResetArmed(bool),
+    /// The time of the last cleaning was set, e.g. because a reset was forgotten, with whoever cleaned, if known
+    CleaningTimeSet(DateTime<Utc>, ResetSource, Option<String>),
+    /// The litter was replaced entirely, which also counts as a cleaning, with who replaced it, if known
+    LitterReplaced(ResetSource, Option<String>),
+    /// The litter used in the litter box changed
+    LitterSelected(LitterProfile),
+    /// A new bag of litter was opened, with who opened it, if known
+    BagOpened(Option<String>),
+    /// The care reminder with the given name was done, the first one that is due if not given, with who did it, if known
+    CareDone(Option<String>, Option<String>),
+    /// A cat used the litter box, as detected by a sensor
+    CatVisited(DateTime<Utc>),
+    /// A sensor detected that the litter box was cleaned, with the name of the sensor
+    CleaningDetected(String),
+    /// Someone started dealing with the litter box, which freezes the escalation until the cleaning is confirmed,
+    /// with the name of whoever started, if known
+    CleaningStarted(ResetSource, Option<String>),
+    /// The weight of the litter box settled at the given number of grams
+    WeightMeasured(DateTime<Utc>, f64),
+    /// The state matching the smell measured by a sensor changed
+    SmellMeasured(LEDStripState),
+    ClimateMeasured(DateTime<Utc>, Climate),
+    /// The level of the ambient light, between 0 in the dark and 1 in a bright room
+    AmbientLightMeasured(f64),
+    /// A snooze duration is being selected, in steps, or the selection was abandoned
+    SnoozeSelecting(Option<usize>),
+    /// Snoozes the reminder for the given duration, a zero duration ending a running snooze, with who snoozed it, if known
+    Snooze(Duration, Option<String>),
+    /// Pauses the reminder until further notice, or resumes it if it is paused, with who did it, if known
+    TogglePause(Option<String>),
+    /// Another node was snoozed, paused or resumed at the given time, until the other one, the end of times if paused,
+    /// with the address of the node
+    SnoozeSynced(DateTime<Utc>, DateTime<Utc>, String),
+    /// The SoC temperature in degrees Celsius, with the action to take if it is too high
+    CpuTemperatureMeasured(f64, Option<ThermalAction>),
+    /// Whether the node runs on battery
+    PowerChanged(bool),
+    /// Whether the named subsystem keeps failing despite being restarted
+    SubsystemFailing(String, bool),
+    /// Checks for an update right away, asking the other nodes to do the same if set
+    UpdateRequested(bool),
+    /// Whether someone is home, as detected by the presence detection
+    PresenceChanged(bool),
+    /// Enables the guest mode for the given duration, a zero duration ending it
+    GuestMode(Duration),
+    /// Enables the guest mode for the given duration, or ends it if it is enabled
+    ToggleGuestMode(Duration),
+    /// Selects the next litter box, which the next reset from an input applies to. Hubs cycle through the boxes of the
+    /// other zones after their own one.
+    SelectNextBox
 }
 
-#[derive(PartialEq)]
-enum LEDStripState {
+/// What triggered a reset of the cleaning time.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub enum ResetSource {
+    Button,
+    Remote,
+    Tag,
+    /// A voice assistant, with its name
+    Assistant(String),
+    Chat,
+    /// A sensor detecting the cleaning, with its name
+    Sensor(String),
+    /// Another node, with its name
+    Node(String),
+    /// A local process, through the control socket
+    Control,
+    DBus,
+    Http
+}
+
+impl fmt::Display for ResetSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResetSource::Button => write!(f, "the button"),
+            ResetSource::Remote => write!(f, "the IR remote"),
+            ResetSource::Tag => write!(f, "an NFC tag"),
+            ResetSource::Assistant(name) => write!(f, "{}", name),
+            ResetSource::Chat => write!(f, "chat"),
+            ResetSource::Sensor(name) => write!(f, "the {} sensor", name.to_lowercase()),
+            ResetSource::Node(name) => write!(f, "the {} node", name),
+            ResetSource::Control => write!(f, "the control socket"),
+            ResetSource::DBus => write!(f, "D-Bus"),
+            ResetSource::Http => write!(f, "the HTTP API")
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum LEDStripState {
     LightGreen,
     DarkGreen,
     Orange,
@@ -30,103 +147,758 @@ enum LEDStripState {
 }
 
 impl LEDStripState {
-    fn state_from_duration(duration: &Duration) -> Self {
-        match duration.num_seconds() {
-            0..=7 => LEDStripState::LightGreen,
-            8..=11 => LEDStripState::DarkGreen,
-            12..=23 => LEDStripState::Orange,
-            24..=25 => LEDStripState::Red,
-            _ => LEDStripState::BlinkingRed
+    pub const ALL: [LEDStripState; 5] = [LEDStripState::LightGreen, LEDStripState::DarkGreen, LEDStripState::Orange, LEDStripState::Red, LEDStripState::BlinkingRed];
+
+    pub(crate) fn state_from_duration(duration: &Duration) -> Self {
+        Self::state_of_box(DEFAULT_BOX, duration)
+    }
+
+    /// The state of the given litter box after the given time since its last cleaning.
+    fn state_of_box(name: &str, duration: &Duration) -> Self {
+        *Self::ALL.iter().rev()
+            .find(|state| *duration >= state.starts_after_in(name))
+            .unwrap_or(&LEDStripState::LightGreen)
+    }
+
+    /// The state shown after the given time in the demo, cycling through all states.
+    fn demo_state(duration: &Duration) -> Self {
+        let index = (duration.num_seconds() / DEMO_STATE_SECONDS) as usize % Self::ALL.len();
+        Self::ALL[index]
+    }
+
+    /// The state the given number of levels more urgent than this one, at most [LEDStripState::BlinkingRed].
+    fn escalated(&self, levels: usize) -> Self {
+        let index = Self::ALL.iter().position(|s| s == self).unwrap_or(0);
+        Self::ALL[(index + levels).min(Self::ALL.len() - 1)]
+    }
+
+    /// Progress after the given time since the last cleaning toward the next state, between 0 when the current state
+    /// was just entered and 1 when the next one is about to be, always 1 in the last state.
+    pub fn progress(duration: &Duration) -> f64 {
+        let state = Self::state_from_duration(duration);
+        let Some(next) = Self::ALL.get(state as usize + 1) else {
+            return 1.0;
+        };
+        let length = (next.starts_after() - state.starts_after()).num_seconds();
+        if length <= 0 {
+            return 1.0;
         }
+        ((*duration - state.starts_after()).num_seconds() as f64 / length as f64).clamp(0.0, 1.0)
+    }
+
+    /// Time elapsed since the last cleaning after which the strip enters this state, as configured in the schedule for
+    /// the litter box of the node.
+    pub fn starts_after(&self) -> Duration {
+        self.starts_after_in(DEFAULT_BOX)
     }
 
-    fn controller_color(&self) -> RawColor {
+    /// Time elapsed since the last cleaning after which the given litter box enters this state.
+    pub fn starts_after_in(&self, name: &str) -> Duration {
         match self {
-            LEDStripState::LightGreen => RPILedController::LIGHT_GREEN,
-            LEDStripState::DarkGreen => RPILedController::DARK_GREEN,
-            LEDStripState::Orange => RPILedController::ORANGE,
-            LEDStripState::Red => RPILedController::RED,
-            LEDStripState::BlinkingRed => RPILedController::RED
+            LEDStripState::LightGreen => Duration::zero(),
+            state => Duration::seconds(schedule().thresholds(name)[*state as usize - 1])
         }
     }
+
+}
+/// Snapshot of the reminder state, shared with the subsystems that report on it.
+#[derive(Clone, Serialize)]
+pub struct ReminderStatus {
+    pub last_cleaning_time: DateTime<Utc>,
+    pub state: LEDStripState,
+    /// Time at which the litter box becomes overdue, i.e. the strip turns red, unless something else happens
+    pub due_time: DateTime<Utc>,
+    /// Household member whose turn it is to clean the litter box, if chores are configured
+    pub assignee: Option<String>,
+    /// Latest temperature and humidity, if there is a sensor measuring them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub climate: Option<Climate>,
+    /// SoC temperature in degrees Celsius, if it is monitored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_temperature: Option<f64>,
+    /// Time until which the reminder is snoozed, the end of times if it is paused
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snoozed_until: Option<DateTime<Utc>>,
+    /// Whether the lamp and the sounds are silenced along with the strip, because the reminder is snoozed or nobody
+    /// is home
+    pub quiet: bool,
+    /// Time until which the guest mode is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guest_until: Option<DateTime<Utc>>,
+    /// Time at which someone started dealing with the litter box, if the cleaning is still in progress
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_progress_since: Option<DateTime<Utc>>,
+    pub litter: LitterProfile,
+    /// Time at which the litter is due to be replaced entirely, if it is known when it last was
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_full_change: Option<DateTime<Utc>>,
+    /// Time at which the bag of litter is predicted to run out, if supplies are tracked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bag_runs_out: Option<DateTime<Utc>>,
+    /// Names of the care reminders that are due
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub care_due: Vec<String>,
+    /// States of the litter boxes of the other zones, on hubs
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub zones: BTreeMap<String, LEDStripState>,
+    /// Subsystems and displays that keep failing despite being restarted or retried
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub failing: BTreeSet<String>,
+    /// Cleanings of the day, if there is a daily target
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scoops: Option<Scoops>
+}
+
+/// Cleanings done during the current day toward the daily target.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub struct Scoops {
+    pub done: usize,
+    pub target: usize
+}
+
+
+/// The rotation index after a cleaning: the turn goes to the member after whoever cleaned, or simply to the next
+/// member if it is not known who cleaned.
+pub fn next_rotation_index(members: &[String], rotation_index: usize, cleaned_by: Option<&str>) -> usize {
+    match cleaned_by.and_then(|by| members.iter().position(|m| m == by)) {
+        Some(position) => position + 1,
+        None => rotation_index + 1
+    }
 }
+
 pub struct Reminder {
-    pub chip: Chip,
-    pub controller: RPILedController,
+    /// Outputs showing the state, such as the LED strip
+    pub displays: Vec<Box<dyn Display>>,
+    pub storage_tx: Sender<StorageEvent>,
+    /// The persisted state, saved whenever it changes
+    pub state: State,
     pub reminder_rx: Receiver<ReminderEvent>,
     pub transport_tx: Sender<TransportEvent>,
+    pub notifier_tx: Sender<NotifierEvent>,
+    pub event_tx: Sender<StreamEvent>,
     pub last_cleaning_time: DateTime<Utc>,
-    pub is_strip_on: bool
+    pub last_state: Option<LEDStripState>,
+    pub status: Arc<RwLock<ReminderStatus>>,
+    pub peer_count: Arc<AtomicUsize>,
+    /// Household members taking turns in cleaning the litter box
+    pub members: Vec<String>,
+    /// Number of cleanings so far, used to determine whose turn it is
+    pub rotation_index: usize,
+    /// Number of cat visits since the last cleaning
+    pub visits: usize,
+    /// Number of visits after which the urgency is raised by one level, if visits escalate the urgency
+    pub visits_per_escalation: Option<usize>,
+    /// State matching the smell, if there is a sensor measuring it
+    pub smell: Option<LEDStripState>,
+    /// How the smell is combined with the state resulting from the elapsed time
+    pub blending: Blending,
+    pub climate: Option<Climate>,
+    /// Level of the ambient light, if there is a sensor measuring it, in which case the displays follow it rather
+    /// than the time of day
+    pub ambient_light: Option<f64>,
+    pub cpu_temperature: Option<f64>,
+    /// Action taken on the displays while the SoC is too hot
+    pub throttling: Option<ThermalAction>,
+    /// Whether the node runs on battery, in which case the displays are dimmed and blink slower
+    pub on_battery: bool,
+    /// Whether nobody is home, in which case the displays stay dark and notifications wait
+    pub nobody_home: bool,
+    /// Time until which the displays stay dark and no notifications are sent
+    pub snoozed_until: Option<DateTime<Utc>>,
+    /// Time until which visitors are over, during which the state does not escalate beyond red
+    pub guest_until: Option<DateTime<Utc>>,
+    /// Number of snooze steps being selected, if a selection is in progress
+    pub snooze_selection: Option<usize>,
+    /// Zone of the litter box the next reset from an input applies to, if it is not the one of the node
+    pub selected_box: Option<String>,
+    /// Whether a reset waits for its confirmation by a second press
+    pub reset_armed: bool,
+    /// Time at which someone started dealing with the litter box, the escalation being frozen until the cleaning is
+    /// confirmed or [IN_PROGRESS_MINUTES] have passed
+    pub in_progress_since: Option<DateTime<Utc>>,
+    /// Whether the litter is due to be replaced entirely, which is notified once
+    pub full_change_due: bool,
+    /// How long a bag of litter lasts, if supplies are tracked
+    pub supplies: Option<SuppliesConfig>,
+    /// Whether the bag of litter is about to run out, which is notified once and shown by the displays
+    pub supplies_low: bool,
+    /// Care reminders recurring every few months
+    pub care: CareReminders,
+    /// Blink pattern of the displays, advanced on every render
+    pub animation: Animation,
+    /// States shown during the current night, with the times they were reached, starting with the one at nightfall
+    pub overnight_states: Option<Vec<(LEDStripState, DateTime<Utc>)>>,
+    /// Subsystems that keep failing despite being restarted
+    pub failing_subsystems: BTreeSet<String>,
+    /// Times of the cleanings of the current day, counted toward the daily target
+    pub scoops: Vec<DateTime<Utc>>,
+    /// Trips of the household, during which the thresholds are relaxed and the cat-sitter is notified
+    pub holidays: Holidays,
+    /// Source of the current time, simulated in tests
+    pub clock: Arc<dyn Clock>,
+    /// When running the demo, the time from which the states cycle regardless of the cleaning time
+    pub demo_since: Option<DateTime<Utc>>,
+    /// Whether the state and the history are left alone, as in the demo and while the clock runs faster
+    pub dry_run: bool
 }
 
 impl Reminder {
     pub fn run(&mut self, shutdown_hook: Arc<AtomicBool>) {
+        let _span = tracing::info_span!("reminder").entered();
+        if let Some(last_cleaning) = self.state.boxes.get(DEFAULT_BOX) {
+            let event = NotifierEvent::Cleaned(last_cleaning.last_cleaned_by.clone(), last_cleaning.last_reset_source.clone());
+            self.notify(event);
+        }
+        if let Some(until) = self.snoozed_until {
+            self.notify(NotifierEvent::Snoozed(until));
+        }
+        // the other nodes take the snooze of this one if they do not know of a later one, and the other way around
+        if let (Some(snoozed_at), Some(until)) = (self.state.snoozed_at, self.snoozed_until) {
+            self.propagate(TransportEvent::Snoozed(snoozed_at, until));
+        }
 
         while !shutdown_hook.load(Ordering::Relaxed) {
-            self.reset_state_if_button_pushed();
+            let state = self.step();
+            // patterns play slower on battery
+            if self.on_battery {
+                sleep(LOOP_DELAY);
+            } else {
+                sleep(self.animation.frame_delay(state, self.reset_armed));
+            }
+        }
 
-            if let Ok(event) = self.reminder_rx.try_recv() {
-                match event {
-                    ReminderEvent::CleaningTimeUpdated(updated_cleaning_time) => {
-                        log::info!("New cleaning time from network");
-                        self.last_cleaning_time = updated_cleaning_time;
+        self.displays.iter_mut().for_each(|d| d.clear());
+    }
+
+    /// Handles the next pending event, if any, and renders the displays, returning the current state.
+    pub fn step(&mut self) -> LEDStripState {
+        // whether a state was received from another node, which the displays briefly show
+        let mut synced = false;
+        if let Ok(event) = self.reminder_rx.try_recv() {
+            match event {
+                ReminderEvent::CleaningTimeUpdated(updated_cleaning_time, by, node) => {
+                    log::info!("New cleaning time from the {} node", node);
+                    self.cleaned(updated_cleaning_time, by, ResetSource::Node(node));
+                    synced = true;
+                }
+                ReminderEvent::ZoneCleaningTimeUpdated(zone, time, by, node) => {
+                    log::info!("New cleaning time of the {} zone from the {} node", zone, node);
+                    self.zone_cleaned(zone, time, by, ResetSource::Node(node));
+                    synced = true;
+                }
+                ReminderEvent::ResetArmed(armed) => {
+                    self.reset_armed = armed;
+                }
+                ReminderEvent::ResetRequested(source, by) => {
+                    self.reset_armed = false;
+                    let by = by.map(|by| users::resolve(&by));
+                    log::info!("Reset requested through {} by {}", source, by.as_deref().unwrap_or("unknown"));
+                    // the box is selected with an input, so that the resets of the other sources are left alone
+                    let zone = if source == ResetSource::Button { self.selected_box.take() } else { None };
+                    match zone {
+                        Some(zone) => self.zone_reset(zone, source, by),
+                        None => self.reset(source, by)
                     }
                 }
-            }
-
-            let now = Utc::now().with_timezone(&Vienna);
-            let is_night = now.hour() >= 22 || now.hour() < 7;
-            let time_elapsed = Utc::now().signed_duration_since(self.last_cleaning_time);
-            let current_state = LEDStripState::state_from_duration(&time_elapsed);
-
-            if is_night && self.is_strip_on {
-                // go dark
-                self.controller.set_all_to(RPILedController::BLACK);
-                self.is_strip_on = false;
-            } else if !is_night {
-                if current_state == LEDStripState::BlinkingRed {
-                    if self.is_strip_on {
-                        self.controller.set_all_to(RPILedController::BLACK);
-                        self.is_strip_on = false;
+                ReminderEvent::SelectNextBox => {
+                    let zones: Vec<&String> = self.state.boxes.keys().filter(|name| name.as_str() != DEFAULT_BOX).collect();
+                    let next = match self.selected_box.as_deref() {
+                        None => zones.first().map(|zone| zone.to_string()),
+                        Some(selected) => zones.iter().skip_while(|zone| zone.as_str() != selected).nth(1).map(|zone| zone.to_string())
+                    };
+                    match &next {
+                        Some(zone) => log::info!("The next reset applies to the litter box of the {} zone", zone),
+                        None => log::info!("The next reset applies to the litter box of the node")
+                    }
+                    self.selected_box = next;
+                }
+                ReminderEvent::CleaningTimeSet(time, source, by) => {
+                    let by = by.map(|by| users::resolve(&by));
+                    log::info!("Cleaning time set to {} through {}", time, source);
+                    self.set_cleaning_time(time, source, by);
+                }
+                ReminderEvent::LitterReplaced(source, by) => {
+                    let by = by.map(|by| users::resolve(&by));
+                    log::info!("Litter replaced through {} by {}", source, by.as_deref().unwrap_or("unknown"));
+                    self.litter_replaced(source, by);
+                }
+                ReminderEvent::LitterSelected(profile) => {
+                    log::info!("Litter changed to {}", profile);
+                    litter::select(DEFAULT_BOX, profile);
+                    if let Some(box_state) = self.state.boxes.get_mut(DEFAULT_BOX) {
+                        box_state.litter = Some(profile);
+                    }
+                    self.save_state();
+                }
+                ReminderEvent::BagOpened(by) => {
+                    let by = by.map(|by| users::resolve(&by));
+                    log::info!("New bag of litter opened by {}", by.as_deref().unwrap_or("unknown"));
+                    let now = self.clock.now();
+                    self.state.bag = Some(BagState { opened: now, full_changes: 0 });
+                    self.save_state();
+                    self.record(HistoryEntry { time: now, event: HistoryEvent::BagOpened, by, source: None, assignee: None });
+                }
+                ReminderEvent::CareDone(name, by) => {
+                    let by = by.map(|by| users::resolve(&by));
+                    self.care_done(name, by);
+                }
+                ReminderEvent::CleaningDetected(sensor) => {
+                    log::info!("Cleaning detected by {}", sensor);
+                    self.reset(ResetSource::Sensor(sensor), None);
+                    self.displays.iter_mut().for_each(|d| d.confirm());
+                }
+                ReminderEvent::CleaningStarted(source, by) => {
+                    let by = by.map(|by| users::resolve(&by));
+                    log::info!("Cleaning started through {} by {}", source, by.as_deref().unwrap_or("unknown"));
+                    // starting over does not extend the freeze, else the escalation could be held off for good
+                    self.in_progress_since.get_or_insert(self.clock.now());
+                }
+                ReminderEvent::WeightMeasured(time, grams) => {
+                    self.record(HistoryEntry::new(time, HistoryEvent::Weighed(grams)));
+                }
+                ReminderEvent::SmellMeasured(state) => {
+                    self.smell = Some(state);
+                }
+                ReminderEvent::ClimateMeasured(time, climate) => {
+                    self.climate = Some(climate);
+                    self.record(HistoryEntry::new(time, HistoryEvent::ClimateMeasured(climate)));
+                    self.notify(NotifierEvent::ClimateMeasured(climate));
+                }
+                ReminderEvent::AmbientLightMeasured(level) => {
+                    self.ambient_light = Some(level);
+                }
+                ReminderEvent::SnoozeSelecting(steps) => {
+                    self.snooze_selection = steps;
+                }
+                ReminderEvent::Snooze(duration, by) => {
+                    self.snooze_selection = None;
+                    match self.clock.now().checked_add_signed(duration) {
+                        Some(until) => self.snooze_until(until, by),
+                        None => log::warn!("Ignoring a snooze of {} minutes, which ends after the end of times", duration.num_minutes())
+                    }
+                }
+                ReminderEvent::TogglePause(by) => {
+                    if self.snoozed_until == Some(DateTime::<Utc>::MAX_UTC) {
+                        log::info!("Resuming reminder");
+                        self.snooze_until(self.clock.now(), by);
+                    } else {
+                        log::info!("Pausing reminder");
+                        self.snooze_until(DateTime::<Utc>::MAX_UTC, by);
+                    }
+                }
+                ReminderEvent::SnoozeSynced(snoozed_at, until, node) => {
+                    log::info!("Snooze until {} received from {}", until, node);
+                    self.set_snooze(snoozed_at, until);
+                }
+                ReminderEvent::CpuTemperatureMeasured(celsius, throttling) => {
+                    self.cpu_temperature = Some(celsius);
+                    self.throttling = throttling;
+                }
+                ReminderEvent::PowerChanged(on_battery) => {
+                    self.on_battery = on_battery;
+                }
+                ReminderEvent::SubsystemFailing(name, true) => {
+                    self.failing_subsystems.insert(name);
+                }
+                ReminderEvent::SubsystemFailing(name, false) => {
+                    self.failing_subsystems.remove(&name);
+                }
+                ReminderEvent::CatVisited(time) => {
+                    self.visits += 1;
+                    self.record(HistoryEntry::new(time, HistoryEvent::CatVisited));
+                }
+                ReminderEvent::PresenceChanged(home) => {
+                    self.nobody_home = !home;
+                    self.notify(NotifierEvent::PresenceChanged(home));
+                }
+                ReminderEvent::GuestMode(duration) => {
+                    match self.clock.now().checked_add_signed(duration) {
+                        Some(until) => self.guest_until(until),
+                        None => log::warn!("Ignoring a guest mode of {} hours, which ends after the end of times", duration.num_hours())
+                    }
+                }
+                ReminderEvent::ToggleGuestMode(duration) => {
+                    if self.is_guest_mode() {
+                        self.guest_until(self.clock.now());
                     } else {
-                        self.controller.set_all_to(RPILedController::RED);
-                        self.is_strip_on = true;
+                        match self.clock.now().checked_add_signed(duration) {
+                            Some(until) => self.guest_until(until),
+                            None => log::warn!("Ignoring a guest mode of {} hours, which ends after the end of times", duration.num_hours())
+                        }
+                    }
+                }
+                ReminderEvent::UpdateRequested(fleet) => {
+                    log::info!("Update check requested{}", if fleet { " on all nodes" } else { "" });
+                    update::request_check();
+                    if fleet {
+                        self.propagate(TransportEvent::UpdateRequested);
                     }
-                } else {
-                    self.controller.set_all_to(LEDStripState::controller_color(&current_state));
                 }
             }
+        }
 
-            if current_state == LEDStripState::BlinkingRed {
-                sleep(BLINK_DELAY);
-            } else {
-                sleep(LOOP_DELAY);
+        let now = self.clock.now();
+        let local_now = now.with_timezone(&schedule().timezone);
+        // the demo is meant to be shown, whatever the time, and a light sensor tells when the room is dark
+        let is_night = self.demo_since.is_none() && match self.ambient_light {
+            Some(level) => level <= 0.0,
+            None => local_now.hour() >= 22 || local_now.hour() < 7
+        };
+        if let Some(since) = self.in_progress_since.filter(|since| now - *since >= Duration::minutes(IN_PROGRESS_MINUTES)) {
+            log::info!("The cleaning started at {} was not confirmed, resuming the escalation", since);
+            self.in_progress_since = None;
+        }
+        // while someone deals with the litter box, the urgency stays where it was when they started, and overnight,
+        // it may build up slower than the time passes
+        let urgency_time = self.in_progress_since.map_or(now, |since| since.min(now));
+        let today = local_now.date_naive();
+        if let Some(on_trip) = self.holidays.update(today) {
+            log::info!("{}", if on_trip { "A trip started" } else { "The trip is over" });
+            if let Some(sitter) = self.holidays.sitter() {
+                self.notify(NotifierEvent::Sitter(on_trip.then(|| sitter.to_string())));
+            }
+        }
+        // during trips, the thresholds may be relaxed for the cat-sitter
+        let time_elapsed = self.holidays.relaxed(schedule().urgency_elapsed(self.last_cleaning_time, urgency_time));
+        // a daily target still missed in the evening raises the urgency by one level until it is met
+        self.scoops.retain(|time| time.with_timezone(&schedule().timezone).date_naive() == today);
+        let scoops = schedule().daily_target.as_ref().map(|target| Scoops { done: self.scoops.len(), target: target.scoops });
+        let target_missed = schedule().daily_target.as_ref().is_some_and(|target| local_now.time() >= target.deadline && self.scoops.len() < target.scoops);
+        let escalation_levels = self.visits_per_escalation.filter(|n| *n > 0).map_or(0, |n| self.visits / n) + target_missed as usize;
+        let time_state = match self.demo_since {
+            Some(since) => LEDStripState::demo_state(&now.signed_duration_since(since)),
+            None => LEDStripState::state_from_duration(&time_elapsed).escalated(escalation_levels)
+        };
+        let current_state = self.smell.map_or(time_state, |smell| self.blending.blend(time_state, smell));
+        // guests are spared the blinking and the alarms, the cleaning time being left as is
+        let is_guest_mode = self.is_guest_mode();
+        let capped = |state: LEDStripState| if is_guest_mode { state.min(LEDStripState::Red) } else { state };
+        let current_state = capped(current_state);
+        let due_time = self.due_time(now, escalation_levels);
+        if self.status.read().unwrap().due_time != due_time {
+            self.propagate(TransportEvent::DueTimeChanged(due_time));
+            self.notify(NotifierEvent::DueTimeChanged(due_time));
+        }
+        self.notify_if_state_changed(current_state);
+        // hubs show the most urgent of all litter boxes, the other zones notifying about theirs
+        let zones: BTreeMap<String, LEDStripState> = self.state.boxes.iter()
+            .filter(|(name, _)| name.as_str() != DEFAULT_BOX)
+            .map(|(name, box_state)| (name.clone(), LEDStripState::state_of_box(name, &schedule().urgency_elapsed(box_state.last_cleaning_time, now))))
+            .collect();
+        let shown_state = capped(zones.values().copied().fold(current_state, LEDStripState::max));
+        let last_full_change = self.state.boxes.get(DEFAULT_BOX).and_then(|b| b.last_full_change);
+        let next_full_change = last_full_change.map(|time| time + Duration::days(schedule().full_change_days(DEFAULT_BOX)));
+        let full_change_due = next_full_change.is_some_and(|time| now >= time);
+        if let (Some(last_full_change), true, false) = (last_full_change, full_change_due, self.full_change_due) {
+            self.notify(NotifierEvent::FullChangeDue(last_full_change));
+        }
+        self.full_change_due = full_change_due;
+        let interval = Duration::days(schedule().full_change_days(DEFAULT_BOX));
+        let bag_runs_out = self.supplies.as_ref().zip(self.state.bag.as_ref()).map(|(supplies, bag)| supplies::runs_out(supplies, bag, last_full_change, interval));
+        let supplies_low = self.supplies.as_ref().zip(bag_runs_out).is_some_and(|(supplies, runs_out)| runs_out - now <= Duration::days(supplies.warn_days));
+        if let (Some(runs_out), true, false) = (bag_runs_out, supplies_low, self.supplies_low) {
+            log::info!("The bag of litter is predicted to run out on {}", runs_out);
+            self.notify(NotifierEvent::SuppliesLow(runs_out));
+        }
+        self.supplies_low = supplies_low;
+        let is_snoozed = self.snoozed_until.is_some_and(|until| now < until);
+        let is_dark = is_night || is_snoozed || self.nobody_home || self.throttling == Some(ThermalAction::Off);
+        // the changes of the night are caught up on once it is over, the first state being the one at nightfall
+        let overnight = match (self.overnight_states.as_mut(), is_night) {
+            (None, true) => {
+                self.overnight_states = Some(vec![(shown_state, now)]);
+                None
+            }
+            (Some(states), true) => {
+                if states.last().is_some_and(|(state, _)| *state != shown_state) {
+                    states.push((shown_state, now));
+                }
+                None
+            }
+            (Some(_), false) => self.overnight_states.take().map(|states| states[1..].to_vec()).filter(|changes| !changes.is_empty()),
+            (None, false) => None
+        };
+        if let Some(changes) = &overnight {
+            log::info!("Catching up on {} state changes during the night", changes.len());
+            self.notify(NotifierEvent::Overnight(changes.clone()));
+            if !is_dark {
+                let states: Vec<LEDStripState> = changes.iter().map(|(state, _)| *state).collect();
+                self.displays.iter_mut().for_each(|d| d.catch_up(&states));
             }
         }
+        let care = self.care.check(&self.state.care, now, !is_dark);
+        for name in &care.newly_due {
+            log::info!("The {} is due", name);
+            self.notify(NotifierEvent::CareDue(name.clone()));
+        }
+        let status = ReminderStatus { last_cleaning_time: self.last_cleaning_time, state: shown_state,
+            due_time, assignee: self.assignee(), climate: self.climate,
+            cpu_temperature: self.cpu_temperature, snoozed_until: self.snoozed_until, quiet: is_snoozed || self.nobody_home, guest_until: self.guest_until.filter(|until| now < *until),
+            in_progress_since: self.in_progress_since,
+            litter: schedule().litter(DEFAULT_BOX), next_full_change, bag_runs_out, care_due: care.due, zones,
+            failing: self.failing_subsystems.clone(), scoops };
+        *self.status.write().unwrap() = status.clone();
+
+        let frame = self.animation.advance(shown_state, self.reset_armed);
+        let display_state = DisplayState {
+            status,
+            own_state: current_state,
+            peer_count: self.peer_count.load(Ordering::Relaxed),
+            is_dark,
+            is_dimmed: self.throttling == Some(ThermalAction::Dim) || self.on_battery,
+            brightness: self.ambient_light.unwrap_or(1.0),
+            snooze_selection: self.snooze_selection,
+            is_reset_armed: self.reset_armed,
+            is_supply_low: self.supplies_low,
+            blink_on: frame.on,
+            chase: frame.chase,
+            is_failing: !self.failing_subsystems.is_empty(),
+            // escalations, smells and other zones leave the time behind, making their state complete
+            progress: if shown_state == LEDStripState::state_from_duration(&time_elapsed) { LEDStripState::progress(&time_elapsed) } else { 1.0 }
+        };
+        self.displays.iter_mut().for_each(|d| d.render(&display_state));
+        for display in &self.displays {
+            let name = format!("{} display", display.name());
+            if display.is_failing() {
+                if self.failing_subsystems.insert(name.clone()) {
+                    log::error!("The {} keeps failing", name);
+                }
+            } else if self.failing_subsystems.remove(&name) {
+                log::info!("The {} works again", name);
+            }
+        }
+        if synced && !is_dark {
+            self.displays.iter_mut().for_each(|d| d.synced());
+        }
+        for color in care.pulses {
+            self.displays.iter_mut().for_each(|d| d.pulse(color));
+        }
+        shown_state
+    }
+
+    /// The time at which the strip turns red if nothing else happens, worked out from what the state is: the red
+    /// threshold, brought closer by the escalations and stretched during a trip, is reached after the last cleaning,
+    /// the overnight hours counting by their factor, and not before a cleaning under way times out or a snooze ends. A
+    /// pause, which does not end, leaves it as is.
+    fn due_time(&self, now: DateTime<Utc>, escalation_levels: usize) -> DateTime<Utc> {
+        // each escalation level makes the strip turn red where it would enter the previous state
+        let threshold = LEDStripState::ALL[(LEDStripState::Red as usize).saturating_sub(escalation_levels)].starts_after();
+        let mut due_time = schedule().urgency_reached_at(self.last_cleaning_time, self.holidays.unrelaxed(threshold));
+        if let Some(since) = self.in_progress_since.filter(|since| due_time > *since) {
+            due_time = due_time.max(since + Duration::minutes(IN_PROGRESS_MINUTES));
+        }
+        if let Some(until) = self.snoozed_until.filter(|until| now < *until && *until != DateTime::<Utc>::MAX_UTC) {
+            due_time = due_time.max(until);
+        }
+        due_time
+    }
+
+    /// Resets the state and propagates the new cleaning time to the other nodes
+    fn reset(&mut self, source: ResetSource, by: Option<String>) {
+        self.set_cleaning_time(self.clock.now(), source, by);
+    }
+
+    /// Sets the time of the last cleaning and propagates it to the other nodes
+    fn set_cleaning_time(&mut self, time: DateTime<Utc>, source: ResetSource, by: Option<String>) {
+        if self.demo_since.is_some() {
+            log::info!("Restarting the demo");
+            self.demo_since = Some(self.clock.now());
+            return;
+        }
+        self.cleaned(time, by.clone(), source);
+        self.propagate(TransportEvent::CleaningTimeReset(self.last_cleaning_time, by, self.clock.now()));
+    }
+
+    /// Updates, persists and records the time of the last cleaning, and advances the rotation.
+    fn cleaned(&mut self, time: DateTime<Utc>, by: Option<String>, source: ResetSource) {
+        let assignee = self.assignee();
+        if let (Some(assignee), Some(by)) = (&assignee, &by) {
+            if assignee != by {
+                log::info!("{} cleaned the litter box, although it was the turn of {}", by, assignee);
+            }
+        }
+        self.last_cleaning_time = time;
+        self.in_progress_since = None;
+        self.scoops.push(time);
+        self.rotation_index = next_rotation_index(&self.members, self.rotation_index, by.as_deref());
+        self.visits = 0;
+        let box_state = self.state.boxes.entry(DEFAULT_BOX.to_string()).or_insert_with(|| BoxState {
+            last_cleaning_time: time, last_cleaned_by: None, last_reset_source: None, litter: None, last_full_change: None
+        });
+        box_state.last_cleaning_time = time;
+        box_state.last_cleaned_by = by.clone();
+        box_state.last_reset_source = Some(source.clone());
+        self.save_state();
+        self.record(HistoryEntry { time, event: HistoryEvent::Cleaned, by: by.clone(), source: Some(source.clone()), assignee });
+        self.notify(NotifierEvent::Cleaned(by, Some(source)));
+    }
+
+    /// Resets the litter box of another zone, as selected on a hub, and sends it to the nodes of the zone.
+    fn zone_reset(&mut self, zone: String, source: ResetSource, by: Option<String>) {
+        let now = self.clock.now();
+        log::info!("Resetting the litter box of the {} zone", zone);
+        self.zone_cleaned(zone.clone(), now, by.clone(), source);
+        self.propagate(TransportEvent::ZoneCleaningTimeReset(zone, now, by));
+    }
+
+    /// Keeps track of the cleaning of the litter box of another zone, unless a later one is known already.
+    fn zone_cleaned(&mut self, zone: String, time: DateTime<Utc>, by: Option<String>, source: ResetSource) {
+        let box_state = self.state.boxes.entry(zone).or_insert_with(|| BoxState {
+            last_cleaning_time: time, last_cleaned_by: None, last_reset_source: None, litter: None, last_full_change: None
+        });
+        if box_state.last_cleaning_time > time {
+            return;
+        }
+        box_state.last_cleaning_time = time;
+        box_state.last_cleaned_by = by;
+        box_state.last_reset_source = Some(source);
+        self.save_state();
+    }
+
+    /// Resets the state, since the litter box is clean with fresh litter, and records the full change.
+    fn litter_replaced(&mut self, source: ResetSource, by: Option<String>) {
+        self.reset(source, by.clone());
+        let now = self.clock.now();
+        if let Some(box_state) = self.state.boxes.get_mut(DEFAULT_BOX) {
+            box_state.last_full_change = Some(now);
+        }
+        if let Some(bag) = self.state.bag.as_mut() {
+            bag.full_changes += 1;
+        }
+        self.save_state();
+        self.record(HistoryEntry { time: now, event: HistoryEvent::LitterReplaced, by, source: None, assignee: None });
+    }
 
-        self.controller.set_all_to(RPILedController::BLACK);
+    /// Records that the care reminder was done, starting its next interval.
+    fn care_done(&mut self, name: Option<String>, by: Option<String>) {
+        let now = self.clock.now();
+        let name = name.or_else(|| self.care.configs().iter()
+            .filter(|config| self.state.care.get(&config.name).is_none_or(|done| now >= care::due_time(config, *done)))
+            .map(|config| config.name.clone())
+            .next());
+        let Some(name) = name.filter(|name| self.care.configs().iter().any(|config| config.name == *name)) else {
+            log::warn!("No such care reminder is due or configured");
+            return;
+        };
+        log::info!("The {} was done by {}", name, by.as_deref().unwrap_or("unknown"));
+        self.state.care.insert(name.clone(), now);
+        self.save_state();
+        self.record(HistoryEntry { time: now, event: HistoryEvent::CareDone(name), by, source: None, assignee: None });
     }
 
-    /// Checks if the button was pushed and if so, resets the state
-    fn reset_state_if_button_pushed(&mut self) {
-        let button_pushed = self.read_button_state().unwrap();
-        if button_pushed {
-            // reset
-            self.last_cleaning_time = crate::reset_state();
-            self.transport_tx.send(TransportEvent::CleaningTimeReset(self.last_cleaning_time)).expect("Could not send updated state to transport module");
+    /// Snoozes the displays and notifications until the given time, and records who did it. Pausing snoozes them until
+    /// the end of times.
+    fn snooze_until(&mut self, until: DateTime<Utc>, by: Option<String>) {
+        let now = self.clock.now();
+        let event = if until == DateTime::<Utc>::MAX_UTC {
+            HistoryEvent::Paused
+        } else if until <= now {
+            HistoryEvent::Resumed
+        } else {
+            HistoryEvent::Snoozed(until)
+        };
+        self.set_snooze(now, until);
+        self.record(HistoryEntry { time: now, event, by: by.map(|by| users::resolve(&by)), source: None, assignee: None });
+        self.propagate(TransportEvent::Snoozed(now, until));
+    }
+
+    /// Snoozes the displays and notifications until the given time, the snooze having been set at the first one. The
+    /// state is written right away, as a snooze is usually over before the next flush.
+    fn set_snooze(&mut self, snoozed_at: DateTime<Utc>, until: DateTime<Utc>) {
+        self.snoozed_until = Some(until);
+        self.state.snoozed_at = Some(snoozed_at);
+        self.save_state();
+        self.store(StorageEvent::Flush);
+        self.notify(NotifierEvent::Snoozed(until));
+    }
+
+    /// Enables the guest mode until the given time, ending it if the time is not in the future.
+    fn guest_until(&mut self, until: DateTime<Utc>) {
+        if until > self.clock.now() {
+            log::info!("Guest mode until {}", until);
+            self.guest_until = Some(until);
+        } else {
+            log::info!("Ending guest mode");
+            self.guest_until = None;
         }
+        self.save_state();
     }
 
-    /// Reads the push button state. Expects the button to be connected at [GPIO_BUTTON_PIN]
-    ///
-    /// # Errors
-    ///
-    /// This function will return an error if the GPIO value cannot be read.
-    fn read_button_state(&self) -> std::io::Result<bool> {
-        let opts = Options::input([GPIO_BUTTON_PIN]);
-        let inputs = self.chip.request_lines(opts)?;
-        let values = inputs.get_values([false; 1])?;
-        // false if pushed
-        Ok(!values[0])
+    fn is_guest_mode(&self) -> bool {
+        self.guest_until.is_some_and(|until| self.clock.now() < until)
+    }
+
+    /// Persists the state, with the current rotation, snooze and guest mode.
+    fn save_state(&mut self) {
+        self.state.rotation_index = Some(self.rotation_index);
+        self.state.paused = self.snoozed_until == Some(DateTime::<Utc>::MAX_UTC);
+        self.state.snoozed_until = self.snoozed_until.filter(|until| *until != DateTime::<Utc>::MAX_UTC);
+        self.state.guest_until = self.guest_until;
+        self.store(StorageEvent::StateChanged(self.state.clone()));
+    }
+
+    /// Records state transitions in the history and forwards them to the notification channels.
+    fn notify_if_state_changed(&mut self, current_state: LEDStripState) {
+        if self.last_state != Some(current_state) {
+            self.last_state = Some(current_state);
+            self.record(HistoryEntry::new(self.clock.now(), HistoryEvent::StateChanged(current_state)));
+            self.notify(NotifierEvent::StateChanged(current_state, self.last_cleaning_time, self.assignee()));
+        }
     }
+
+    fn assignee(&self) -> Option<String> {
+        if self.members.is_empty() {
+            None
+        } else {
+            Some(self.members[self.rotation_index % self.members.len()].clone())
+        }
+    }
+
+    fn notify(&self, event: NotifierEvent) {
+        if self.notifier_tx.send(event).is_err() {
+            log::error!("{}", CatReminderError::ChannelClosed("notifier"));
+        }
+    }
+
+    fn store(&self, event: StorageEvent) {
+        // a dry run leaves the state and the history alone
+        if self.dry_run {
+            return;
+        }
+        if self.storage_tx.send(event).is_err() {
+            log::error!("{}", CatReminderError::ChannelClosed("storage"));
+        }
+    }
+
+    fn propagate(&self, event: TransportEvent) {
+        // the other nodes are not connected during a dry run
+        if self.dry_run {
+            return;
+        }
+        if self.transport_tx.send(event).is_err() {
+            log::error!("{}", CatReminderError::ChannelClosed("transport"));
+        }
+    }
+
+    /// Records the event in the history and streams it to the WebSocket clients.
+    fn record(&self, entry: HistoryEntry) {
+        let time = entry.time;
+        let stream_event = match &entry.event {
+            HistoryEvent::Cleaned => StreamEvent::Reset { time, by: entry.by.clone(), source: entry.source.clone() },
+            HistoryEvent::CatVisited => StreamEvent::CatVisited { time },
+            HistoryEvent::Weighed(grams) => StreamEvent::Weighed { time, grams: *grams },
+            HistoryEvent::ClimateMeasured(climate) => StreamEvent::ClimateMeasured { time, climate: *climate },
+            HistoryEvent::StateChanged(state) => StreamEvent::StateChanged { state: *state, last_cleaning_time: self.last_cleaning_time },
+            HistoryEvent::Snoozed(until) => StreamEvent::Snoozed { time, until: *until, by: entry.by.clone() },
+            HistoryEvent::Paused => StreamEvent::Paused { time, by: entry.by.clone() },
+            HistoryEvent::Resumed => StreamEvent::Resumed { time, by: entry.by.clone() },
+            HistoryEvent::LitterReplaced => StreamEvent::LitterReplaced { time, by: entry.by.clone() },
+            HistoryEvent::BagOpened => StreamEvent::BagOpened { time, by: entry.by.clone() },
+            HistoryEvent::CareDone(name) => StreamEvent::CareDone { time, name: name.clone(), by: entry.by.clone() }
+        };
+        // there is no receiver if the WebSocket server is disabled
+        let _ = self.event_tx.send(stream_event);
+        self.store(StorageEvent::Recorded(entry));
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,132 @@
+//! The `replay` command, which feeds a recorded history through the reminder with a simulated clock and prints what
+//! the terminal display shows along the way, so that e.g. a strip that went red too early can be reproduced exactly.
+//! The schedule and the other settings are the ones of the current configuration, and nothing is stored or sent.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, mpsc, RwLock};
+use std::sync::atomic::AtomicUsize;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::animation::Animation;
+use crate::care::CareReminders;
+use crate::clock::{Clock, SimulatedClock};
+use crate::config::{self, schedule};
+use crate::history::{self, HistoryEntry, HistoryEvent};
+use crate::holiday::Holidays;
+use crate::reminder::{LEDStripState, Reminder, ReminderEvent, ReminderStatus, ResetSource};
+use crate::state::{State, DEFAULT_BOX};
+use crate::terminal::TerminalDisplay;
+
+/// Simulated time between two steps of the reminder between the recorded events
+const STEP_MINUTES: i64 = 1;
+
+/// Replays the given history file, the one of the node if not given, from the given local date on, printing the states
+/// shown and where they differ from the recorded ones. Returns the number of differences.
+///
+/// # Errors
+///
+/// This function will return an error if the history cannot be read.
+pub fn run(file: Option<&Path>, since: Option<NaiveDate>) -> io::Result<usize> {
+    let config = config::load();
+    let mut entries = match file {
+        Some(file) => history::load_from(file)?,
+        None => history::load()?
+    };
+    entries.sort_by_key(|entry| entry.time);
+    let start = since.and_then(|date| date.and_hms_opt(0, 0, 0))
+        .and_then(|time| time.and_local_timezone(schedule().timezone).earliest())
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|| entries.first().map(|entry| entry.time))
+        .unwrap_or_else(Utc::now);
+    // the replay starts with the last cleaning before it, as the reminder would have
+    let last_cleaning_time = entries.iter().rev()
+        .find(|entry| entry.time < start && entry.event == HistoryEvent::Cleaned)
+        .map_or(start, |entry| entry.time);
+    entries.retain(|entry| entry.time >= start);
+    println!("Replaying {} history entries from {}", entries.len(), start.with_timezone(&schedule().timezone).format("%Y-%m-%d %H:%M"));
+
+    let clock = Arc::new(SimulatedClock::new(start));
+    let (reminder_tx, reminder_rx) = mpsc::channel();
+    // the receivers are kept so that the reminder does not log closed channels
+    let (storage_tx, _storage_rx) = mpsc::channel();
+    let (transport_tx, _transport_rx) = mpsc::channel();
+    let (notifier_tx, _notifier_rx) = mpsc::channel();
+    let (event_tx, _event_rx) = mpsc::channel();
+    let state = State::new(last_cleaning_time);
+    let status = Arc::new(RwLock::new(ReminderStatus { last_cleaning_time, state: LEDStripState::LightGreen,
+        due_time: schedule().due_time(DEFAULT_BOX, last_cleaning_time), assignee: None, climate: None, cpu_temperature: None,
+        snoozed_until: None, quiet: false, guest_until: None, in_progress_since: None, litter: Default::default(), next_full_change: None, bag_runs_out: None,
+        care_due: Vec::new(), zones: Default::default(), failing: BTreeSet::new(), scoops: None }));
+    let visits_per_escalation = config.sensors.pir.as_ref().and_then(|pir| pir.visits_per_escalation);
+    let blending = config.sensors.ammonia.as_ref().map(|ammonia| ammonia.blending).unwrap_or_default();
+    let mut reminder = Reminder {
+        displays: vec![Box::new(TerminalDisplay::replaying(clock.clone()))], storage_tx, state, reminder_rx, transport_tx, notifier_tx, event_tx,
+        last_cleaning_time, last_state: None, status, peer_count: Arc::new(AtomicUsize::new(0)),
+        members: config.chores.members, rotation_index: 0, visits: 0, visits_per_escalation,
+        smell: None, blending, climate: None, ambient_light: None, cpu_temperature: None, throttling: None, on_battery: false, nobody_home: false,
+        snoozed_until: None, guest_until: None, snooze_selection: None, selected_box: None, reset_armed: false, full_change_due: false, supplies: config.supplies, supplies_low: false,
+        care: CareReminders::new(config.care), animation: Animation::new(config.displays.patterns.clone()), overnight_states: None, in_progress_since: None,
+        failing_subsystems: BTreeSet::new(), scoops: Vec::new(), holidays: Holidays::new(config.holidays.unwrap_or_default()), clock: clock.clone(),
+        demo_since: None, dry_run: true
+    };
+
+    let mut differences = 0;
+    let mut entries = entries.into_iter().peekable();
+    while let Some(entry) = entries.next() {
+        while clock.now() + Duration::minutes(STEP_MINUTES) <= entry.time {
+            clock.advance(Duration::minutes(STEP_MINUTES));
+            reminder.step();
+        }
+        clock.advance(entry.time - clock.now());
+        // replacing the litter records a cleaning first, which the replacement replays on its own
+        let is_replacement = entry.event == HistoryEvent::Cleaned && entries.peek()
+            .is_some_and(|next| next.time == entry.time && next.event == HistoryEvent::LitterReplaced);
+        if let HistoryEvent::StateChanged(recorded) = entry.event {
+            reminder.step();
+            if reminder.last_state != Some(recorded) {
+                differences += 1;
+                println!("{} recorded {:?}, replayed {:?}", local_time(entry.time), recorded, reminder.last_state);
+            }
+        } else if let Some(event) = event(&entry).filter(|_| !is_replacement) {
+            println!("{} {}", local_time(entry.time), describe(&entry));
+            reminder_tx.send(event).expect("The reminder of the replay is gone");
+            reminder.step();
+        }
+    }
+    reminder.step();
+    println!("Replay done, {} state changes differing from the recorded ones", differences);
+    Ok(differences)
+}
+
+/// The event that made the reminder record the entry, none for the state changes, which the reminder records itself.
+fn event(entry: &HistoryEntry) -> Option<ReminderEvent> {
+    let source = entry.source.clone().unwrap_or(ResetSource::Button);
+    let by = entry.by.clone();
+    match &entry.event {
+        HistoryEvent::Cleaned => Some(ReminderEvent::ResetRequested(source, by)),
+        HistoryEvent::LitterReplaced => Some(ReminderEvent::LitterReplaced(source, by)),
+        HistoryEvent::StateChanged(_) => None,
+        HistoryEvent::CatVisited => Some(ReminderEvent::CatVisited(entry.time)),
+        HistoryEvent::Weighed(grams) => Some(ReminderEvent::WeightMeasured(entry.time, *grams)),
+        HistoryEvent::ClimateMeasured(climate) => Some(ReminderEvent::ClimateMeasured(entry.time, *climate)),
+        HistoryEvent::Snoozed(until) => Some(ReminderEvent::Snooze(*until - entry.time, by)),
+        HistoryEvent::Paused => Some(ReminderEvent::TogglePause(by)),
+        HistoryEvent::Resumed => Some(ReminderEvent::Snooze(Duration::zero(), by)),
+        HistoryEvent::BagOpened => Some(ReminderEvent::BagOpened(by)),
+        HistoryEvent::CareDone(name) => Some(ReminderEvent::CareDone(Some(name.clone()), by))
+    }
+}
+
+fn describe(entry: &HistoryEntry) -> String {
+    match &entry.by {
+        Some(by) => format!("{:?} by {}", entry.event, by),
+        None => format!("{:?}", entry.event)
+    }
+}
+
+fn local_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&schedule().timezone).format("%Y-%m-%d %H:%M").to_string()
+}
@@ -0,0 +1,143 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use chrono::Utc;
+use linux_embedded_hal::gpio_cdev::{Chip, Error, LineHandle, LineRequestFlags};
+use serde::Deserialize;
+
+use crate::display::{Display, DisplayState};
+
+const BIT_DELAY: Duration = Duration::from_micros(5);
+
+const DATA_AUTO_INCREMENT: u8 = 0x40;
+const ADDRESS_FIRST_DIGIT: u8 = 0xC0;
+const DISPLAY_ON: u8 = 0x88;
+const DISPLAY_OFF: u8 = 0x80;
+const DECIMAL_POINT: u8 = 0x80;
+const DIGITS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+
+/// Pins of a TM1637 four-digit seven-segment display.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct SegmentConfig {
+    pub gpio_chip: String,
+    pub clk_pin: u32,
+    pub dio_pin: u32,
+    /// Brightness between 0 and 7
+    pub brightness: u8
+}
+
+impl Default for SegmentConfig {
+    fn default() -> Self {
+        SegmentConfig {
+            gpio_chip: "/dev/gpiochip0".to_string(),
+            clk_pin: 20,
+            dio_pin: 21,
+            brightness: 2
+        }
+    }
+}
+
+/// A TM1637 display driven by bit-banging its two-wire interface.
+struct Tm1637 {
+    clk: LineHandle,
+    dio: LineHandle
+}
+
+impl Tm1637 {
+    /// Opens the GPIO lines of the display.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the GPIO lines cannot be requested.
+    fn new(config: &SegmentConfig) -> Self {
+        let mut chip = Chip::new(&config.gpio_chip).expect("Could not open GPIO chip");
+        let mut line = |offset: u32| {
+            chip.get_line(offset)
+                .and_then(|l| l.request(LineRequestFlags::OUTPUT | LineRequestFlags::OPEN_DRAIN, 1, "cat-reminder-segment"))
+                .expect("Could not request GPIO line")
+        };
+        Tm1637 { clk: line(config.clk_pin), dio: line(config.dio_pin) }
+    }
+
+    fn show(&mut self, segments: [u8; 4], brightness: Option<u8>) -> Result<(), Error> {
+        self.command(&[DATA_AUTO_INCREMENT])?;
+        let mut data = vec![ADDRESS_FIRST_DIGIT];
+        data.extend_from_slice(&segments);
+        self.command(&data)?;
+        self.command(&[brightness.map_or(DISPLAY_OFF, |b| DISPLAY_ON | b.min(7))])
+    }
+
+    fn command(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        // start condition: data goes low while the clock is high
+        self.dio.set_value(0)?;
+        sleep(BIT_DELAY);
+        for byte in bytes {
+            for bit in 0..8 {
+                self.clk.set_value(0)?;
+                self.dio.set_value((byte >> bit) & 1)?;
+                sleep(BIT_DELAY);
+                self.clk.set_value(1)?;
+                sleep(BIT_DELAY);
+            }
+            // release the data line for the acknowledgement, which is not checked
+            self.clk.set_value(0)?;
+            self.dio.set_value(1)?;
+            sleep(BIT_DELAY);
+            self.clk.set_value(1)?;
+            sleep(BIT_DELAY);
+        }
+        // stop condition: data goes high while the clock is high
+        self.clk.set_value(0)?;
+        self.dio.set_value(0)?;
+        sleep(BIT_DELAY);
+        self.clk.set_value(1)?;
+        sleep(BIT_DELAY);
+        self.dio.set_value(1)?;
+        sleep(BIT_DELAY);
+        Ok(())
+    }
+}
+
+/// A TM1637 display showing the time since the last cleaning as `HH.MM`. The display is off during the night, like the strip.
+pub struct SegmentDisplay {
+    tm1637: Tm1637,
+    brightness: u8,
+    shown: Option<([u8; 4], Option<u8>)>
+}
+
+impl SegmentDisplay {
+    pub fn new(config: &SegmentConfig) -> Self {
+        SegmentDisplay { tm1637: Tm1637::new(config), brightness: config.brightness, shown: None }
+    }
+}
+
+impl Display for SegmentDisplay {
+    fn name(&self) -> &str {
+        "segment"
+    }
+
+    fn render(&mut self, state: &DisplayState) {
+        let elapsed = Utc::now().signed_duration_since(state.status.last_cleaning_time);
+        let current = (segments(elapsed.num_minutes()), if state.is_dark { None } else { Some(self.brightness) });
+        if self.shown != Some(current) {
+            match self.tm1637.show(current.0, current.1) {
+                Ok(()) => self.shown = Some(current),
+                Err(e) => log::error!("Could not update the seven-segment display: {:?}", e)
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        if let Err(e) = self.tm1637.show([0; 4], None) {
+            log::error!("Could not turn off the seven-segment display: {:?}", e);
+        }
+    }
+}
+
+/// Segments showing the given number of minutes as `HH.MM`, capped at `99.59`.
+fn segments(minutes: i64) -> [u8; 4] {
+    let minutes = minutes.clamp(0, 99 * 60 + 59);
+    let (hours, minutes) = ((minutes / 60) as usize, (minutes % 60) as usize);
+    [DIGITS[hours / 10], DIGITS[hours % 10] | DECIMAL_POINT, DIGITS[minutes / 10], DIGITS[minutes % 10]]
+}
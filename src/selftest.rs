@@ -0,0 +1,218 @@
+//! The `selftest` command, which walks every configured peripheral so that wiring mistakes show up before the
+//! reminder is put to use. What cannot be measured, such as an LED lighting up, is confirmed by whoever runs it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use crate::config::{self, Config};
+use crate::display::DisplayState;
+use crate::gpio::{Chip, Input, Lines, Options};
+use crate::health::Health;
+use crate::input::{self, InputConfig};
+use crate::led::{LedController, RPILedController};
+use crate::reminder::{LEDStripState, ReminderStatus};
+use crate::relay::RelayConfig;
+use crate::sensor::{ammonia, dht22, load_cell, nfc, SensorsConfig};
+use crate::sound::{self, SoundConfig};
+
+const LED_DELAY: Duration = Duration::from_millis(400);
+const BLINK_DELAY: Duration = Duration::from_millis(500);
+const RELAY_DELAY: Duration = Duration::from_secs(1);
+const ACTION_TIMEOUT: Duration = Duration::from_secs(15);
+const POLL_DELAY: Duration = Duration::from_millis(20);
+
+/// The outcome of testing a device, named after its kind and, if there are several of them, its pin.
+pub struct DeviceCheck {
+    pub device: String,
+    pub health: Health,
+    pub detail: String
+}
+
+impl DeviceCheck {
+    fn new(device: impl Into<String>, health: Health, detail: impl Into<String>) -> Self {
+        DeviceCheck { device: device.into(), health, detail: detail.into() }
+    }
+}
+
+/// Tests the peripherals one after the other, prints the outcome for each of them, and returns whether none failed.
+/// The reminder must not be running, as it holds the same GPIO lines and the strip.
+pub fn report() -> bool {
+    let config = config::load();
+    let mut checks = Vec::new();
+    if config.displays.strip {
+        checks.push(guarded("strip", || strip(config.displays.led_count)));
+    }
+    let inputs = if config.inputs.is_empty() { input::default_inputs() } else { config.inputs.clone() };
+    checks.extend(inputs.iter().map(button));
+    if let Some(sound_config) = &config.sounds {
+        checks.push(sounds(sound_config));
+    }
+    if let Some(relay_config) = &config.relay {
+        checks.push(relay(relay_config));
+    }
+    checks.extend(sensors(&config.sensors));
+    checks.extend(displays(config));
+
+    println!();
+    for check in &checks {
+        println!("{:<12} {:<8} {}", check.device, format!("{:?}", check.health).to_lowercase(), check.detail);
+    }
+    checks.iter().all(|check| check.health != Health::Failed)
+}
+
+/// Lights the LEDs one by one, so that a broken LED or a wrong LED count shows.
+fn strip(led_count: usize) -> DeviceCheck {
+    println!("Lighting the LEDs of the strip one after the other");
+    let mut strip = RPILedController::with_led_count(led_count);
+    let count = strip.led_count();
+    for index in 0..count {
+        if let Err(e) = strip.set_only(index, RPILedController::WHITE) {
+            return DeviceCheck::new("strip", Health::Failed, format!("LED {}: {}", index + 1, e));
+        }
+        sleep(LED_DELAY);
+    }
+    if let Err(e) = strip.set_all_to(RPILedController::BLACK) {
+        return DeviceCheck::new("strip", Health::Failed, e.to_string());
+    }
+    confirm("strip", &format!("Did the {} LEDs light up in white, one after the other?", count))
+}
+
+fn button(config: &InputConfig) -> DeviceCheck {
+    let device = format!("input {}", config.pin);
+    wait_for(&device, &format!("Press the {:?} input on GPIO {}", config.action, config.pin), true, |chip| input::request(chip, config))
+}
+
+fn sounds(config: &SoundConfig) -> DeviceCheck {
+    let clips: Vec<&Option<String>> = [&config.reset, &config.due, &config.overdue].into_iter().filter(|clip| clip.is_some()).collect();
+    if clips.is_empty() {
+        return DeviceCheck::new("sound", Health::Ok, "no clips configured");
+    }
+    println!("Playing the sound clips");
+    if !clips.iter().all(|clip| sound::play(config, clip)) {
+        return DeviceCheck::new("sound", Health::Failed, format!("could not play all clips with {}", config.player));
+    }
+    confirm("sound", "Did you hear the clips?")
+}
+
+fn relay(config: &RelayConfig) -> DeviceCheck {
+    println!("Switching the relay on and off");
+    let result = Chip::new("gpiochip0")
+        .and_then(|chip| chip.request_lines(Options::output([config.pin]).values([config.active_low]).consumer("cat-reminder-selftest")))
+        .and_then(|output| {
+            output.set_values([!config.active_low])?;
+            sleep(RELAY_DELAY);
+            output.set_values([config.active_low])
+        });
+    match result {
+        Ok(()) => confirm("relay", &format!("Did the relay on GPIO {} click on and off?", config.pin)),
+        Err(e) => DeviceCheck::new("relay", Health::Failed, e.to_string())
+    }
+}
+
+fn sensors(config: &SensorsConfig) -> Vec<DeviceCheck> {
+    let mut checks = Vec::new();
+    if let Some(ammonia_config) = &config.ammonia {
+        checks.push(match ammonia::read_once(ammonia_config) {
+            Ok(raw) => DeviceCheck::new("ammonia", Health::Ok, format!("raw reading {}", raw)),
+            Err(e) => DeviceCheck::new("ammonia", Health::Failed, e.to_string())
+        });
+    }
+    if let Some(dht22_config) = &config.dht22 {
+        checks.push(match dht22::read(Path::new(&dht22_config.iio_device)) {
+            Ok(climate) => DeviceCheck::new("dht22", Health::Ok, format!("{:.1}°C, {:.0}%", climate.temperature, climate.humidity)),
+            Err(e) => DeviceCheck::new("dht22", Health::Failed, e.to_string())
+        });
+    }
+    if let Some(door_config) = &config.door {
+        let request = |chip: &Chip| chip.request_lines(Options::input([door_config.pin]).consumer("cat-reminder-selftest"));
+        checks.push(wait_for("door", "Open the door of the litter box", door_config.open_when_high, request));
+    }
+    if let Some(load_cell_config) = &config.load_cell {
+        checks.push(match load_cell::read_once(load_cell_config) {
+            Ok(raw) => DeviceCheck::new("load_cell", Health::Ok, format!("raw reading {}", raw)),
+            Err(e) => DeviceCheck::new("load_cell", Health::Failed, e.to_string())
+        });
+    }
+    if let Some(nfc_config) = &config.nfc {
+        checks.push(match nfc::probe(nfc_config) {
+            Ok(version) => DeviceCheck::new("nfc", Health::Ok, format!("reader version {:#04x}", version)),
+            Err(e) => DeviceCheck::new("nfc", Health::Failed, e.to_string())
+        });
+    }
+    if let Some(pir_config) = &config.pir {
+        let request = |chip: &Chip| chip.request_lines(Options::input([pir_config.pin]).consumer("cat-reminder-selftest"));
+        checks.push(wait_for("pir", "Move in front of the motion sensor", true, request));
+    }
+    checks
+}
+
+/// Shows all states on each display other than the strip, which was tested LED by LED, and the terminal.
+fn displays(mut config: Config) -> Vec<DeviceCheck> {
+    config.displays.strip = false;
+    config.displays.terminal = false;
+    let displays = match panic::catch_unwind(AssertUnwindSafe(|| crate::displays(&config, None))) {
+        Ok(displays) => displays,
+        Err(_) => return vec![DeviceCheck::new("displays", Health::Failed, "could not be initialized, see the error above")]
+    };
+    displays.into_iter().map(|mut display| {
+        println!("Showing all states on the {} display", display.name());
+        for state in LEDStripState::ALL {
+            for blink_on in [true, false] {
+                let last_cleaning_time = Utc::now() - state.starts_after();
+                let status = ReminderStatus { last_cleaning_time, state, due_time: last_cleaning_time + LEDStripState::Red.starts_after(), assignee: None, climate: None,
+                    cpu_temperature: None, snoozed_until: None, quiet: false, guest_until: None, in_progress_since: None, litter: Default::default(), next_full_change: None, bag_runs_out: None,
+                    care_due: Vec::new(), zones: BTreeMap::new(), failing: BTreeSet::new(), scoops: None };
+                display.render(&DisplayState { status, own_state: state, peer_count: 0, is_dark: false, is_dimmed: false, brightness: 1.0, snooze_selection: None, is_reset_armed: false, is_supply_low: false, blink_on, chase: None,
+                    is_failing: false, progress: 1.0 });
+                sleep(BLINK_DELAY);
+            }
+        }
+        display.clear();
+        let name = display.name().to_string();
+        confirm(&name, &format!("Did the {} display show the states from light green to blinking red?", name))
+    }).collect()
+}
+
+/// Waits for a line to reach the value, after asking for the action that should make it do so.
+fn wait_for(device: &str, action: &str, value: bool, request: impl FnOnce(&Chip) -> io::Result<Lines<Input>>) -> DeviceCheck {
+    if cfg!(not(hardware)) {
+        return DeviceCheck::new(device, Health::Ok, "simulated");
+    }
+    let line = match Chip::new("gpiochip0").and_then(|chip| request(&chip)) {
+        Ok(line) => line,
+        Err(e) => return DeviceCheck::new(device, Health::Failed, e.to_string())
+    };
+    println!("{} within {} seconds", action, ACTION_TIMEOUT.as_secs());
+    let started = Instant::now();
+    while started.elapsed() < ACTION_TIMEOUT {
+        match line.get_values([false; 1]) {
+            Ok([current]) if current == value => return DeviceCheck::new(device, Health::Ok, "detected"),
+            Ok(_) => sleep(POLL_DELAY),
+            Err(e) => return DeviceCheck::new(device, Health::Failed, e.to_string())
+        }
+    }
+    DeviceCheck::new(device, Health::Failed, "nothing detected, check the wiring and the pin")
+}
+
+/// Asks whether the device did what it should, the test failing unless the answer is yes.
+fn confirm(device: &str, question: &str) -> DeviceCheck {
+    print!("{} [y/n] ", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(_) if answer.trim().eq_ignore_ascii_case("y") => DeviceCheck::new(device, Health::Ok, "confirmed"),
+        _ => DeviceCheck::new(device, Health::Failed, "not confirmed")
+    }
+}
+
+/// Runs a test whose driver panics when the device cannot be initialized, turning the panic into a failure.
+fn guarded(device: &str, test: impl FnOnce() -> DeviceCheck) -> DeviceCheck {
+    panic::catch_unwind(AssertUnwindSafe(test))
+        .unwrap_or_else(|_| DeviceCheck::new(device, Health::Failed, "could not be initialized, see the error above"))
+}
@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration;
+
+use linux_embedded_hal::spidev::{SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer};
+use serde::Deserialize;
+
+use crate::reminder::{LEDStripState, ReminderEvent};
+
+const POLL_DELAY: Duration = Duration::from_millis(1000);
+const SMOOTHING_SAMPLES: usize = 30;
+
+/// An MQ-137 ammonia sensor read through an MCP3008 ADC.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct AmmoniaConfig {
+    pub spi_device: String,
+    /// ADC channel the sensor is connected to, between 0 and 7
+    pub channel: u8,
+    /// Raw reading in clean air. If not set, the lowest reading since startup is used.
+    pub baseline: Option<u16>,
+    /// Readings above the baseline at which the smell reaches the dark green, orange, red and blinking red states
+    pub thresholds: [u16; 4],
+    pub blending: Blending
+}
+
+impl Default for AmmoniaConfig {
+    fn default() -> Self {
+        AmmoniaConfig {
+            spi_device: "/dev/spidev0.1".to_string(),
+            channel: 0,
+            baseline: None,
+            thresholds: [40, 80, 160, 240],
+            blending: Blending::default()
+        }
+    }
+}
+
+/// How the state measured by the sensor is combined with the state resulting from the time since the last cleaning.
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Blending {
+    /// The more urgent of both states
+    #[default]
+    Max,
+    /// The state halfway between both, rounded towards the more urgent one
+    Average,
+    /// Only the state measured by the sensor
+    Sensor
+}
+
+impl Blending {
+    pub fn blend(&self, time_state: LEDStripState, sensor_state: LEDStripState) -> LEDStripState {
+        match self {
+            Blending::Max => time_state.max(sensor_state),
+            Blending::Average => LEDStripState::ALL[(time_state as usize + sensor_state as usize).div_ceil(2)],
+            Blending::Sensor => sensor_state
+        }
+    }
+}
+
+/// Runs the ammonia sensor thread, which reports the state matching the smoothed readings to the reminder whenever
+/// it changes. MQ sensors need to heat up for a few minutes before their readings are meaningful.
+///
+/// # Panics
+///
+/// Panics if the SPI device cannot be opened.
+pub fn run(config: AmmoniaConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let spi = open(&config.spi_device).expect("Could not open SPI device");
+    std::thread::spawn(move || {
+        let mut samples: VecDeque<u16> = VecDeque::new();
+        let mut lowest: Option<u16> = None;
+        let mut reported: Option<LEDStripState> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let raw = match read_mcp3008(&spi, config.channel) {
+                Ok(raw) => raw,
+                Err(e) => {
+                    log::warn!("Could not read ammonia sensor: {}", e);
+                    continue;
+                }
+            };
+            samples.push_back(raw);
+            if samples.len() > SMOOTHING_SAMPLES {
+                samples.pop_front();
+            } else if samples.len() < SMOOTHING_SAMPLES {
+                continue;
+            }
+            let reading = (samples.iter().map(|s| *s as u32).sum::<u32>() / samples.len() as u32) as u16;
+            lowest = Some(lowest.map_or(reading, |l| l.min(reading)));
+            let baseline = config.baseline.or(lowest).unwrap_or(reading);
+            log::debug!("Ammonia sensor reading: {}, baseline: {}", reading, baseline);
+
+            let above_baseline = reading.saturating_sub(baseline);
+            let level = config.thresholds.iter().filter(|t| above_baseline >= **t).count();
+            let state = LEDStripState::ALL[level];
+            if reported != Some(state) {
+                log::info!("Ammonia level corresponds to {:?}", state);
+                reported = Some(state);
+                if reminder_tx.send(ReminderEvent::SmellMeasured(state)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+fn open(spi_device: &str) -> std::io::Result<Spidev> {
+    let mut spi = Spidev::open(spi_device)?;
+    spi.configure(&SpidevOptions::new().bits_per_word(8).max_speed_hz(1_000_000).mode(SpiModeFlags::SPI_MODE_0).build())?;
+    Ok(spi)
+}
+
+/// Takes a single raw reading, for the self-test.
+pub(crate) fn read_once(config: &AmmoniaConfig) -> std::io::Result<u16> {
+    read_mcp3008(&open(&config.spi_device)?, config.channel)
+}
+
+/// Reads a single-ended channel of the MCP3008, returning a value between 0 and 1023.
+fn read_mcp3008(spi: &Spidev, channel: u8) -> std::io::Result<u16> {
+    let tx = [0x01, (0x08 | (channel & 0x07)) << 4, 0x00];
+    let mut rx = [0u8; 3];
+    spi.transfer(&mut SpidevTransfer::read_write(&tx, &mut rx))?;
+    Ok((((rx[1] & 0x03) as u16) << 8) | rx[2] as u16)
+}
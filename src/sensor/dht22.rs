@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::reminder::ReminderEvent;
+use crate::sensor::Climate;
+
+const POLL_DELAY: Duration = Duration::from_millis(1000);
+
+/// A DHT22/AM2302 temperature and humidity sensor near the litter box. The sensor's timing is too tight to be read
+/// from user space reliably, so it is read through the kernel driver, enabled with `dtoverlay=dht11,gpiopin=4`.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Dht22Config {
+    /// IIO device created by the kernel driver
+    pub iio_device: String,
+    pub interval_seconds: u64
+}
+
+impl Default for Dht22Config {
+    fn default() -> Self {
+        Dht22Config { iio_device: "/sys/bus/iio/devices/iio:device0".to_string(), interval_seconds: 300 }
+    }
+}
+
+/// Runs the DHT22 thread, which periodically reports the temperature and humidity to the reminder.
+pub fn run(config: Dht22Config, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let mut last_reading: Option<chrono::DateTime<Utc>> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let now = Utc::now();
+            if last_reading.is_some_and(|t| now.signed_duration_since(t).num_seconds() < config.interval_seconds as i64) {
+                continue;
+            }
+            last_reading = Some(now);
+
+            match read(Path::new(&config.iio_device)) {
+                Ok(climate) => {
+                    if reminder_tx.send(ReminderEvent::ClimateMeasured(now, climate)).is_err() {
+                        break;
+                    }
+                }
+                // the driver regularly fails on checksum errors, the next reading usually succeeds
+                Err(e) => log::warn!("Could not read DHT22: {}", e)
+            }
+        }
+    });
+}
+
+/// Reads the sensor, whose driver reports values in thousandths of degrees Celsius and percent.
+pub(crate) fn read(device: &Path) -> std::io::Result<Climate> {
+    let value = |file: &str| -> std::io::Result<f64> {
+        fs::read_to_string(device.join(file))?.trim().parse::<f64>()
+            .map(|v| v / 1000.0)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    };
+    Ok(Climate { temperature: value("in_temp_input")?, humidity: value("in_humidityrelative_input")? })
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::gpio::{Chip, Options};
+use crate::reminder::{ReminderEvent, ResetSource};
+
+const POLL_DELAY: StdDuration = StdDuration::from_millis(200);
+
+/// A magnetic reed switch on the door of the litter box enclosure.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct DoorConfig {
+    pub pin: u32,
+    /// Time the door needs to be held open for it to count as a cleaning
+    pub open_seconds: i64,
+    /// Whether the line is high while the door is open, which is the case with the switch pulling the line low when closed
+    pub open_when_high: bool,
+    /// Whether opening the door only marks the cleaning as in progress, the reset being left to an input or the web UI
+    pub confirm: bool
+}
+
+impl Default for DoorConfig {
+    fn default() -> Self {
+        DoorConfig { pin: 13, open_seconds: 30, open_when_high: true, confirm: false }
+    }
+}
+
+/// Runs the door sensor thread, which reports a cleaning once per opening when the door stays open long enough, or
+/// that a cleaning started as soon as it opens if the cleaning needs to be confirmed.
+///
+/// # Panics
+///
+/// Panics if the GPIO line of the switch cannot be requested.
+pub fn run(config: DoorConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let chip = Chip::new("gpiochip0").expect("Cannot open GPIO");
+    let input = chip.request_lines(Options::input([config.pin]).consumer("cat-reminder-door")).expect("Could not request door switch line");
+    std::thread::spawn(move || {
+        let mut opened_at: Option<DateTime<Utc>> = None;
+        let mut reported = false;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            match input.get_values([false; 1]) {
+                Ok([value]) if value == config.open_when_high => {
+                    let now = Utc::now();
+                    let opened_at = *opened_at.get_or_insert(now);
+                    if config.confirm {
+                        if !reported {
+                            reported = true;
+                            if reminder_tx.send(ReminderEvent::CleaningStarted(ResetSource::Sensor("Door".to_string()), None)).is_err() {
+                                break;
+                            }
+                        }
+                    } else if !reported && now.signed_duration_since(opened_at) >= Duration::seconds(config.open_seconds) {
+                        reported = true;
+                        if reminder_tx.send(ReminderEvent::CleaningDetected("Door".to_string())).is_err() {
+                            break;
+                        }
+                    }
+                }
+                Ok(_) => {
+                    opened_at = None;
+                    reported = false;
+                }
+                Err(e) => log::error!("Could not read door switch: {:?}", e)
+            }
+            sleep(POLL_DELAY);
+        }
+    });
+}
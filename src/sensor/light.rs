@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration;
+
+use linux_embedded_hal::i2cdev::core::I2CDevice;
+use linux_embedded_hal::i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+use serde::Deserialize;
+
+use crate::reminder::ReminderEvent;
+
+const POLL_DELAY: Duration = Duration::from_millis(2000);
+/// Change of the level below which it is not reported, so that the brightness does not flicker
+const LEVEL_STEP: f64 = 0.05;
+
+const BH1750_CONTINUOUS_HIGH_RES_MODE: u8 = 0x10;
+const TSL2561_COMMAND: u8 = 0x80;
+const TSL2561_WORD: u8 = 0x20;
+const TSL2561_CONTROL_REGISTER: u8 = 0x00;
+const TSL2561_TIMING_REGISTER: u8 = 0x01;
+const TSL2561_CHANNEL_0_REGISTER: u8 = 0x0C;
+const TSL2561_CHANNEL_1_REGISTER: u8 = 0x0E;
+const TSL2561_POWER_ON: u8 = 0x03;
+/// 16x gain and 402ms integration, for which the lux formulas of the datasheet are given
+const TSL2561_HIGH_GAIN_402_MS: u8 = 0x12;
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LightSensorModel {
+    #[default]
+    Bh1750,
+    Tsl2561
+}
+
+/// An ambient light sensor scaling the brightness of the displays to the light in the room. It replaces the night
+/// determined by the time of day: the displays stay dark while the room is dark, whenever that is.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct LightConfig {
+    pub model: LightSensorModel,
+    pub i2c_bus: String,
+    /// Address of the sensor, 0x23 for the BH1750 and 0x39 for the TSL2561 if not set
+    pub address: Option<u16>,
+    /// Light level in lux at and below which the displays stay dark
+    pub dark_lux: f64,
+    /// Light level in lux from which the displays are at their full brightness
+    pub bright_lux: f64
+}
+
+impl Default for LightConfig {
+    fn default() -> Self {
+        LightConfig { model: LightSensorModel::default(), i2c_bus: "/dev/i2c-1".to_string(), address: None, dark_lux: 1.0, bright_lux: 200.0 }
+    }
+}
+
+impl LightConfig {
+    /// The level of the light, between 0 in the dark and 1 in a bright room. Eyes adapt to the light logarithmically,
+    /// so does the level.
+    pub fn level(&self, lux: f64) -> f64 {
+        if lux <= self.dark_lux {
+            return 0.0;
+        }
+        let dark_lux = self.dark_lux.max(0.1);
+        ((lux / dark_lux).ln() / (self.bright_lux / dark_lux).ln()).clamp(0.0, 1.0)
+    }
+}
+
+/// Runs the light sensor thread, which reports the level of the ambient light to the reminder whenever it changes.
+///
+/// # Panics
+///
+/// Panics if the I²C device cannot be opened.
+pub fn run(config: LightConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let address = config.address.unwrap_or(match config.model {
+        LightSensorModel::Bh1750 => 0x23,
+        LightSensorModel::Tsl2561 => 0x39
+    });
+    let mut device = LinuxI2CDevice::new(&config.i2c_bus, address).expect("Could not open I2C device");
+    std::thread::spawn(move || {
+        if let Err(e) = start(&mut device, config.model) {
+            log::error!("Could not start the light sensor: {:?}", e);
+            return;
+        }
+        let mut reported: Option<f64> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let lux = match read(&mut device, config.model) {
+                Ok(lux) => lux,
+                Err(e) => {
+                    log::warn!("Could not read the light sensor: {:?}", e);
+                    continue;
+                }
+            };
+            let level = config.level(lux);
+            let is_change = match reported {
+                None => true,
+                // reaching the dark or the full brightness is always reported, as they matter most
+                Some(reported) => (level - reported).abs() >= LEVEL_STEP || (level != reported && (level == 0.0 || level == 1.0))
+            };
+            if is_change {
+                log::debug!("Ambient light at {:.1} lux, level {:.2}", lux, level);
+                reported = Some(level);
+                if reminder_tx.send(ReminderEvent::AmbientLightMeasured(level)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+fn start(device: &mut LinuxI2CDevice, model: LightSensorModel) -> Result<(), LinuxI2CError> {
+    match model {
+        LightSensorModel::Bh1750 => device.smbus_write_byte(BH1750_CONTINUOUS_HIGH_RES_MODE),
+        LightSensorModel::Tsl2561 => {
+            device.smbus_write_byte_data(TSL2561_COMMAND | TSL2561_CONTROL_REGISTER, TSL2561_POWER_ON)?;
+            device.smbus_write_byte_data(TSL2561_COMMAND | TSL2561_TIMING_REGISTER, TSL2561_HIGH_GAIN_402_MS)
+        }
+    }
+}
+
+/// Reads the light level in lux.
+fn read(device: &mut LinuxI2CDevice, model: LightSensorModel) -> Result<f64, LinuxI2CError> {
+    match model {
+        LightSensorModel::Bh1750 => {
+            let mut data = [0u8; 2];
+            device.read(&mut data)?;
+            Ok(u16::from_be_bytes(data) as f64 / 1.2)
+        }
+        LightSensorModel::Tsl2561 => {
+            // the channels are little endian, as are SMBus words
+            let broadband = device.smbus_read_word_data(TSL2561_COMMAND | TSL2561_WORD | TSL2561_CHANNEL_0_REGISTER)? as f64;
+            let infrared = device.smbus_read_word_data(TSL2561_COMMAND | TSL2561_WORD | TSL2561_CHANNEL_1_REGISTER)? as f64;
+            Ok(tsl2561_lux(broadband, infrared))
+        }
+    }
+}
+
+/// The lux from the broadband and infrared channels, with the formulas of the datasheet for the T package.
+fn tsl2561_lux(broadband: f64, infrared: f64) -> f64 {
+    if broadband == 0.0 {
+        return 0.0;
+    }
+    let ratio = infrared / broadband;
+    let lux = if ratio <= 0.5 {
+        0.0304 * broadband - 0.062 * broadband * ratio.powf(1.4)
+    } else if ratio <= 0.61 {
+        0.0224 * broadband - 0.031 * infrared
+    } else if ratio <= 0.80 {
+        0.0128 * broadband - 0.0153 * infrared
+    } else if ratio <= 1.30 {
+        0.00146 * broadband - 0.00112 * infrared
+    } else {
+        0.0
+    };
+    lux.max(0.0)
+}
@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::gpio::{Chip, Input, Lines, Options, Output};
+use crate::reminder::ReminderEvent;
+
+const POLL_DELAY: StdDuration = StdDuration::from_millis(500);
+const READY_TIMEOUT: StdDuration = StdDuration::from_millis(200);
+
+/// A load cell under the litter box, connected through an HX711 amplifier.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct LoadCellConfig {
+    pub dout_pin: u32,
+    pub sck_pin: u32,
+    /// Raw reading of the empty scale
+    pub offset: i32,
+    /// Raw units per gram
+    pub scale: f64,
+    /// Smallest drop of the weight that counts as a cleaning
+    pub min_drop_grams: f64,
+    /// Largest drop of the weight that counts as a cleaning, larger drops are cats leaving the box
+    pub max_drop_grams: f64,
+    /// Maximum variation of the weight while it is considered stable
+    pub tolerance_grams: f64,
+    /// Time during which the weight needs to be stable before it is taken into account
+    pub settle_seconds: i64
+}
+
+impl Default for LoadCellConfig {
+    fn default() -> Self {
+        LoadCellConfig {
+            dout_pin: 16,
+            sck_pin: 26,
+            offset: 0,
+            scale: 420.0,
+            min_drop_grams: 30.0,
+            max_drop_grams: 1500.0,
+            tolerance_grams: 10.0,
+            settle_seconds: 10
+        }
+    }
+}
+
+/// An HX711 amplifier read by bit-banging its serial interface, on channel A with a gain of 128.
+struct Hx711 {
+    dout: Lines<Input>,
+    sck: Lines<Output>
+}
+
+impl Hx711 {
+    fn new(config: &LoadCellConfig) -> std::io::Result<Self> {
+        let chip = Chip::new("gpiochip0")?;
+        Ok(Hx711 {
+            dout: chip.request_lines(Options::input([config.dout_pin]).consumer("cat-reminder-hx711"))?,
+            sck: chip.request_lines(Options::output([config.sck_pin]).consumer("cat-reminder-hx711"))?
+        })
+    }
+
+    /// Reads the raw value, waiting for the conversion to be ready.
+    fn read(&self) -> std::io::Result<i32> {
+        let waiting_since = std::time::Instant::now();
+        // the data line goes low when a conversion is ready
+        while self.dout.get_values([false; 1])?[0] {
+            if waiting_since.elapsed() > READY_TIMEOUT {
+                return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "HX711 not ready"));
+            }
+            sleep(StdDuration::from_millis(1));
+        }
+        let mut value: u32 = 0;
+        for _ in 0..24 {
+            self.sck.set_values([true])?;
+            self.sck.set_values([false])?;
+            value = (value << 1) | self.dout.get_values([false; 1])?[0] as u32;
+        }
+        // one more pulse selects channel A with a gain of 128 for the next conversion
+        self.sck.set_values([true])?;
+        self.sck.set_values([false])?;
+        // sign-extend the 24-bit two's complement value
+        Ok(((value << 8) as i32) >> 8)
+    }
+}
+
+/// Takes a single raw reading, for the self-test.
+pub(crate) fn read_once(config: &LoadCellConfig) -> std::io::Result<i32> {
+    Hx711::new(config)?.read()
+}
+
+/// Runs the load cell thread. Whenever the weight settles, it is reported to the reminder so that the weight trend
+/// is logged. A settled weight lower than the previous one by an amount consistent with scooping is reported as a
+/// cleaning, while larger drops are cats leaving the box.
+///
+/// # Panics
+///
+/// Panics if the GPIO lines of the HX711 cannot be requested.
+pub fn run(config: LoadCellConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let hx711 = Hx711::new(&config).expect("Could not request HX711 lines");
+    std::thread::spawn(move || {
+        let mut readings: VecDeque<(DateTime<Utc>, f64)> = VecDeque::new();
+        let mut settled_weight: Option<f64> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let raw = match hx711.read() {
+                Ok(raw) => raw,
+                Err(e) => {
+                    log::warn!("Could not read HX711: {}", e);
+                    continue;
+                }
+            };
+            log::debug!("HX711 raw reading: {}", raw);
+            let now = Utc::now();
+            readings.push_back((now, (raw - config.offset) as f64 / config.scale));
+
+            let settle = Duration::seconds(config.settle_seconds);
+            while readings.front().is_some_and(|(t, _)| now.signed_duration_since(*t) > settle) {
+                readings.pop_front();
+            }
+            let Some(weight) = stable_weight(&readings, settle, config.tolerance_grams) else {
+                continue;
+            };
+            if settled_weight.is_some_and(|w| (w - weight).abs() <= config.tolerance_grams) {
+                continue;
+            }
+
+            log::info!("Litter box weight settled at {:.0}g", weight);
+            let mut events = vec![ReminderEvent::WeightMeasured(now, weight)];
+            if settled_weight.is_some_and(|w| (config.min_drop_grams..=config.max_drop_grams).contains(&(w - weight))) {
+                events.push(ReminderEvent::CleaningDetected("Load cell".to_string()));
+            }
+            settled_weight = Some(weight);
+            if events.into_iter().any(|event| reminder_tx.send(event).is_err()) {
+                break;
+            }
+        }
+    });
+}
+
+/// The mean of the readings, if they cover the whole settling time and all lie within the tolerance.
+fn stable_weight(readings: &VecDeque<(DateTime<Utc>, f64)>, settle: Duration, tolerance: f64) -> Option<f64> {
+    let (first, _) = readings.front()?;
+    let (last, _) = readings.back()?;
+    if last.signed_duration_since(*first) < settle - Duration::seconds(1) {
+        return None;
+    }
+    let min = readings.iter().map(|(_, w)| *w).fold(f64::INFINITY, f64::min);
+    let max = readings.iter().map(|(_, w)| *w).fold(f64::NEG_INFINITY, f64::max);
+    if max - min > tolerance {
+        return None;
+    }
+    Some(readings.iter().map(|(_, w)| *w).sum::<f64>() / readings.len() as f64)
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::Sender;
+
+use serde::{Deserialize, Serialize};
+
+use crate::reminder::ReminderEvent;
+
+pub mod ammonia;
+pub mod dht22;
+pub mod door;
+pub mod light;
+pub mod load_cell;
+pub mod nfc;
+pub mod pir;
+
+/// Sensors around the litter box, each enabled by its own section.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct SensorsConfig {
+    pub ammonia: Option<ammonia::AmmoniaConfig>,
+    pub dht22: Option<dht22::Dht22Config>,
+    pub door: Option<door::DoorConfig>,
+    pub light: Option<light::LightConfig>,
+    pub load_cell: Option<load_cell::LoadCellConfig>,
+    pub nfc: Option<nfc::NfcConfig>,
+    pub pir: Option<pir::PirConfig>
+}
+
+/// Temperature and humidity near the litter box, which strongly affect how fast it gets unpleasant.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Climate {
+    /// Degrees Celsius
+    pub temperature: f64,
+    /// Relative humidity in percent
+    pub humidity: f64
+}
+
+/// Runs a thread for each configured sensor, which reports what it detects to the reminder.
+pub fn run(config: &SensorsConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    if let Some(ammonia_config) = &config.ammonia {
+        ammonia::run(ammonia_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(dht22_config) = &config.dht22 {
+        dht22::run(dht22_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(door_config) = &config.door {
+        door::run(door_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(light_config) = &config.light {
+        light::run(light_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(load_cell_config) = &config.load_cell {
+        load_cell::run(load_cell_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(nfc_config) = &config.nfc {
+        nfc::run(nfc_config.clone(), reminder_tx.clone(), shutdown_flag.clone());
+    }
+    if let Some(pir_config) = &config.pir {
+        pir::run(pir_config.clone(), reminder_tx, shutdown_flag);
+    }
+}
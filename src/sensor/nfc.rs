@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use linux_embedded_hal::spidev::{SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer};
+use serde::Deserialize;
+
+use crate::reminder::{ReminderEvent, ResetSource};
+use crate::users;
+
+const POLL_DELAY: Duration = Duration::from_millis(250);
+/// Time during which the same tag is not taken into account again, as the reader keeps seeing it while it is held
+const TAG_COOLDOWN: Duration = Duration::from_secs(10);
+const TRANSCEIVE_TIMEOUT: Duration = Duration::from_millis(50);
+
+// MFRC522 registers and commands
+const COMMAND_REG: u8 = 0x01;
+const COM_IRQ_REG: u8 = 0x04;
+const ERROR_REG: u8 = 0x06;
+const FIFO_DATA_REG: u8 = 0x09;
+const FIFO_LEVEL_REG: u8 = 0x0A;
+const BIT_FRAMING_REG: u8 = 0x0D;
+const COLL_REG: u8 = 0x0E;
+const MODE_REG: u8 = 0x11;
+const TX_CONTROL_REG: u8 = 0x14;
+const TX_ASK_REG: u8 = 0x15;
+const T_MODE_REG: u8 = 0x2A;
+const T_PRESCALER_REG: u8 = 0x2B;
+const T_RELOAD_REG_H: u8 = 0x2C;
+const T_RELOAD_REG_L: u8 = 0x2D;
+const VERSION_REG: u8 = 0x37;
+
+const IDLE: u8 = 0x00;
+const TRANSCEIVE: u8 = 0x0C;
+const SOFT_RESET: u8 = 0x0F;
+
+const REQUEST_A: u8 = 0x26;
+const ANTICOLLISION_CL1: [u8; 2] = [0x93, 0x20];
+
+/// An RC522 NFC reader on which household members tap their tag when they clean the litter box.
+#[derive(Deserialize, Clone)]
+pub struct NfcConfig {
+    #[serde(default = "default_spi_device")]
+    pub spi_device: String,
+    /// Names of the household members by tag UID, in hexadecimal, e.g. `"04a1b2c3" = "Paul"`, besides the tags of
+    /// the users
+    #[serde(default)]
+    pub tags: HashMap<String, String>
+}
+
+fn default_spi_device() -> String {
+    "/dev/spidev0.0".to_string()
+}
+
+struct Mfrc522 {
+    spi: Spidev
+}
+
+impl Mfrc522 {
+    fn new(spi_device: &str) -> std::io::Result<Self> {
+        let mut spi = Spidev::open(spi_device)?;
+        spi.configure(&SpidevOptions::new().bits_per_word(8).max_speed_hz(1_000_000).mode(SpiModeFlags::SPI_MODE_0).build())?;
+        let reader = Mfrc522 { spi };
+        reader.write(COMMAND_REG, SOFT_RESET)?;
+        sleep(Duration::from_millis(50));
+        // timer of about 25ms, used to time out when no tag answers
+        reader.write(T_MODE_REG, 0x80)?;
+        reader.write(T_PRESCALER_REG, 0xA9)?;
+        reader.write(T_RELOAD_REG_H, 0x03)?;
+        reader.write(T_RELOAD_REG_L, 0xE8)?;
+        reader.write(TX_ASK_REG, 0x40)?;
+        reader.write(MODE_REG, 0x3D)?;
+        // antenna on
+        let tx_control = reader.read(TX_CONTROL_REG)?;
+        reader.write(TX_CONTROL_REG, tx_control | 0x03)?;
+        Ok(reader)
+    }
+
+    fn read(&self, register: u8) -> std::io::Result<u8> {
+        let tx = [0x80 | ((register << 1) & 0x7E), 0];
+        let mut rx = [0u8; 2];
+        self.spi.transfer(&mut SpidevTransfer::read_write(&tx, &mut rx))?;
+        Ok(rx[1])
+    }
+
+    fn write(&self, register: u8, value: u8) -> std::io::Result<()> {
+        let tx = [(register << 1) & 0x7E, value];
+        let mut rx = [0u8; 2];
+        self.spi.transfer(&mut SpidevTransfer::read_write(&tx, &mut rx))
+    }
+
+    /// Sends the data to the tag and returns its answer, or `None` if no tag answered.
+    fn transceive(&self, data: &[u8], last_bits: u8) -> std::io::Result<Option<Vec<u8>>> {
+        self.write(COMMAND_REG, IDLE)?;
+        self.write(COM_IRQ_REG, 0x7F)?;
+        self.write(FIFO_LEVEL_REG, 0x80)?;
+        for byte in data {
+            self.write(FIFO_DATA_REG, *byte)?;
+        }
+        self.write(COMMAND_REG, TRANSCEIVE)?;
+        // start sending
+        self.write(BIT_FRAMING_REG, 0x80 | last_bits)?;
+
+        let started = Instant::now();
+        let answered = loop {
+            let irq = self.read(COM_IRQ_REG)?;
+            // RxIRq or IdleIRq
+            if irq & 0x30 != 0 {
+                break true;
+            }
+            // TimerIRq
+            if irq & 0x01 != 0 || started.elapsed() > TRANSCEIVE_TIMEOUT {
+                break false;
+            }
+        };
+        self.write(BIT_FRAMING_REG, 0)?;
+        // BufferOvfl, ParityErr or ProtocolErr
+        if !answered || self.read(ERROR_REG)? & 0x13 != 0 {
+            return Ok(None);
+        }
+        let length = self.read(FIFO_LEVEL_REG)?;
+        (0..length).map(|_| self.read(FIFO_DATA_REG)).collect::<std::io::Result<Vec<u8>>>().map(Some)
+    }
+
+    /// Returns the UID of the tag in front of the reader, if any. Only 4-byte UIDs are supported.
+    fn read_uid(&self) -> std::io::Result<Option<String>> {
+        let coll = self.read(COLL_REG)?;
+        self.write(COLL_REG, coll & 0x7F)?;
+        if self.transceive(&[REQUEST_A], 7)?.is_none() {
+            return Ok(None);
+        }
+        let uid = match self.transceive(&ANTICOLLISION_CL1, 0)? {
+            // the last byte is a checksum of the UID
+            Some(answer) if answer.len() == 5 && answer[..4].iter().fold(0, |a, b| a ^ b) == answer[4] => answer,
+            _ => return Ok(None)
+        };
+        Ok(Some(uid[..4].iter().map(|b| format!("{:02x}", b)).collect()))
+    }
+}
+
+/// Initializes the reader and returns its chip version, for the self-test. A reader that is not connected reads
+/// as all zeros or all ones.
+pub(crate) fn probe(config: &NfcConfig) -> std::io::Result<u8> {
+    let version = Mfrc522::new(&config.spi_device)?.read(VERSION_REG)?;
+    if version == 0x00 || version == 0xFF {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no reader answering"));
+    }
+    Ok(version)
+}
+
+/// Runs the NFC reader thread, which requests a reset on behalf of the household member whose tag is tapped.
+///
+/// # Panics
+///
+/// Panics if the reader cannot be initialized.
+pub fn run(config: NfcConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let reader = Mfrc522::new(&config.spi_device).expect("Could not initialize NFC reader");
+    let tags: HashMap<String, String> = config.tags.into_iter().map(|(uid, name)| (uid.to_lowercase(), name)).collect();
+    std::thread::spawn(move || {
+        let mut last_tag: Option<(String, Instant)> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let uid = match reader.read_uid() {
+                Ok(Some(uid)) => uid,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::warn!("Could not read NFC reader: {}", e);
+                    continue;
+                }
+            };
+            if last_tag.as_ref().is_some_and(|(last, at)| *last == uid && at.elapsed() < TAG_COOLDOWN) {
+                continue;
+            }
+            last_tag = Some((uid.clone(), Instant::now()));
+
+            match tags.get(&uid).map(String::as_str).or_else(|| users::by_tag(&uid)) {
+                Some(name) => {
+                    if reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Tag, Some(name.to_string()))).is_err() {
+                        break;
+                    }
+                }
+                None => log::warn!("Unknown NFC tag {}, add it to the configuration to use it", uid)
+            }
+        }
+    });
+}
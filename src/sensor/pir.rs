@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::gpio::{Chip, Options};
+use crate::reminder::ReminderEvent;
+
+const POLL_DELAY: StdDuration = StdDuration::from_millis(200);
+
+/// A PIR motion sensor pointed at the litter box.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct PirConfig {
+    pub pin: u32,
+    /// Time without motion after which the next motion counts as a new visit
+    pub cooldown_seconds: i64,
+    /// Number of visits since the last cleaning after which the urgency is raised by one level, if set
+    pub visits_per_escalation: Option<usize>
+}
+
+impl Default for PirConfig {
+    fn default() -> Self {
+        PirConfig { pin: 6, cooldown_seconds: 60, visits_per_escalation: None }
+    }
+}
+
+/// Runs the PIR sensor thread, which reports cat visits to the reminder. A cat moving around in the box triggers
+/// the sensor many times, which is why motion only counts as a new visit after a period without any.
+///
+/// # Panics
+///
+/// Panics if the GPIO line of the sensor cannot be requested.
+pub fn run(config: PirConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let chip = Chip::new("gpiochip0").expect("Cannot open GPIO");
+    let input = chip.request_lines(Options::input([config.pin]).consumer("cat-reminder-pir")).expect("Could not request PIR sensor line");
+    std::thread::spawn(move || {
+        let mut last_motion: Option<DateTime<Utc>> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            match input.get_values([false; 1]) {
+                Ok([true]) => {
+                    let now = Utc::now();
+                    if last_motion.is_none_or(|t| now.signed_duration_since(t) > Duration::seconds(config.cooldown_seconds)) {
+                        log::info!("Cat visit detected");
+                        if reminder_tx.send(ReminderEvent::CatVisited(now)).is_err() {
+                            break;
+                        }
+                    }
+                    last_motion = Some(now);
+                }
+                Ok(_) => (),
+                Err(e) => log::error!("Could not read PIR sensor: {:?}", e)
+            }
+            sleep(POLL_DELAY);
+        }
+    });
+}
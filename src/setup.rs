@@ -0,0 +1,182 @@
+//! The first-boot setup wizard: a web page asking for the settings that differ between households, which writes the
+//! configuration file and restarts the reminder with it. Every page is answered with the form, so that phones
+//! connected to the node's access point open it as a captive portal.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+
+use chrono_tz::Tz;
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::config::{ChoresConfig, DisplaysConfig, ScheduleConfig, CONFIG_FILE_PATH};
+use crate::state;
+
+pub const SETUP_PORT: u16 = 80;
+
+/// The part of the configuration written by the wizard.
+#[derive(Serialize)]
+struct SetupConfig {
+    schedule: ScheduleConfig,
+    displays: DisplaysConfig,
+    chores: ChoresConfig
+}
+
+/// Whether the node was never set up, i.e. there is neither a configuration file nor a state. Nodes that ran on the
+/// default configuration before the wizard existed have a state, and are left alone.
+pub fn is_first_boot() -> bool {
+    [CONFIG_FILE_PATH, state::STATE_FILE_PATH, state::LEGACY_STATE_FILE_PATH].iter().all(|path| !Path::new(path).exists())
+}
+
+/// Serves the wizard until the configuration is written, then restarts the process to run with it.
+///
+/// # Panics
+///
+/// Panics if the port cannot be listened at.
+pub fn run() -> ! {
+    let server = Server::http(("0.0.0.0", SETUP_PORT)).unwrap_or_else(|e| panic!("Can not listen at port {}: {}", SETUP_PORT, e));
+    log::info!("No configuration found, serving the setup wizard at port {}", SETUP_PORT);
+
+    for request in server.incoming_requests() {
+        if handle(request) {
+            break;
+        }
+    }
+    log::info!("Configuration written to {}, restarting", CONFIG_FILE_PATH);
+    restart()
+}
+
+/// Answers a request, returning whether the configuration was written.
+fn handle(mut request: Request) -> bool {
+    if *request.method() != Method::Post {
+        respond(request, page(&form_defaults(), None));
+        return false;
+    }
+    let mut body = String::new();
+    if let Err(e) = request.as_reader().read_to_string(&mut body) {
+        log::warn!("Could not read setup form: {}", e);
+        return false;
+    }
+    let fields = parse_form(&body);
+    let result = parse_config(&fields)
+        .and_then(|config| toml::to_string(&config).map_err(|e| e.to_string()))
+        .and_then(|toml| fs::write(CONFIG_FILE_PATH, toml).map_err(|e| format!("Could not write {}: {}", CONFIG_FILE_PATH, e)));
+    match result {
+        Ok(()) => {
+            respond(request, "<!DOCTYPE html><html><body><h1>Cat Litter Reminder</h1><p>All set, the reminder is starting.</p></body></html>".to_string());
+            true
+        }
+        Err(e) => {
+            respond(request, page(&fields, Some(&e)));
+            false
+        }
+    }
+}
+
+pub(crate) fn respond(request: Request, html: String) {
+    let header = Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap();
+    if let Err(e) = request.respond(Response::from_string(html).with_header(header)) {
+        log::warn!("Could not answer setup request: {}", e);
+    }
+}
+
+fn parse_config(fields: &HashMap<String, String>) -> Result<SetupConfig, String> {
+    let field = |name: &str| fields.get(name).map(|value| value.trim()).unwrap_or("");
+    let timezone: Tz = field("timezone").parse().map_err(|_| format!("Unknown time zone {}", field("timezone")))?;
+    let mut thresholds = [0; 4];
+    for (i, name) in ["dark_green", "orange", "red", "blinking_red"].iter().enumerate() {
+        thresholds[i] = field(name).parse().map_err(|_| format!("Invalid number of seconds for {}", name.replace('_', " ")))?;
+    }
+    if thresholds[0] <= 0 || thresholds.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err("The thresholds need to be positive and increasing".to_string());
+    }
+    let cats = field("cats").parse().ok().filter(|cats| (1..=20).contains(cats))
+        .ok_or_else(|| "The number of cats needs to be between 1 and 20".to_string())?;
+    let led_count = field("led_count").parse().ok().filter(|count| (1..=1000).contains(count))
+        .ok_or_else(|| "The number of LEDs needs to be between 1 and 1000".to_string())?;
+    let members = field("members").split(',').map(|name| name.trim().to_string()).filter(|name| !name.is_empty()).collect();
+
+    Ok(SetupConfig {
+        schedule: ScheduleConfig { timezone, thresholds, cats, ..ScheduleConfig::default() },
+        displays: DisplaysConfig { led_count, ..DisplaysConfig::default() },
+        chores: ChoresConfig { members }
+    })
+}
+
+fn form_defaults() -> HashMap<String, String> {
+    let schedule = ScheduleConfig::default();
+    let names = ["dark_green", "orange", "red", "blinking_red"];
+    let mut fields: HashMap<String, String> = names.iter().zip(schedule.thresholds).map(|(name, seconds)| (name.to_string(), seconds.to_string())).collect();
+    fields.insert("timezone".to_string(), schedule.timezone.name().to_string());
+    fields.insert("cats".to_string(), schedule.cats.to_string());
+    fields.insert("led_count".to_string(), DisplaysConfig::default().led_count.to_string());
+    fields
+}
+
+fn page(fields: &HashMap<String, String>, error: Option<&str>) -> String {
+    let value = |name: &str| escape(fields.get(name).map(String::as_str).unwrap_or(""));
+    let input = |name: &str, label: &str, kind: &str| {
+        format!("<p><label>{}<br><input name=\"{}\" type=\"{}\" value=\"{}\" required></label></p>", label, name, kind, value(name))
+    };
+    let error = error.map(|e| format!("<p style=\"color: red\">{}</p>", escape(e))).unwrap_or_default();
+    format!("<!DOCTYPE html><html><head><meta name=\"viewport\" content=\"width=device-width\"><title>Cat Litter Reminder setup</title></head>\
+        <body><h1>Cat Litter Reminder</h1>{}<form method=\"post\" action=\"/\">{}{}{}<h2>Seconds after a cleaning until the strip turns, for one cat</h2>{}{}{}{}\
+        <p><label>Household members taking turns, separated by commas<br><input name=\"members\" value=\"{}\"></label></p>\
+        <p><button type=\"submit\">Save</button></p></form></body></html>",
+        error,
+        input("timezone", "Time zone, e.g. Europe/Vienna", "text"),
+        input("led_count", "Number of LEDs of the strip", "number"),
+        input("cats", "Number of cats using the litter box", "number"),
+        input("dark_green", "dark green", "number"),
+        input("orange", "orange", "number"),
+        input("red", "red", "number"),
+        input("blinking_red", "blinking red", "number"),
+        value("members"))
+}
+
+/// Decodes an `application/x-www-form-urlencoded` body.
+pub(crate) fn parse_form(body: &str) -> HashMap<String, String> {
+    body.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (decode(name), decode(value)))
+        .collect()
+}
+
+fn decode(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok()).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%')
+            },
+            byte => decoded.push(byte)
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Replaces the process with a new one started with the same arguments, which loads the new configuration.
+fn restart() -> ! {
+    let error = match std::env::current_exe() {
+        Ok(exe) => Command::new(exe).args(std::env::args_os().skip(1)).exec(),
+        Err(e) => e
+    };
+    log::error!("Could not restart, exiting instead: {}", error);
+    std::process::exit(1)
+}
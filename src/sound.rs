@@ -0,0 +1,82 @@
+use std::process::Command;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::config::schedule;
+use crate::notifier::QuietHours;
+use crate::reminder::{LEDStripState, ReminderStatus};
+
+const POLL_DELAY: StdDuration = StdDuration::from_millis(200);
+
+/// Sound clips played on the Pi's audio output. All clips are optional.
+#[derive(Deserialize, Clone)]
+pub struct SoundConfig {
+    /// Command playing a sound file given as last argument
+    #[serde(default = "default_player")]
+    pub player: String,
+    /// Played when the state is reset, to confirm the button press
+    pub reset: Option<String>,
+    /// Played when the litter box becomes due, i.e. the strip turns red
+    pub due: Option<String>,
+    /// Played when the strip starts blinking red, and repeated as long as it does
+    pub overdue: Option<String>,
+    #[serde(default = "default_overdue_repeat_minutes")]
+    pub overdue_repeat_minutes: i64
+}
+
+fn default_player() -> String {
+    "paplay".to_string()
+}
+
+fn default_overdue_repeat_minutes() -> i64 {
+    30
+}
+
+/// Runs the sound effects, playing clips on resets and escalations. Escalation sounds are not played
+/// during the quiet hours, nor while the reminder is snoozed or nobody is home.
+pub fn run(config: SoundConfig, quiet_hours: Option<QuietHours>, status: Arc<RwLock<ReminderStatus>>, shutdown_flag: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let mut last_status: ReminderStatus = status.read().unwrap().clone();
+        let mut last_overdue_alarm: Option<DateTime<Utc>> = None;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let now = Utc::now();
+            let current = status.read().unwrap().clone();
+            let is_quiet = current.quiet || quiet_hours.as_ref().is_some_and(|q| q.contains(now.with_timezone(&schedule().timezone).time()));
+
+            if current.last_cleaning_time > last_status.last_cleaning_time {
+                play(&config, &config.reset);
+            } else if current.state == LEDStripState::Red && last_status.state < LEDStripState::Red && !is_quiet {
+                play(&config, &config.due);
+            }
+
+            if current.state == LEDStripState::BlinkingRed {
+                let is_due = last_overdue_alarm.is_none_or(|t| now.signed_duration_since(t) >= Duration::minutes(config.overdue_repeat_minutes));
+                if is_due && !is_quiet {
+                    last_overdue_alarm = Some(now);
+                    play(&config, &config.overdue);
+                }
+            } else {
+                last_overdue_alarm = None;
+            }
+            last_status = current;
+        }
+    });
+}
+
+/// Plays the clip, if there is one, returning whether it was played successfully.
+pub(crate) fn play(config: &SoundConfig, clip: &Option<String>) -> bool {
+    let Some(file) = clip else { return false };
+    match Command::new(&config.player).arg(file).status() {
+        Ok(status) if !status.success() => log::warn!("{} exited with {} playing {}", config.player, status, file),
+        Err(e) => log::error!("Could not play {}: {}", file, e),
+        _ => return true
+    }
+    false
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Error, Read, Write};
+use std::io::ErrorKind::InvalidData;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::history::{self, HistoryEntry};
+use crate::litter::LitterProfile;
+use crate::reminder::ResetSource;
+use crate::supplies::BagState;
+
+pub const STATE_FILE_PATH: &str = "/var/lib/cat-reminder/state";
+/// Where the state used to be kept, relative to the working directory
+pub(crate) const LEGACY_STATE_FILE_PATH: &str = "cat_reminder_state";
+const STATE_VERSION: u32 = 1;
+/// Name of the litter box, as long as a node handles a single one
+pub const DEFAULT_BOX: &str = "default";
+
+/// The persisted state of the node, as a versioned JSON document.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct State {
+    pub version: u32,
+    /// Random identifier of the node, generated on the first start
+    pub node_id: String,
+    pub boxes: BTreeMap<String, BoxState>,
+    /// Whether the reminder is paused until further notice
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+    /// Time at which the reminder was last snoozed, paused or resumed, the latest one winning between the nodes
+    #[serde(default)]
+    pub snoozed_at: Option<DateTime<Utc>>,
+    /// Time until which the guest mode is enabled
+    #[serde(default)]
+    pub guest_until: Option<DateTime<Utc>>,
+    /// Position in the chore rotation, computed from the history when unknown
+    #[serde(default)]
+    pub rotation_index: Option<usize>,
+    /// The bag of litter in use, if it is known when it was opened
+    #[serde(default)]
+    pub bag: Option<BagState>,
+    /// Time at which each care reminder was last done, by name
+    #[serde(default)]
+    pub care: BTreeMap<String, DateTime<Utc>>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BoxState {
+    pub last_cleaning_time: DateTime<Utc>,
+    #[serde(default)]
+    pub last_cleaned_by: Option<String>,
+    #[serde(default)]
+    pub last_reset_source: Option<ResetSource>,
+    /// Litter selected at runtime, overriding the configured one
+    #[serde(default)]
+    pub litter: Option<LitterProfile>,
+    /// Time at which the litter was last replaced entirely, if known
+    #[serde(default)]
+    pub last_full_change: Option<DateTime<Utc>>
+}
+
+impl State {
+    pub(crate) fn new(last_cleaning_time: DateTime<Utc>) -> Self {
+        State {
+            version: STATE_VERSION,
+            node_id: generate_node_id(),
+            boxes: BTreeMap::from([(DEFAULT_BOX.to_string(), BoxState { last_cleaning_time, last_cleaned_by: None, last_reset_source: None,
+                litter: None, last_full_change: None })]),
+            paused: false,
+            snoozed_until: None,
+            snoozed_at: None,
+            guest_until: None,
+            rotation_index: None,
+            bag: None,
+            care: BTreeMap::new()
+        }
+    }
+
+    pub fn last_cleaning_time(&self) -> DateTime<Utc> {
+        self.boxes.get(DEFAULT_BOX).map(|b| b.last_cleaning_time).unwrap_or_else(Utc::now)
+    }
+}
+
+/// Loads the state from a file, falling back to the file in the working directory used by earlier versions.
+/// State files holding a bare RFC 3339 timestamp, as written by earlier versions, are migrated.
+/// A new state is created if there is none, and one with the last cleaning of the history if the file cannot be read,
+/// the unreadable file being kept aside with the `.corrupt` extension.
+pub fn load(path: &Path) -> State {
+    let path = if !path.exists() && Path::new(LEGACY_STATE_FILE_PATH).exists() { Path::new(LEGACY_STATE_FILE_PATH) } else { path };
+    if !path.exists() {
+        let state = State::new(Utc::now());
+        if let Err(e) = save(path, &state) {
+            log::error!("Could not write state to {}: {}", path.display(), e);
+        }
+        return state;
+    }
+    fs::read_to_string(path).and_then(|str| parse(&str)).unwrap_or_else(|err| {
+        log::error!("Error reading state: {:?}", err);
+        let mut corrupt_path = path.as_os_str().to_owned();
+        corrupt_path.push(".corrupt");
+        match fs::rename(path, &corrupt_path) {
+            Ok(()) => log::error!("Kept the unreadable state as {}", Path::new(&corrupt_path).display()),
+            Err(e) => log::error!("Could not keep the unreadable state aside: {}", e)
+        }
+        let state = recovered();
+        if let Err(e) = save(path, &state) {
+            log::error!("Could not write state to {}: {}", path.display(), e);
+        }
+        state
+    })
+}
+
+/// A new state with the last cleaning recorded in the history, if there is one.
+fn recovered() -> State {
+    let last_cleaning = history::cleanings().unwrap_or_else(|e| {
+        log::error!("Could not read the history to recover the last cleaning: {}", e);
+        Vec::new()
+    }).pop();
+    let Some(HistoryEntry { time, by, source, .. }) = last_cleaning else {
+        return State::new(Utc::now());
+    };
+    log::warn!("Recovered the last cleaning at {} from the history", time);
+    let mut state = State::new(time);
+    if let Some(box_state) = state.boxes.get_mut(DEFAULT_BOX) {
+        box_state.last_cleaned_by = by;
+        box_state.last_reset_source = source;
+    }
+    state
+}
+
+fn parse(str: &str) -> std::io::Result<State> {
+    if !str.trim_start().starts_with('{') {
+        log::info!("Migrating state file from the plain timestamp format");
+        let time = DateTime::parse_from_rfc3339(str.trim()).map_err(|e| Error::new(InvalidData, e))?;
+        return Ok(State::new(time.with_timezone(&Utc)));
+    }
+    let state: State = serde_json::from_str(str).map_err(|e| Error::new(InvalidData, e))?;
+    if state.version > STATE_VERSION {
+        return Err(Error::new(InvalidData, format!("Unsupported state version {}", state.version)));
+    }
+    Ok(state)
+}
+
+/// Saves the state atomically.
+pub fn save(path: &Path, state: &State) -> std::io::Result<()> {
+    let contents = serde_json::to_vec_pretty(state).map_err(|e| Error::new(InvalidData, e))?;
+    write_atomically(path, &contents)
+}
+
+/// Writes a file through a temporary file that is renamed once it is synced to disk,
+/// so that a power loss never leaves a truncated file behind.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let directory = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::create_dir_all(directory)?;
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    let mut file = File::create(&temporary_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary_path, path)?;
+    // the rename itself is only durable once the directory is synced
+    File::open(directory)?.sync_all()
+}
+
+fn generate_node_id() -> String {
+    let mut bytes = [0u8; 8];
+    File::open("/dev/urandom").and_then(|mut f| f.read_exact(&mut bytes)).expect("Could not generate node identifier");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
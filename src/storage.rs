@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::history::{self, HistoryEntry};
+use crate::state::{self, State};
+
+const MAX_PENDING_ENTRIES: usize = 10_000;
+
+/// Batching of the writes to the SD card, which wears out from frequent small writes.
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Seconds between writes of the state and the history, written on every change if zero
+    pub flush_interval_seconds: u64,
+    /// Copy of the state written on every change, meant to be on a tmpfs such as `/run`
+    pub hot_state_file: Option<PathBuf>
+}
+
+pub enum StorageEvent {
+    StateChanged(State),
+    Recorded(HistoryEntry),
+    /// Writes everything pending right away, for changes that would not outlive the next flush otherwise
+    Flush
+}
+
+/// Runs the writer, which keeps the state and the history entries in memory until the next flush.
+/// Failed writes, e.g. on a read-only root filesystem, are logged and retried with the next flush.
+/// Everything pending is flushed once all senders are gone.
+pub fn run(config: StorageConfig, state_file: PathBuf, rx: Receiver<StorageEvent>) -> JoinHandle<()> {
+    std::thread::Builder::new().name("storage".to_string()).spawn(move || {
+        let interval = Duration::from_secs(config.flush_interval_seconds);
+        let mut pending_state: Option<State> = None;
+        let mut pending_entries: Vec<HistoryEntry> = Vec::new();
+        let mut last_flush = Instant::now();
+        let mut flush_requested = false;
+
+        loop {
+            let disconnected = match rx.recv_timeout(interval.max(Duration::from_millis(100))) {
+                Ok(StorageEvent::StateChanged(state)) => {
+                    if let Some(hot_state_file) = &config.hot_state_file {
+                        if let Err(e) = state::save(hot_state_file, &state) {
+                            log::warn!("Could not write state to {}: {}", hot_state_file.display(), e);
+                        }
+                    }
+                    pending_state = Some(state);
+                    false
+                }
+                Ok(StorageEvent::Recorded(entry)) => {
+                    pending_entries.push(entry);
+                    if pending_entries.len() > MAX_PENDING_ENTRIES {
+                        pending_entries.remove(0);
+                    }
+                    false
+                }
+                Ok(StorageEvent::Flush) => {
+                    flush_requested = true;
+                    false
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => true
+            };
+
+            if disconnected || flush_requested || last_flush.elapsed() >= interval {
+                last_flush = Instant::now();
+                flush_requested = false;
+                if let Some(state) = &pending_state {
+                    match state::save(&state_file, state) {
+                        Ok(()) => pending_state = None,
+                        Err(e) => log::warn!("Could not write state to {}, retrying later: {}", state_file.display(), e)
+                    }
+                }
+                if !pending_entries.is_empty() {
+                    match history::append_all(&pending_entries) {
+                        Ok(()) => pending_entries.clear(),
+                        Err(e) => log::warn!("Could not write to history, keeping {} entries for later: {}", pending_entries.len(), e)
+                    }
+                }
+            }
+            if disconnected {
+                break;
+            }
+        }
+    }).expect("Could not start storage thread")
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::reminder::ReminderEvent;
+
+const POLL_DELAY: Duration = Duration::from_millis(1000);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// Number of failures in a row after which a subsystem is reported as failing
+const FAILING_AFTER: u32 = 3;
+/// Time after which a restarted subsystem is considered healthy again
+const STABLE_AFTER: Duration = Duration::from_secs(300);
+
+struct Subsystem {
+    name: &'static str,
+    start: Box<dyn FnMut() -> JoinHandle<()> + Send>,
+    handle: Option<JoinHandle<()>>,
+    started_at: Instant,
+    failures: u32,
+    restart_at: Option<Instant>
+}
+
+/// Watches the threads of subsystems that the rest of the program depends on, restarting them with an exponential
+/// backoff when they stop. Subsystems that keep failing are reported to the reminder, so that the strip shows it.
+pub struct Supervisor {
+    subsystems: Vec<Subsystem>,
+    shutdown_flag: Arc<AtomicBool>
+}
+
+impl Supervisor {
+    pub fn new(shutdown_flag: Arc<AtomicBool>) -> Self {
+        Supervisor { subsystems: Vec::new(), shutdown_flag }
+    }
+
+    pub fn shutdown_flag(&self) -> Arc<AtomicBool> {
+        self.shutdown_flag.clone()
+    }
+
+    /// Starts the subsystem, whose thread is expected to run until shutdown.
+    pub fn supervise(&mut self, name: &'static str, mut start: impl FnMut() -> JoinHandle<()> + Send + 'static) {
+        let handle = start();
+        self.subsystems.push(Subsystem { name, start: Box::new(start), handle: Some(handle), started_at: Instant::now(), failures: 0, restart_at: None });
+    }
+
+    /// Runs the supervisor until shutdown.
+    pub fn run(mut self, reminder_tx: Sender<ReminderEvent>) {
+        std::thread::Builder::new().name("supervisor".to_string()).spawn(move || {
+            while !self.shutdown_flag.load(Ordering::Relaxed) {
+                sleep(POLL_DELAY);
+                for subsystem in self.subsystems.iter_mut() {
+                    if let Some(event) = subsystem.check(&self.shutdown_flag) {
+                        if reminder_tx.send(event).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }).expect("Could not start supervisor thread");
+    }
+}
+
+impl Subsystem {
+    /// Restarts the subsystem if it stopped and its backoff elapsed, returning a change in its health.
+    fn check(&mut self, shutdown_flag: &AtomicBool) -> Option<ReminderEvent> {
+        if self.handle.as_ref().is_some_and(|h| h.is_finished()) {
+            let result = self.handle.take().map(|h| h.join());
+            if shutdown_flag.load(Ordering::Relaxed) {
+                return None;
+            }
+            self.failures += 1;
+            let backoff = INITIAL_BACKOFF.saturating_mul(2u32.saturating_pow(self.failures - 1)).min(MAX_BACKOFF);
+            match result {
+                Some(Err(_)) => log::error!("The {} subsystem panicked, restarting it in {:?}", self.name, backoff),
+                _ => log::error!("The {} subsystem stopped, restarting it in {:?}", self.name, backoff)
+            }
+            self.restart_at = Some(Instant::now() + backoff);
+            if self.failures == FAILING_AFTER {
+                return Some(ReminderEvent::SubsystemFailing(self.name.to_string(), true));
+            }
+        } else if self.restart_at.is_some_and(|t| Instant::now() >= t) {
+            log::info!("Restarting the {} subsystem", self.name);
+            self.restart_at = None;
+            self.handle = Some((self.start)());
+            self.started_at = Instant::now();
+        } else if self.handle.is_some() && self.failures > 0 && self.started_at.elapsed() >= STABLE_AFTER {
+            log::info!("The {} subsystem recovered", self.name);
+            let was_failing = self.failures >= FAILING_AFTER;
+            self.failures = 0;
+            if was_failing {
+                return Some(ReminderEvent::SubsystemFailing(self.name.to_string(), false));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,37 @@
+//! Litter supplies: the full changes done from the current bag of litter are counted, so that the household is warned
+//! before the bag runs out rather than when it is needed.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Supply tracking is enabled by adding the section.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct SuppliesConfig {
+    /// Number of full changes a bag of litter is good for
+    pub full_changes_per_bag: u32,
+    /// Days before the bag is predicted to run out from which the household is warned
+    pub warn_days: i64
+}
+
+impl Default for SuppliesConfig {
+    fn default() -> Self {
+        SuppliesConfig { full_changes_per_bag: 4, warn_days: 7 }
+    }
+}
+
+/// The bag of litter in use, as persisted in the state.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BagState {
+    pub opened: DateTime<Utc>,
+    /// Full changes done with litter from the bag
+    pub full_changes: u32
+}
+
+/// The time at which the bag runs out, i.e. the first full change for which there is no litter left. Full changes are
+/// expected at the given interval from the last one, or from the opening of the bag if there was none since.
+pub fn runs_out(config: &SuppliesConfig, bag: &BagState, last_full_change: Option<DateTime<Utc>>, interval: Duration) -> DateTime<Utc> {
+    let reference = last_full_change.filter(|time| *time >= bag.opened).unwrap_or(bag.opened);
+    let remaining = config.full_changes_per_bag.saturating_sub(bag.full_changes);
+    reference + interval * (remaining as i32 + 1)
+}
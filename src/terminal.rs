@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use crate::clock::Clock;
+use crate::config::schedule;
+use crate::display::{Display, DisplayState};
+use crate::notifier::format_duration;
+
+/// Prints the state to the standard output whenever it changes, useful when running without any hardware attached.
+#[derive(Default)]
+pub struct TerminalDisplay {
+    line: String,
+    /// Clock of a replay, whose time starts every line. Lines are then only printed when more than the time changed.
+    clock: Option<Arc<dyn Clock>>
+}
+
+impl TerminalDisplay {
+    /// A terminal display for a replay with the given clock.
+    pub fn replaying(clock: Arc<dyn Clock>) -> Self {
+        TerminalDisplay { line: String::new(), clock: Some(clock) }
+    }
+}
+
+impl Display for TerminalDisplay {
+    fn name(&self) -> &str {
+        "terminal"
+    }
+
+    fn render(&mut self, state: &DisplayState) {
+        let now = self.clock.as_ref().map_or_else(Utc::now, |clock| clock.now());
+        let elapsed = now.signed_duration_since(state.status.last_cleaning_time);
+        let mut line = format!("{:?}, last cleaned {} ago", state.status.state, format_duration(&elapsed));
+        // the time since the last cleaning changes on every step of a replay
+        let mut details = String::new();
+        if let Some(assignee) = &state.status.assignee {
+            details.push_str(&format!(", {}'s turn", assignee));
+        }
+        if let Some(steps) = state.snooze_selection {
+            details.push_str(&format!(", selecting {} snooze steps", steps));
+        }
+        for (zone, zone_state) in &state.status.zones {
+            details.push_str(&format!(", {} {:?}", zone, zone_state));
+        }
+        if !state.status.care_due.is_empty() {
+            details.push_str(&format!(", {} due", state.status.care_due.join(" and ")));
+        }
+        if state.is_supply_low {
+            details.push_str(", litter running out");
+        }
+        if state.is_reset_armed {
+            details.push_str(", press again to confirm the reset");
+        }
+        line.push_str(&details);
+        match &self.clock {
+            Some(_) => {
+                let key = format!("{:?}{}", state.status.state, details);
+                if key != self.line {
+                    println!("{} {}", now.with_timezone(&schedule().timezone).format("%Y-%m-%d %H:%M"), line);
+                    self.line = key;
+                }
+            }
+            None if line != self.line => {
+                println!("{}", line);
+                self.line = line;
+            }
+            None => ()
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::sleep;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::reminder::ReminderEvent;
+
+const POLL_DELAY: Duration = Duration::from_millis(1000);
+
+/// Monitoring of the SoC temperature, which gets high in fanless cases in summer.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct ThermalConfig {
+    pub zone: String,
+    pub interval_seconds: u64,
+    /// Temperature above which the strip is throttled
+    pub threshold_celsius: f64,
+    /// Amount by which the temperature needs to drop below the threshold for the throttling to end
+    pub hysteresis_celsius: f64,
+    pub action: ThermalAction
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        ThermalConfig {
+            zone: "/sys/class/thermal/thermal_zone0/temp".to_string(),
+            interval_seconds: 30,
+            threshold_celsius: 75.0,
+            hysteresis_celsius: 5.0,
+            action: ThermalAction::Dim
+        }
+    }
+}
+
+/// What happens to the displays that light up the room while the SoC is too hot.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ThermalAction {
+    #[default]
+    Dim,
+    Off
+}
+
+/// Runs the thermal thread, which periodically reports the SoC temperature to the reminder, along with the action
+/// to take while the temperature is above the threshold.
+pub fn run(config: ThermalConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let mut last_reading: Option<chrono::DateTime<Utc>> = None;
+        let mut is_throttling = false;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let now = Utc::now();
+            if last_reading.is_some_and(|t| now.signed_duration_since(t).num_seconds() < config.interval_seconds as i64) {
+                continue;
+            }
+            last_reading = Some(now);
+
+            // the kernel reports thousandths of degrees Celsius
+            let celsius = match fs::read_to_string(&config.zone).map(|s| s.trim().parse::<f64>()) {
+                Ok(Ok(millidegrees)) => millidegrees / 1000.0,
+                Ok(Err(e)) => {
+                    log::error!("Invalid SoC temperature: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    log::error!("Could not read SoC temperature: {}", e);
+                    continue;
+                }
+            };
+            if !is_throttling && celsius > config.threshold_celsius {
+                log::warn!("SoC temperature is {:.1}°C, throttling the LED strip ({:?})", celsius, config.action);
+                is_throttling = true;
+            } else if is_throttling && celsius < config.threshold_celsius - config.hysteresis_celsius {
+                log::info!("SoC temperature is back to {:.1}°C", celsius);
+                is_throttling = false;
+            }
+            let throttling = if is_throttling { Some(config.action) } else { None };
+            if reminder_tx.send(ReminderEvent::CpuTemperatureMeasured(celsius, throttling)).is_err() {
+                break;
+            }
+        }
+    });
+}
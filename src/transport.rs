@@ -1,16 +1,26 @@
 use std::collections::HashMap;
-use super::protocol::Message;
+use std::fmt;
+use super::protocol::{Encoding, Message};
 
-use message_io::network::{Endpoint, NetEvent, Transport, ToRemoteAddr, SendStatus};
-use message_io::node::{self, NodeEvent};
+use message_io::network::{Endpoint, NetEvent, Transport, SendStatus};
+use message_io::node::{self, NodeEvent, NodeHandler};
 
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::sync::Arc;
+use std::os::unix::ffi::OsStrExt;
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
+use std::thread::JoinHandle;
 use std::time::Duration;
 use chrono::{DateTime, Utc};
+use gethostname::gethostname;
+use serde::Deserialize;
+use crate::config::schedule;
+use crate::error::CatReminderError;
 use crate::reminder::ReminderEvent;
+use crate::state::DEFAULT_BOX;
+use crate::update;
+use crate::websocket::StreamEvent;
 
 enum Signal {
     Tick
@@ -18,42 +28,255 @@ enum Signal {
 
 pub enum TransportEvent {
     NodeListUpdated(HashMap<String, Vec<Ipv4Addr>>),
-    CleaningTimeReset(DateTime<Utc>)
+    /// The time of the last cleaning, with who cleaned, if known, and the time at which it was set, later than the
+    /// cleaning when it was corrected
+    CleaningTimeReset(DateTime<Utc>, Option<String>, DateTime<Utc>),
+    /// Asks all the other nodes to check for an update
+    UpdateRequested,
+    /// The reminder was snoozed, paused or resumed at the given time, until the other one, the end of times if paused
+    Snoozed(DateTime<Utc>, DateTime<Utc>),
+    /// The time at which the litter box becomes overdue changed
+    DueTimeChanged(DateTime<Utc>),
+    /// The litter box of another zone was reset on a hub, with the time of the cleaning and who cleaned, if known
+    ZoneCleaningTimeReset(String, DateTime<Utc>, Option<String>)
 }
 
-pub fn run(ip_addr: IpAddr, port: u16, reminder_tx: Sender<ReminderEvent>, rx: Receiver<TransportEvent>, initial_state: DateTime<Utc>, shutdown_flag: Arc<AtomicBool>) {
-    let addr: SocketAddr = SocketAddr::new(ip_addr, port);
+/// What a node is made of, as advertised in the `profile` TXT record of its mDNS service.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeRole {
+    /// A node with a button and displays, which other nodes can get the state from
+    #[default]
+    Full,
+    /// A node without a button, which shows the state of the other nodes but leaves it to them to answer for it
+    Display,
+    /// A node without displays, e.g. next to a litter box with sensors
+    Sensor
+}
+
+impl NodeRole {
+    /// Whether other nodes get the state from the node, which display-only nodes leave to the others.
+    pub fn has_authority(&self) -> bool {
+        *self != NodeRole::Display
+    }
+}
+
+impl fmt::Display for NodeRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeRole::Full => write!(f, "full"),
+            NodeRole::Display => write!(f, "display"),
+            NodeRole::Sensor => write!(f, "sensor")
+        }
+    }
+}
+
+/// The zone of a node, e.g. a floor of the house, whose litter box it shows and keeps in sync with the other nodes of
+/// the zone. Hubs also keep track of the litter boxes of the other zones, to show all of them.
+#[derive(Clone, Default, Debug)]
+pub struct Zoning {
+    pub zone: Option<String>,
+    pub hub: bool
+}
 
-    let (handler, listener) = node::split();
+impl Zoning {
+    /// The message with the state of the litter box of the zone. Nodes outside of zones send the message that companions
+    /// and nodes of earlier versions understand.
+    fn state_message(&self, time: DateTime<Utc>, by: Option<String>, node: Option<String>) -> Message {
+        if self.zone.is_some() || self.hub {
+            Message::ZoneState(self.zone.clone(), Some(time), by, node)
+        } else {
+            Message::UpdateState(Some(time), by, node)
+        }
+    }
 
-    match handler.network().listen(Transport::Udp, addr) {
-        Ok((_id, real_addr)) =>
-            log::info!("Server running at {}", real_addr),
-        Err(_) =>
-            panic!("Can not listen at {}", addr)
+    /// The messages with the state of the litter box of the zone. A cleaning time set later than the cleaning, e.g. to
+    /// correct it, is sent as a correction first, which full nodes take even though the time is older than theirs,
+    /// followed by the state for companions and nodes of earlier versions.
+    fn state_messages(&self, time: DateTime<Utc>, set_at: DateTime<Utc>, by: Option<String>, node: Option<String>) -> Vec<Message> {
+        let state = self.state_message(time, by.clone(), node.clone());
+        if set_at.timestamp() > time.timestamp() {
+            vec![Message::CorrectedState(self.zone.clone(), time, set_at, by, node), state]
+        } else {
+            vec![state]
+        }
     }
+}
+
+/// Runs the transport, which exchanges cleaning times with the other nodes. The receiver is shared so that it
+/// outlives the thread, which can be restarted when it stops.
+pub fn run(ip_addr: IpAddr, port: u16, reminder_tx: Sender<ReminderEvent>, event_tx: Sender<StreamEvent>, rx: Arc<Mutex<Receiver<TransportEvent>>>, initial_state: DateTime<Utc>, encoding: Encoding, zoning: Zoning, role: NodeRole, shutdown_flag: Arc<AtomicBool>) -> JoinHandle<()> {
+    std::thread::Builder::new().name("transport".to_string()).spawn(move || {
+        let _span = tracing::info_span!("transport", %ip_addr, port).entered();
+        let addr: SocketAddr = SocketAddr::new(ip_addr, port);
 
-    let mut other_nodes_connections: HashMap<String, Endpoint> = HashMap::new();
-    let mut last_modification_time: DateTime<Utc> = initial_state;
+        let (handler, listener) = node::split();
 
-    handler.signals().send_with_timer(Signal::Tick, Duration::from_millis(500));
+        match handler.network().listen(Transport::Udp, addr) {
+            Ok((_id, real_addr)) =>
+                log::info!("Server running at {}", real_addr),
+            Err(e) => {
+                // the supervisor starts the transport again, e.g. once the address is available
+                log::error!("Can not listen at {}: {}", addr, e);
+                return;
+            }
+        }
+
+        let mut other_nodes_connections: HashMap<String, Endpoint> = HashMap::new();
+        let mut last_modification_time: DateTime<Utc> = initial_state;
+        // when the cleaning time was set, which differs from it when it was corrected
+        let mut last_set_at: DateTime<Utc> = initial_state;
+        let mut encodings = Encodings { configured: encoding, peers: HashMap::new() };
+        let mut last_modified_by: Option<String> = None;
+        let node_name = String::from_utf8_lossy(gethostname().as_bytes()).to_string();
+        let mut last_modified_on: Option<String> = None;
+        // the latest cleaning times of the other zones, kept by hubs
+        let mut zone_times: HashMap<String, DateTime<Utc>> = HashMap::new();
+        // the latest snooze, pause or resume known, with the time at which it happened
+        let mut snooze: Option<(DateTime<Utc>, Option<DateTime<Utc>>)> = None;
+        // the due time worked out by the reminder, once it differs from the one of the schedule alone
+        let mut due_time: Option<DateTime<Utc>> = None;
+
+        handler.signals().send_with_timer(Signal::Tick, Duration::from_millis(500));
 
-    std::thread::spawn(move || {
         listener.for_each(move |event| match event {
             NodeEvent::Network(net_event) => match net_event {
                 NetEvent::Message(endpoint, input_data) => {
-                    let message: Message = bincode::deserialize(&input_data).unwrap();
+                    let message = match Message::decode_with_encoding(input_data) {
+                        Ok((message, encoding)) => {
+                            encodings.peers.insert(endpoint.addr().ip(), encoding);
+                            message
+                        }
+                        Err(e) => {
+                            log::warn!("Dropping packet from {}: {}", endpoint.addr(), e);
+                            return;
+                        }
+                    };
+                    // updates without a zone come from companions and from nodes outside of zones, which discovery keeps
+                    // within the zone of the node
+                    let message = match message {
+                        Message::ZoneState(zone, new_state, by, node) if zone == zoning.zone => Message::UpdateState(new_state, by, node),
+                        message => message
+                    };
                     match message {
+                        Message::RequestState if !role.has_authority() => {
+                            log::debug!("Leaving the state request of {} to the other nodes", endpoint.addr());
+                        }
                         Message::RequestState => {
-                            let reply = Message::UpdateState(Some(last_modification_time));
-                            let output_data = bincode::serialize(&reply).unwrap();
-                            handler.network().send(endpoint, &output_data);
+                            zoning.state_messages(last_modification_time, last_set_at, last_modified_by.clone(), last_modified_on.clone()).iter().for_each(|reply| {
+                                send(&handler, endpoint, reply, encodings.of(endpoint));
+                            });
+                            // a restarted node would otherwise resume blinking in the middle of a snooze
+                            if let Some((snoozed_at, until)) = snooze {
+                                send(&handler, endpoint, &Message::Snooze(snoozed_at, until), encodings.of(endpoint));
+                            }
                         }
-                        Message::UpdateState(new_state) => {
+                        Message::UpdateState(new_state, by, node) => {
                             log::info!("Update state received from network");
                             if let Some(timestamp) = new_state {
+                                if timestamp.timestamp() == last_modification_time.timestamp() {
+                                    return;
+                                }
+                                // the sender missed a reset or a correction known here, e.g. while the network was
+                                // partitioned. A node that has not seen any reset since it started takes the state of the
+                                // network instead.
+                                if last_modified_on.is_some() && timestamp.timestamp() < last_modification_time.max(last_set_at).timestamp() {
+                                    if !role.has_authority() {
+                                        return;
+                                    }
+                                    log::info!("Outdated state received from {}, sending the current one to all nodes", endpoint.addr());
+                                    let current = zoning.state_messages(last_modification_time, last_set_at, last_modified_by.clone(), last_modified_on.clone());
+                                    other_nodes_connections.values().for_each(|endpoint| {
+                                        current.iter().for_each(|message| {
+                                            send(&handler, *endpoint, message, encodings.of(*endpoint));
+                                        });
+                                    });
+                                    return;
+                                }
+                                let node = node.unwrap_or_else(|| endpoint.addr().ip().to_string());
                                 last_modification_time = timestamp;
-                                reminder_tx.send(ReminderEvent::CleaningTimeUpdated(timestamp)).expect("Failed to send updated state")
+                                last_set_at = timestamp;
+                                last_modified_by = by.clone();
+                                last_modified_on = Some(node.clone());
+                                if reminder_tx.send(ReminderEvent::CleaningTimeUpdated(timestamp, by, node)).is_err() {
+                                    log::error!("{}", CatReminderError::ChannelClosed("reminder"));
+                                }
+                            }
+                        }
+                        Message::ZoneState(Some(zone), Some(timestamp), by, node) if zoning.hub => {
+                            if zone_times.get(&zone).is_some_and(|time| time.timestamp() >= timestamp.timestamp()) {
+                                return;
+                            }
+                            log::info!("Update state of the {} zone received from network", zone);
+                            zone_times.insert(zone.clone(), timestamp);
+                            let node = node.unwrap_or_else(|| endpoint.addr().ip().to_string());
+                            if reminder_tx.send(ReminderEvent::ZoneCleaningTimeUpdated(zone, timestamp, by, node)).is_err() {
+                                log::error!("{}", CatReminderError::ChannelClosed("reminder"));
+                            }
+                        }
+                        Message::ZoneState(zone, _, _, _) => {
+                            log::debug!("Ignoring the state of the {} zone from {}", zone.as_deref().unwrap_or("unzoned"), endpoint.addr());
+                        }
+                        Message::CorrectedState(zone, time, set_at, by, node) if zone == zoning.zone => {
+                            // corrections made before the latest change known here are outdated, as are repeated ones
+                            if set_at.timestamp() <= last_modification_time.max(last_set_at).timestamp() {
+                                return;
+                            }
+                            log::info!("Corrected cleaning time received from network");
+                            let node = node.unwrap_or_else(|| endpoint.addr().ip().to_string());
+                            last_modification_time = time;
+                            last_set_at = set_at;
+                            last_modified_by = by.clone();
+                            last_modified_on = Some(node.clone());
+                            if reminder_tx.send(ReminderEvent::CleaningTimeUpdated(time, by, node)).is_err() {
+                                log::error!("{}", CatReminderError::ChannelClosed("reminder"));
+                            }
+                        }
+                        Message::CorrectedState(Some(zone), time, _, by, node) if zoning.hub => {
+                            if zone_times.get(&zone).is_some_and(|known| known.timestamp() == time.timestamp()) {
+                                return;
+                            }
+                            log::info!("Corrected cleaning time of the {} zone received from network", zone);
+                            zone_times.insert(zone.clone(), time);
+                            let node = node.unwrap_or_else(|| endpoint.addr().ip().to_string());
+                            if reminder_tx.send(ReminderEvent::ZoneCleaningTimeUpdated(zone, time, by, node)).is_err() {
+                                log::error!("{}", CatReminderError::ChannelClosed("reminder"));
+                            }
+                        }
+                        Message::CorrectedState(zone, _, _, _, _) => {
+                            log::debug!("Ignoring the correction of the {} zone from {}", zone.as_deref().unwrap_or("unzoned"), endpoint.addr());
+                        }
+                        Message::Heartbeat if !role.has_authority() => {
+                            log::debug!("Leaving the heartbeat of {} to the other nodes", endpoint.addr());
+                        }
+                        Message::Heartbeat => {
+                            log::debug!("Heartbeat from companion node at {}", endpoint.addr());
+                            let reply = zoning.state_message(last_modification_time, last_modified_by.clone(), last_modified_on.clone());
+                            send(&handler, endpoint, &reply, encodings.of(endpoint));
+                            let due_time = due_time.unwrap_or_else(|| schedule().due_time(DEFAULT_BOX, last_modification_time));
+                            send(&handler, endpoint, &Message::DueTime(due_time), encodings.of(endpoint));
+                        }
+                        Message::GoingDown => {
+                            log::info!("Node at {} is going down", endpoint.addr());
+                            // the connections to the other nodes are not the endpoint their messages arrive on, only their address matches
+                            other_nodes_connections.retain(|_, e| e.addr().ip() != endpoint.addr().ip());
+                        }
+                        Message::CheckForUpdate => {
+                            log::info!("Node at {} asks to check for an update", endpoint.addr());
+                            update::request_check();
+                        }
+                        Message::DueTime(time) => {
+                            log::debug!("Node at {} has the litter box due at {}, which full nodes work out themselves", endpoint.addr(), time);
+                        }
+                        Message::Snooze(snoozed_at, until) => {
+                            if snooze.is_some_and(|(known, _)| known.timestamp() >= snoozed_at.timestamp()) {
+                                return;
+                            }
+                            log::info!("Snooze received from {}", endpoint.addr());
+                            snooze = Some((snoozed_at, until));
+                            let until = until.unwrap_or(DateTime::<Utc>::MAX_UTC);
+                            if reminder_tx.send(ReminderEvent::SnoozeSynced(snoozed_at, until, endpoint.addr().ip().to_string())).is_err() {
+                                log::error!("{}", CatReminderError::ChannelClosed("reminder"));
                             }
                         }
                     }
@@ -63,50 +286,113 @@ pub fn run(ip_addr: IpAddr, port: u16, reminder_tx: Sender<ReminderEvent>, rx: R
             NodeEvent::Signal(signal) => match signal {
                 Signal::Tick => {
                     // see if there are updated nodes from mDNS
-                    if let Ok(msg) = rx.try_recv() {
+                    if let Ok(msg) = rx.lock().unwrap_or_else(|e| e.into_inner()).try_recv() {
                         match msg {
                             TransportEvent::NodeListUpdated(list) => {
                                 log::info!("Updating node list {:?}", list);
+                                let _ = event_tx.send(StreamEvent::PeersChanged { peers: list.keys().cloned().collect() });
                                 let new_node_connections: HashMap<String, Endpoint> = list.iter()
                                     .filter(|(k, _)| { !&other_nodes_connections.contains_key(k.as_str()) })
                                     .flat_map(|(k, ips)| {
-                                        ips.iter().map(|ip| {
-                                            let (receiver_id, _) =
-                                                handler.network().connect_sync(Transport::Udp, format!("{}:{}", ip.clone().to_string(), port).to_remote_addr().expect("Failed to convert remote address")).expect("Failed to connect");
-                                            (k.clone(), receiver_id)
+                                        ips.iter().filter_map(|ip| {
+                                            match handler.network().connect_sync(Transport::Udp, SocketAddr::new(IpAddr::V4(*ip), port)) {
+                                                Ok((receiver_id, _)) => Some((k.clone(), receiver_id)),
+                                                Err(e) => {
+                                                    log::warn!("Could not connect to {} at {}: {}", k, ip, e);
+                                                    None
+                                                }
+                                            }
                                         }).collect::<Vec<_>>()
                                 }).collect();
                                 let require_state = other_nodes_connections.len() == 0 && new_node_connections.len() > 0;
+                                // display-only nodes do not answer, so that all nodes are asked, and hubs ask every node joining
+                                // to learn the state of each zone
+                                let state_requests: Vec<Endpoint> = if require_state || zoning.hub { new_node_connections.values().copied().collect() } else { Vec::new() };
+                                // nodes joining after a partition may have missed the last reset
+                                if last_modified_on.is_some() && role.has_authority() {
+                                    let current = zoning.state_messages(last_modification_time, last_set_at, last_modified_by.clone(), last_modified_on.clone());
+                                    new_node_connections.values().for_each(|endpoint| {
+                                        current.iter().for_each(|message| {
+                                            send(&handler, *endpoint, message, encodings.of(*endpoint));
+                                        });
+                                    });
+                                }
+                                // nodes that stayed up missed a snooze set while this node was on its own, e.g. before it restarted
+                                if let Some((snoozed_at, until)) = snooze {
+                                    new_node_connections.values().for_each(|endpoint| {
+                                        send(&handler, *endpoint, &Message::Snooze(snoozed_at, until), encodings.of(*endpoint));
+                                    });
+                                }
+                                if let Some(due_time) = due_time {
+                                    new_node_connections.values().for_each(|endpoint| {
+                                        send(&handler, *endpoint, &Message::DueTime(due_time), encodings.of(*endpoint));
+                                    });
+                                }
                                 other_nodes_connections.extend(new_node_connections);
                                 other_nodes_connections.retain(|k, _| {
                                     list.contains_key(k.as_str())
                                 });
                                 log::info!("Done updating connections: {:?}", other_nodes_connections);
-                                if require_state {
-                                    log::info!("Requesting state update from the network");
-                                    if let Some((_, endpoint)) = &other_nodes_connections.iter().next() {
-                                        let msg = Message::RequestState;
-                                        let output_data = bincode::serialize(&msg).unwrap();
-                                        let status = handler.network().send(**endpoint, &output_data);
+                                if !state_requests.is_empty() {
+                                    log::info!("Requesting state update from {} nodes", state_requests.len());
+                                    state_requests.iter().for_each(|endpoint| {
+                                        let status = send(&handler, *endpoint, &Message::RequestState, encodings.of(*endpoint));
                                         log::info!("Send status {:?}", status);
-                                    }
+                                    });
                                 }
                             }
-                            TransportEvent::CleaningTimeReset(updated_time) => {
+                            TransportEvent::CleaningTimeReset(updated_time, by, set_at) => {
                                 log::info!("Starting to send updated state");
                                 last_modification_time = updated_time;
+                                last_set_at = set_at;
+                                last_modified_by = by.clone();
+                                last_modified_on = Some(node_name.clone());
+                                let messages = zoning.state_messages(updated_time, set_at, by.clone(), Some(node_name.clone()));
                                 other_nodes_connections.iter().for_each(|(id, endpoint)| {
                                     log::info!("Sending updated state to {}", id);
-                                    let msg = Message::UpdateState(Some(updated_time));
-                                    let output_data = bincode::serialize(&msg).unwrap();
-                                    let status: SendStatus = handler.network().send(*endpoint, &output_data);
-                                    log::info!("Send status {:?}", status);
+                                    messages.iter().for_each(|message| {
+                                        let status = send(&handler, *endpoint, message, encodings.of(*endpoint));
+                                        log::info!("Send status {:?}", status);
+                                    });
+                                });
+                            }
+                            TransportEvent::UpdateRequested => {
+                                log::info!("Asking {} nodes to check for an update", other_nodes_connections.len());
+                                other_nodes_connections.values().for_each(|endpoint| {
+                                    send(&handler, *endpoint, &Message::CheckForUpdate, encodings.of(*endpoint));
+                                });
+                            }
+                            TransportEvent::Snoozed(snoozed_at, until) => {
+                                let until = (until != DateTime::<Utc>::MAX_UTC).then_some(until);
+                                snooze = Some((snoozed_at, until));
+                                log::info!("Sending the snooze to {} nodes", other_nodes_connections.len());
+                                other_nodes_connections.values().for_each(|endpoint| {
+                                    send(&handler, *endpoint, &Message::Snooze(snoozed_at, until), encodings.of(*endpoint));
+                                });
+                            }
+                            TransportEvent::DueTimeChanged(time) => {
+                                due_time = Some(time);
+                                other_nodes_connections.values().for_each(|endpoint| {
+                                    send(&handler, *endpoint, &Message::DueTime(time), encodings.of(*endpoint));
+                                });
+                            }
+                            TransportEvent::ZoneCleaningTimeReset(zone, time, by) => {
+                                // the nodes of the zone take it as their own state, the other hubs keep track of it
+                                log::info!("Sending the cleaning of the {} zone to {} nodes", zone, other_nodes_connections.len());
+                                zone_times.insert(zone.clone(), time);
+                                let message = Message::ZoneState(Some(zone), Some(time), by, Some(node_name.clone()));
+                                other_nodes_connections.values().for_each(|endpoint| {
+                                    send(&handler, *endpoint, &message, encodings.of(*endpoint));
                                 });
                             }
                         }
                     }
 
                     if shutdown_flag.load(Ordering::Relaxed) {
+                        log::info!("Letting the other nodes know that this node is going down");
+                        other_nodes_connections.values().for_each(|endpoint| {
+                            send(&handler, *endpoint, &Message::GoingDown, encodings.of(*endpoint));
+                        });
                         handler.stop();
                     } else {
                         handler.signals().send_with_timer(Signal::Tick, Duration::from_millis(500));
@@ -114,6 +400,28 @@ pub fn run(ip_addr: IpAddr, port: u16, reminder_tx: Sender<ReminderEvent>, rx: R
                 }
             }
         });
-    });
+    }).expect("Could not start transport thread")
+}
+
+/// The encoding of the messages to each node: the one it used last, or the configured one until it sent something.
+struct Encodings {
+    configured: Encoding,
+    peers: HashMap<IpAddr, Encoding>
+}
 
+impl Encodings {
+    fn of(&self, endpoint: Endpoint) -> Encoding {
+        self.peers.get(&endpoint.addr().ip()).copied().unwrap_or(self.configured)
+    }
+}
+
+/// Sends a message to another node, which is skipped if the message cannot be encoded.
+fn send(handler: &NodeHandler<Signal>, endpoint: Endpoint, message: &Message, encoding: Encoding) -> Option<SendStatus> {
+    match message.encode_as(encoding) {
+        Ok(data) => Some(handler.network().send(endpoint, &data)),
+        Err(e) => {
+            log::error!("Could not send message to {}: {}", endpoint.addr(), e);
+            None
+        }
+    }
 }
@@ -0,0 +1,199 @@
+//! Over-the-air updates: the updater periodically looks for a newer release, verifies the Ed25519 signature of its
+//! version and binary, swaps it atomically and restarts the service through systemd.
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::Deserialize;
+
+const POLL_DELAY: Duration = Duration::from_secs(1);
+/// Far larger than the binary, while bounding what a wrong URL can make the updater download
+const MAX_BINARY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Set to check for an update right away instead of waiting for the interval, e.g. when another node asks for it.
+static CHECK_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// The updater is enabled by adding the section.
+#[derive(Deserialize, Clone)]
+pub struct UpdateConfig {
+    pub source: ReleaseSource,
+    /// Ed25519 public key with which the binaries are signed, in hexadecimal
+    pub public_key: String,
+    #[serde(default = "default_check_interval_hours")]
+    pub check_interval_hours: u64,
+    /// systemd unit restarted after the update
+    #[serde(default = "default_service")]
+    pub service: String
+}
+
+fn default_check_interval_hours() -> u64 {
+    24
+}
+
+fn default_service() -> String {
+    "cat-reminder".to_string()
+}
+
+/// Where the releases are published.
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ReleaseSource {
+    /// A JSON manifest such as `{"version": "0.2.0", "url": "https://…/cat-litter-reminder", "signature": "…"}`, the
+    /// signature being in hexadecimal
+    Manifest { url: String },
+    /// The latest release of a GitHub repository, e.g. `owner/rust-cat-reminder`, with the binary as the named asset
+    /// and its signature, in hexadecimal, as the same asset with the `.sig` extension
+    Github { repository: String, asset: String }
+}
+
+/// A release, as published by the source.
+struct Release {
+    version: String,
+    url: String,
+    signature: String
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    assets: Vec<GithubAsset>
+}
+
+#[derive(Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String
+}
+
+/// Asks the updater to check for an update right away.
+pub fn request_check() {
+    CHECK_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Runs the updater thread, which checks for an update on start, at every interval and when asked to.
+pub fn run(config: UpdateConfig, shutdown_flag: Arc<AtomicBool>) {
+    std::thread::Builder::new().name("update".to_string()).spawn(move || {
+        let interval = Duration::from_secs(config.check_interval_hours * 3600);
+        let mut last_check: Option<Instant> = None;
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            let is_due = last_check.map_or(true, |t| t.elapsed() >= interval);
+            if CHECK_REQUESTED.swap(false, Ordering::Relaxed) || is_due {
+                last_check = Some(Instant::now());
+                match update(&config) {
+                    Ok(Some(version)) => {
+                        log::info!("Updated to version {}, restarting", version);
+                        restart(&config.service);
+                    }
+                    Ok(None) => log::debug!("No update available"),
+                    Err(e) => log::error!("Could not update: {}", e)
+                }
+            }
+            sleep(POLL_DELAY);
+        }
+    }).expect("Could not start update thread");
+}
+
+/// Installs the latest release if it is newer than the running version, returning its version.
+fn update(config: &UpdateConfig) -> Result<Option<String>, Box<dyn Error>> {
+    let release = latest_release(&config.source)?;
+    if !is_newer(&release.version, env!("CARGO_PKG_VERSION")) {
+        return Ok(None);
+    }
+    log::info!("Downloading version {} from {}", release.version, release.url);
+    let mut binary = Vec::new();
+    ureq::get(&release.url).call()?.into_reader().take(MAX_BINARY_SIZE).read_to_end(&mut binary)?;
+    verify(&signed_message(&release.version, &binary), &release.signature, &config.public_key)?;
+    install(&std::env::current_exe()?, &binary)?;
+    Ok(Some(release.version))
+}
+
+fn latest_release(source: &ReleaseSource) -> Result<Release, Box<dyn Error>> {
+    match source {
+        ReleaseSource::Manifest { url } => {
+            let manifest: serde_json::Value = ureq::get(url).call()?.into_json()?;
+            let field = |name: &str| manifest[name].as_str().map(str::to_string).ok_or_else(|| format!("No {} in the manifest", name));
+            Ok(Release { version: field("version")?, url: field("url")?, signature: field("signature")? })
+        }
+        ReleaseSource::Github { repository, asset } => {
+            let release: GithubRelease = ureq::get(&format!("https://api.github.com/repos/{}/releases/latest", repository))
+                .set("Accept", "application/vnd.github+json")
+                .call()?
+                .into_json()?;
+            let url = |name: &str| release.assets.iter().find(|a| a.name == name).map(|a| a.browser_download_url.clone())
+                .ok_or_else(|| format!("No {} asset in release {}", name, release.tag_name));
+            let signature_url = url(&format!("{}.sig", asset))?;
+            Ok(Release { version: release.tag_name.clone(), url: url(asset)?, signature: ureq::get(&signature_url).call()?.into_string()? })
+        }
+    }
+}
+
+/// Whether the version, with an optional `v` prefix, is newer than the current one, comparing the numbers one by one.
+pub fn is_newer(version: &str, current: &str) -> bool {
+    let numbers = |version: &str| -> Vec<u64> {
+        version.trim_start_matches('v').split('.').map(|n| n.parse().unwrap_or(0)).collect()
+    };
+    numbers(version) > numbers(current)
+}
+
+/// What the signature of a release covers: its version without the `v` prefix, a newline and the binary, so that an
+/// older binary cannot be passed off as a newer version with its genuine signature.
+pub fn signed_message(version: &str, binary: &[u8]) -> Vec<u8> {
+    let mut message = format!("{}\n", version.trim_start_matches('v')).into_bytes();
+    message.extend_from_slice(binary);
+    message
+}
+
+/// Checks the Ed25519 signature of the message, both the signature and the public key being in hexadecimal.
+pub fn verify(message: &[u8], signature: &str, public_key: &str) -> Result<(), Box<dyn Error>> {
+    let key: [u8; 32] = decode_hex(public_key)?.try_into().map_err(|_| "The public key is not 32 bytes long")?;
+    let signature: [u8; 64] = decode_hex(signature)?.try_into().map_err(|_| "The signature is not 64 bytes long")?;
+    VerifyingKey::from_bytes(&key)?.verify(message, &Signature::from_bytes(&signature))
+        .map_err(|_| "The signature of the release does not match, not installing it".into())
+}
+
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let hex = hex.trim();
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err("Invalid hexadecimal".into());
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.into())).collect()
+}
+
+/// Replaces the binary by renaming the new one over it, so that it is never left half-written. The previous binary is
+/// kept with the `.old` extension.
+fn install(path: &Path, binary: &[u8]) -> std::io::Result<()> {
+    let new_path = sibling(path, "new");
+    let mut file = File::create(&new_path)?;
+    file.write_all(binary)?;
+    file.set_permissions(fs::Permissions::from_mode(0o755))?;
+    file.sync_all()?;
+    let old_path = sibling(path, "old");
+    let _ = fs::remove_file(&old_path);
+    fs::hard_link(path, &old_path)?;
+    fs::rename(&new_path, path)
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+/// Restarts the service, which stops this process gracefully.
+fn restart(service: &str) {
+    match Command::new("systemctl").args(["restart", service]).status() {
+        Ok(status) if status.success() => (),
+        Ok(status) => log::error!("systemctl exited with {} restarting {}", status, service),
+        Err(e) => log::error!("Could not run systemctl: {}", e)
+    }
+}
@@ -0,0 +1,112 @@
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{sleep, JoinHandle};
+use std::time::Duration;
+
+use linux_embedded_hal::i2cdev::core::I2CDevice;
+use linux_embedded_hal::i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+use serde::Deserialize;
+
+use crate::reminder::ReminderEvent;
+
+const POLL_DELAY: Duration = Duration::from_millis(5000);
+
+const SHUNT_VOLTAGE_REGISTER: u8 = 0x01;
+const BUS_VOLTAGE_REGISTER: u8 = 0x02;
+
+/// A UPS HAT with an INA219 measuring the battery voltage and current, such as the Waveshare UPS HAT.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct UpsConfig {
+    pub i2c_bus: String,
+    pub address: u16,
+    /// Resistance of the shunt resistor
+    pub shunt_ohms: f64,
+    /// Current drawn from the battery above which the node is considered running on battery. The current is negative
+    /// while discharging on most HATs, set a negative threshold for the others.
+    pub discharge_threshold_ma: f64,
+    /// Battery voltage below which the reminder shuts down, letting its peers know
+    pub shutdown_voltage: f64,
+    /// Command run when shutting down on low battery, e.g. `sudo shutdown -h now`
+    pub shutdown_command: Option<String>
+}
+
+impl Default for UpsConfig {
+    fn default() -> Self {
+        UpsConfig {
+            i2c_bus: "/dev/i2c-1".to_string(),
+            address: 0x42,
+            shunt_ohms: 0.1,
+            discharge_threshold_ma: 50.0,
+            shutdown_voltage: 3.2,
+            shutdown_command: None
+        }
+    }
+}
+
+/// Runs the UPS thread, which reports to the reminder whether the node runs on battery, and shuts the reminder down
+/// when the battery is about to run out. The thread ends with the shutdown command if it shut the reminder down, to
+/// be run with [shut_down] once everything else stopped.
+///
+/// # Panics
+///
+/// Panics if the I²C device cannot be opened.
+pub fn run(config: UpsConfig, reminder_tx: Sender<ReminderEvent>, shutdown_flag: Arc<AtomicBool>) -> JoinHandle<Option<String>> {
+    let mut device = LinuxI2CDevice::new(&config.i2c_bus, config.address).expect("Could not open I2C device");
+    std::thread::spawn(move || {
+        let mut on_battery = false;
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            sleep(POLL_DELAY);
+            let (voltage, current_ma) = match read(&mut device, config.shunt_ohms) {
+                Ok(reading) => reading,
+                Err(e) => {
+                    log::warn!("Could not read UPS: {:?}", e);
+                    continue;
+                }
+            };
+            log::debug!("UPS battery at {:.2}V, {:.0}mA", voltage, current_ma);
+
+            let is_discharging = if config.discharge_threshold_ma >= 0.0 {
+                -current_ma > config.discharge_threshold_ma
+            } else {
+                current_ma > -config.discharge_threshold_ma
+            };
+            if is_discharging != on_battery {
+                on_battery = is_discharging;
+                log::warn!("{}", if on_battery { "Running on battery" } else { "Back on mains power" });
+                if reminder_tx.send(ReminderEvent::PowerChanged(on_battery)).is_err() {
+                    break;
+                }
+            }
+
+            if on_battery && voltage < config.shutdown_voltage {
+                log::warn!("Battery at {:.2}V, shutting down", voltage);
+                shutdown_flag.store(true, Ordering::Relaxed);
+                return config.shutdown_command;
+            }
+        }
+        None
+    })
+}
+
+/// Runs the command shutting the node down.
+pub fn shut_down(command: &str) {
+    log::warn!("Running the shutdown command");
+    if let Err(e) = Command::new("sh").arg("-c").arg(command).status() {
+        log::error!("Could not run shutdown command: {}", e);
+    }
+}
+
+/// Reads the bus voltage in volts and the current in milliamperes.
+fn read(device: &mut LinuxI2CDevice, shunt_ohms: f64) -> Result<(f64, f64), LinuxI2CError> {
+    // the registers are big endian, while SMBus words are little endian
+    let shunt = device.smbus_read_word_data(SHUNT_VOLTAGE_REGISTER)?.swap_bytes() as i16;
+    let bus = device.smbus_read_word_data(BUS_VOLTAGE_REGISTER)?.swap_bytes();
+    // 10µV per bit for the shunt voltage, 4mV per bit for the bus voltage, stored in the upper 13 bits
+    let shunt_volts = shunt as f64 * 0.000_01;
+    let bus_volts = (bus >> 3) as f64 * 0.004;
+    Ok((bus_volts, shunt_volts / shunt_ohms * 1000.0))
+}
@@ -0,0 +1,37 @@
+//! The household members known to the reminder, so that whatever identifies them, be it a name typed in the CLI, an
+//! NFC tag or a chat handle, is recorded under the same name.
+
+use serde::Deserialize;
+
+use crate::config::users;
+
+#[derive(Deserialize, Clone)]
+pub struct UserConfig {
+    pub name: String,
+    /// UIDs of the NFC tags of the user, in hexadecimal
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Handles of the user in the chats, e.g. the Discord user name
+    #[serde(default)]
+    pub chat_handles: Vec<String>
+}
+
+/// The name of the user owning the NFC tag.
+pub fn by_tag(uid: &str) -> Option<&'static str> {
+    users().iter().find(|user| user.tags.iter().any(|tag| tag.eq_ignore_ascii_case(uid))).map(|user| user.name.as_str())
+}
+
+/// The name of the user with the chat handle.
+pub fn by_chat_handle(handle: &str) -> Option<&'static str> {
+    users().iter().find(|user| user.chat_handles.iter().any(|h| h.eq_ignore_ascii_case(handle))).map(|user| user.name.as_str())
+}
+
+/// The name under which to record whoever is identified by the name, tag or chat handle, which is kept as given if it
+/// does not belong to a known user.
+pub fn resolve(by: &str) -> String {
+    users().iter().find(|user| user.name.eq_ignore_ascii_case(by)).map(|user| user.name.as_str())
+        .or_else(|| by_tag(by))
+        .or_else(|| by_chat_handle(by))
+        .unwrap_or(by)
+        .to_string()
+}
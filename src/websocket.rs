@@ -0,0 +1,84 @@
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tungstenite::{Message, WebSocket};
+
+use crate::reminder::{LEDStripState, ResetSource};
+use crate::sensor::Climate;
+
+const POLL_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Deserialize, Clone)]
+pub struct WebSocketConfig {
+    #[serde(default = "default_port")]
+    pub port: u16
+}
+
+fn default_port() -> u16 {
+    8081
+}
+
+/// Events streamed as JSON documents to the WebSocket clients.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    StateChanged { state: LEDStripState, last_cleaning_time: DateTime<Utc> },
+    Reset { time: DateTime<Utc>, by: Option<String>, source: Option<ResetSource> },
+    CatVisited { time: DateTime<Utc> },
+    Weighed { time: DateTime<Utc>, grams: f64 },
+    ClimateMeasured { time: DateTime<Utc>, climate: Climate },
+    Snoozed { time: DateTime<Utc>, until: DateTime<Utc>, by: Option<String> },
+    Paused { time: DateTime<Utc>, by: Option<String> },
+    Resumed { time: DateTime<Utc>, by: Option<String> },
+    LitterReplaced { time: DateTime<Utc>, by: Option<String> },
+    BagOpened { time: DateTime<Utc>, by: Option<String> },
+    CareDone { time: DateTime<Utc>, name: String, by: Option<String> },
+    PeersChanged { peers: Vec<String> }
+}
+
+/// Runs the WebSocket server, which forwards every event it receives to all connected clients.
+pub fn run(ip_addr: IpAddr, config: WebSocketConfig, rx: Receiver<StreamEvent>, shutdown_flag: Arc<AtomicBool>) {
+    let addr = SocketAddr::new(ip_addr, config.port);
+    let listener = TcpListener::bind(addr).unwrap_or_else(|e| panic!("Can not listen at {}: {}", addr, e));
+    listener.set_nonblocking(true).expect("Could not set WebSocket listener to non-blocking");
+    log::info!("WebSocket server running at {}", addr);
+
+    std::thread::spawn(move || {
+        let mut clients: Vec<WebSocket<TcpStream>> = Vec::new();
+
+        while !shutdown_flag.load(Ordering::Relaxed) {
+            while let Ok((stream, peer_addr)) = listener.accept() {
+                match accept(stream) {
+                    Ok(client) => {
+                        log::info!("WebSocket client connected from {}", peer_addr);
+                        clients.push(client);
+                    }
+                    Err(e) => log::warn!("WebSocket handshake with {} failed: {}", peer_addr, e)
+                }
+            }
+
+            match rx.recv_timeout(POLL_DELAY) {
+                Ok(event) => {
+                    let json = serde_json::to_string(&event).expect("Failed to serialize stream event");
+                    clients.retain_mut(|client| client.send(Message::text(json.clone())).is_ok());
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => break
+            }
+        }
+
+        for mut client in clients {
+            let _ = client.close(None);
+        }
+    });
+}
+
+fn accept(stream: TcpStream) -> Result<WebSocket<TcpStream>, Box<dyn std::error::Error>> {
+    stream.set_nonblocking(false)?;
+    Ok(tungstenite::accept(stream)?)
+}
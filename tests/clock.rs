@@ -0,0 +1,28 @@
+use std::thread::sleep;
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use clap::Parser;
+
+use cat_reminder::cli::Cli;
+use cat_reminder::clock::{Clock, ScaledClock};
+
+#[test]
+fn scaled_clocks_run_faster_from_now_on() {
+    let before = Utc::now();
+    let clock = ScaledClock::new(1000.0);
+    let start = clock.now();
+    assert!(start >= before && start - before < Duration::seconds(1));
+    sleep(StdDuration::from_millis(50));
+    assert!(clock.now() - start >= Duration::seconds(50));
+}
+
+#[test]
+fn time_scales_are_positive_numbers() {
+    let time_scale = |value: &str| Cli::try_parse_from(["cat-litter-reminder", "--time-scale", value]).map(|cli| cli.time_scale);
+    assert_eq!(time_scale("1440").unwrap(), Some(1440.0));
+    assert_eq!(time_scale("0.5").unwrap(), Some(0.5));
+    for invalid in ["0", "-2", "inf", "NaN", "fast"] {
+        assert!(time_scale(invalid).is_err(), "{} was accepted", invalid);
+    }
+}
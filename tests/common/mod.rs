@@ -0,0 +1,227 @@
+//! Runs several reminder engines in one process, with simulated clocks and strips recording what they would show.
+//! The nodes talk to each other through their real transports on loopback addresses. mDNS is left out: the harness
+//! tells each transport which nodes it can reach, which also allows partitioning the network.
+
+// every test uses a different part of the harness
+#![allow(dead_code)]
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, mpsc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::{sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use cat_reminder::clock::SimulatedClock;
+use cat_reminder::display::{Display, DisplayState};
+use cat_reminder::notifier::NotifierEvent;
+use cat_reminder::protocol::Encoding;
+use cat_reminder::reminder::{LEDStripState, Reminder, ReminderEvent, ReminderStatus};
+use cat_reminder::state::{BoxState, State, DEFAULT_BOX};
+use cat_reminder::storage::StorageEvent;
+use cat_reminder::transport::{self, NodeRole, TransportEvent, Zoning};
+use cat_reminder::websocket::StreamEvent;
+
+const STEP_DELAY: Duration = Duration::from_millis(50);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Noon in Vienna, so that the strips are not dark for the night.
+pub fn start_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap()
+}
+
+/// Records the states shown on the strip, leaving out repetitions.
+struct RecordingStrip {
+    states: Arc<Mutex<Vec<LEDStripState>>>
+}
+
+impl Display for RecordingStrip {
+    fn name(&self) -> &str {
+        "recording strip"
+    }
+
+    fn render(&mut self, state: &DisplayState) {
+        let mut states = self.states.lock().unwrap();
+        if states.last() != Some(&state.status.state) {
+            states.push(state.status.state);
+        }
+    }
+}
+
+pub struct TestNode {
+    pub name: String,
+    pub ip: Ipv4Addr,
+    pub clock: Arc<SimulatedClock>,
+    pub reminder_tx: Sender<ReminderEvent>,
+    pub status: Arc<RwLock<ReminderStatus>>,
+    /// States shown on the strip so far
+    pub strip: Arc<Mutex<Vec<LEDStripState>>>,
+    reminder: Reminder,
+    transport_tx: Sender<TransportEvent>,
+    shutdown_flag: Arc<AtomicBool>,
+    transport: Option<JoinHandle<()>>,
+    // kept so that the reminder does not log closed channels
+    _storage_rx: Receiver<StorageEvent>,
+    _notifier_rx: Receiver<NotifierEvent>,
+    _event_rx: Receiver<StreamEvent>
+}
+
+impl TestNode {
+    fn start(index: usize, port: u16, last_cleaning_time: DateTime<Utc>, zoning: Zoning) -> TestNode {
+        let name = format!("node-{}", index);
+        let ip = Ipv4Addr::new(127, 0, 0, 10 + index as u8);
+        let clock = Arc::new(SimulatedClock::new(start_time()));
+        let shutdown_flag = Arc::new(AtomicBool::new(false));
+        let (reminder_tx, reminder_rx) = mpsc::channel();
+        let (transport_tx, transport_rx) = mpsc::channel();
+        let (storage_tx, storage_rx) = mpsc::channel();
+        let (notifier_tx, notifier_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+
+        let transport = transport::run(IpAddr::V4(ip), port, reminder_tx.clone(), event_tx.clone(), Arc::new(Mutex::new(transport_rx)),
+            last_cleaning_time, Encoding::default(), zoning, NodeRole::Full, shutdown_flag.clone());
+
+        let state = State {
+            version: 1,
+            node_id: name.clone(),
+            boxes: BTreeMap::from([(DEFAULT_BOX.to_string(), BoxState { last_cleaning_time, last_cleaned_by: None, last_reset_source: None,
+                litter: None, last_full_change: None })]),
+            paused: false,
+            snoozed_until: None,
+            snoozed_at: None,
+            guest_until: None,
+            rotation_index: None,
+            bag: None,
+            care: BTreeMap::new()
+        };
+        let status = Arc::new(RwLock::new(ReminderStatus { last_cleaning_time, state: LEDStripState::LightGreen,
+            due_time: last_cleaning_time + LEDStripState::Red.starts_after(), assignee: None, climate: None,
+            cpu_temperature: None, snoozed_until: None, quiet: false, guest_until: None, in_progress_since: None, litter: Default::default(), next_full_change: None, bag_runs_out: None,
+            care_due: Vec::new(), zones: BTreeMap::new(), failing: BTreeSet::new(), scoops: None }));
+        let strip = Arc::new(Mutex::new(Vec::new()));
+        let reminder = Reminder {
+            displays: vec![Box::new(RecordingStrip { states: strip.clone() })],
+            storage_tx, state, reminder_rx, transport_tx: transport_tx.clone(), notifier_tx, event_tx, last_cleaning_time, last_state: None,
+            status: status.clone(), peer_count: Arc::new(AtomicUsize::new(0)), members: Vec::new(), rotation_index: 0, visits: 0,
+            visits_per_escalation: None, smell: None, blending: Default::default(), climate: None, ambient_light: None, cpu_temperature: None, throttling: None,
+            on_battery: false, nobody_home: false, snoozed_until: None, guest_until: None, snooze_selection: None, selected_box: None, reset_armed: false,
+            full_change_due: false, supplies: None, supplies_low: false, care: Default::default(), animation: Default::default(), overnight_states: None, in_progress_since: None, failing_subsystems: BTreeSet::new(), scoops: Vec::new(), holidays: Default::default(), clock: clock.clone(),
+            demo_since: None, dry_run: false
+        };
+
+        TestNode {
+            name, ip, clock, reminder_tx, status, strip, reminder, transport_tx, shutdown_flag, transport: Some(transport),
+            _storage_rx: storage_rx, _notifier_rx: notifier_rx, _event_rx: event_rx
+        }
+    }
+
+    pub fn last_cleaning_time(&self) -> DateTime<Utc> {
+        self.status.read().unwrap().last_cleaning_time
+    }
+}
+
+/// Nodes that reach each other over loopback addresses.
+pub struct Cluster {
+    pub nodes: Vec<TestNode>
+}
+
+impl Cluster {
+    /// Starts the nodes, all of them cleaned at the given time, without letting them know about each other.
+    pub fn start(count: usize, port: u16, last_cleaning_time: DateTime<Utc>) -> Cluster {
+        Cluster { nodes: (0..count).map(|index| TestNode::start(index, port, last_cleaning_time, Zoning::default())).collect() }
+    }
+
+    /// Starts a node in each of the given zones and lets each of them reach all the others, as hubs would through mDNS.
+    pub fn zoned(zonings: &[Zoning], port: u16) -> Cluster {
+        let mut cluster = Cluster { nodes: zonings.iter().enumerate().map(|(index, zoning)| TestNode::start(index, port, start_time(), zoning.clone())).collect() };
+        cluster.heal();
+        cluster
+    }
+
+    /// Starts the nodes and lets each of them reach all the others.
+    pub fn connected(count: usize, port: u16) -> Cluster {
+        let mut cluster = Cluster::start(count, port, start_time());
+        cluster.heal();
+        cluster
+    }
+
+    /// Tells a node which other nodes it can reach, as mDNS would.
+    pub fn reach(&self, node: usize, others: &[usize]) {
+        let list: HashMap<String, Vec<Ipv4Addr>> = others.iter()
+            .filter(|other| **other != node)
+            .map(|other| (self.nodes[*other].name.clone(), vec![self.nodes[*other].ip]))
+            .collect();
+        self.announce(node, list);
+    }
+
+    /// Tells a node which nodes it can reach, including nodes outside of the cluster.
+    pub fn announce(&self, node: usize, list: HashMap<String, Vec<Ipv4Addr>>) {
+        self.nodes[node].transport_tx.send(TransportEvent::NodeListUpdated(list)).unwrap();
+    }
+
+    /// Splits the network, the nodes only reaching the other nodes of their group.
+    pub fn partition(&mut self, groups: &[&[usize]]) {
+        for group in groups {
+            for node in group.iter() {
+                self.reach(*node, group);
+            }
+        }
+        self.settle();
+    }
+
+    /// Lets every node reach all the others.
+    pub fn heal(&mut self) {
+        let all: Vec<usize> = (0..self.nodes.len()).collect();
+        self.partition(&[&all]);
+    }
+
+    /// Advances the simulated clocks of all nodes.
+    pub fn advance(&self, duration: chrono::Duration) {
+        self.nodes.iter().for_each(|node| node.clock.advance(duration));
+    }
+
+    /// Steps the reminders until the condition holds, returning false if it does not within a few seconds.
+    pub fn step_until(&mut self, condition: impl Fn(&Cluster) -> bool) -> bool {
+        let deadline = Instant::now() + TIMEOUT;
+        while Instant::now() < deadline {
+            self.step();
+            if condition(self) {
+                return true;
+            }
+            sleep(STEP_DELAY);
+        }
+        false
+    }
+
+    /// Steps the reminders long enough for the transports to handle the node lists and the messages in flight.
+    pub fn settle(&mut self) {
+        let deadline = Instant::now() + Duration::from_millis(1500);
+        while Instant::now() < deadline {
+            self.step();
+            sleep(STEP_DELAY);
+        }
+    }
+
+    fn step(&mut self) {
+        self.nodes.iter_mut().for_each(|node| {
+            node.reminder.step();
+        });
+    }
+
+    /// Whether all nodes agree on the time of the last cleaning, to the second as exchanged between the nodes.
+    pub fn agrees_on(&self, time: DateTime<Utc>) -> bool {
+        self.nodes.iter().all(|node| node.last_cleaning_time().timestamp() == time.timestamp())
+    }
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) {
+        self.nodes.iter().for_each(|node| node.shutdown_flag.store(true, Ordering::Relaxed));
+        self.nodes.iter_mut().filter_map(|node| node.transport.take()).for_each(|transport| {
+            let _ = transport.join();
+        });
+    }
+}
@@ -0,0 +1,105 @@
+//! Conformance of the full nodes with the companion profile, as implemented on microcontrollers. The frames are built
+//! and parsed by hand, as a companion node would, rather than with the protocol of the crate.
+
+mod common;
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use chrono::Duration as ChronoDuration;
+use serde_json::{json, Value};
+
+use cat_reminder::reminder::{LEDStripState, ReminderEvent, ResetSource};
+
+use common::{start_time, Cluster};
+
+const PORT: u16 = 15305;
+
+/// A companion node, listening on the transport port like the full nodes.
+struct Companion {
+    socket: UdpSocket,
+    node: SocketAddr
+}
+
+impl Companion {
+    fn start() -> Companion {
+        let socket = UdpSocket::bind((Ipv4Addr::new(127, 0, 0, 20), PORT)).unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        Companion { socket, node: SocketAddr::from((Ipv4Addr::new(127, 0, 0, 10), PORT)) }
+    }
+
+    fn send(&self, message: Value) {
+        let payload = message.to_string().into_bytes();
+        let mut frame = b"CATR".to_vec();
+        frame.push(1); // protocol version
+        frame.push(1); // JSON
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&payload);
+        frame.extend_from_slice(&crc32fast::hash(&frame).to_be_bytes());
+        self.socket.send_to(&frame, self.node).unwrap();
+    }
+
+    fn receive(&self) -> Value {
+        let mut buffer = [0u8; 2048];
+        let (length, _) = self.socket.recv_from(&mut buffer).unwrap();
+        let frame = &buffer[..length];
+        assert_eq!(&frame[..4], b"CATR");
+        assert_eq!(frame[4], 1, "protocol version");
+        assert_eq!(frame[5], 1, "companions are answered in JSON");
+        let payload_length = u32::from_be_bytes([frame[6], frame[7], frame[8], frame[9]]) as usize;
+        assert_eq!(length, 10 + payload_length + 4);
+        assert_eq!(crc32fast::hash(&frame[..10 + payload_length]).to_be_bytes(), frame[10 + payload_length..]);
+        serde_json::from_slice(&frame[10..10 + payload_length]).unwrap()
+    }
+
+    /// Receives messages until a state update, answering state requests and skipping the messages companions may ignore
+    /// on the way.
+    fn receive_update(&self) -> Value {
+        loop {
+            match self.receive() {
+                Value::String(request) if request == "RequestState" => self.send(json!({"UpdateState": [null, null, null]})),
+                Value::Object(mut update) if update.contains_key("UpdateState") => return update.remove("UpdateState").unwrap(),
+                Value::Object(other) if other.contains_key("DueTime") || other.contains_key("Snooze") => (),
+                other => panic!("Unexpected message {}", other)
+            }
+        }
+    }
+}
+
+#[test]
+fn full_nodes_serve_companion_nodes() {
+    let mut cluster = Cluster::start(1, PORT, start_time());
+    let companion = Companion::start();
+
+    companion.send(json!("RequestState"));
+    assert_eq!(companion.receive_update()[0], json!(start_time().timestamp()));
+
+    companion.send(json!("Heartbeat"));
+    assert_eq!(companion.receive_update()[0], json!(start_time().timestamp()));
+    let due_time = start_time() + LEDStripState::Red.starts_after();
+    assert_eq!(companion.receive(), json!({"DueTime": due_time.timestamp()}));
+
+    // the litter box is not due before the snooze ends
+    let snoozed_until = start_time() + ChronoDuration::days(2);
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::Snooze(ChronoDuration::days(2), None)).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[0].status.read().unwrap().due_time == snoozed_until));
+    companion.send(json!("Heartbeat"));
+    assert_eq!(companion.receive_update()[0], json!(start_time().timestamp()));
+    assert_eq!(companion.receive(), json!({"DueTime": snoozed_until.timestamp()}));
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::Snooze(ChronoDuration::zero(), None)).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[0].status.read().unwrap().due_time == due_time));
+
+    cluster.announce(0, HashMap::from([("companion".to_string(), vec![Ipv4Addr::new(127, 0, 0, 20)])]));
+    cluster.settle();
+    cluster.advance(ChronoDuration::hours(1));
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, Some("Anna".to_string()))).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[0].last_cleaning_time() == start_time() + ChronoDuration::hours(1)));
+    let update = companion.receive_update();
+    assert_eq!(update[0], json!((start_time() + ChronoDuration::hours(1)).timestamp()));
+    assert_eq!(update[1], json!("Anna"));
+
+    let reset_on_companion = start_time() + ChronoDuration::hours(2);
+    companion.send(json!({"UpdateState": [reset_on_companion.timestamp(), null, "companion"]}));
+    assert!(cluster.step_until(|c| c.nodes[0].last_cleaning_time() == reset_on_companion));
+}
@@ -0,0 +1,38 @@
+use chrono::{Duration, NaiveDate};
+
+use cat_reminder::holiday::{parse_ics, Holidays, HolidaysConfig, Trip};
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 7, day).unwrap()
+}
+
+#[test]
+fn calendar_events_become_trips() {
+    let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Beach\r\nDTSTART;VALUE=DATE:20240701\r\nDTEND;VALUE=DATE:20240708\r\nEND:VEVENT\r\n\
+               BEGIN:VEVENT\r\nDTSTART:20240712T080000Z\r\nDTEND:20240714T180000Z\r\nEND:VEVENT\r\n\
+               BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20240720\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    assert_eq!(parse_ics(ics), vec![
+        Trip { start: date(1), end: date(7) },
+        Trip { start: date(12), end: date(14) },
+        Trip { start: date(20), end: date(20) }
+    ]);
+}
+
+#[test]
+fn folded_lines_are_unfolded() {
+    let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:A long trip to the seaside with a descr\r\n iption folded onto the next line\r\n\
+               DTSTART;VALUE=DATE:202407\r\n 01\r\nDTEND;VALUE=\r\n\tDATE:20240708\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    assert_eq!(parse_ics(ics), vec![Trip { start: date(1), end: date(7) }]);
+}
+
+#[test]
+fn thresholds_are_relaxed_during_trips() {
+    let config = HolidaysConfig { trips: vec![Trip { start: date(1), end: date(7) }], threshold_factor: 2.0, ..HolidaysConfig::default() };
+    let mut holidays = Holidays::new(config);
+    assert_eq!(holidays.update(date(1)), Some(true));
+    assert_eq!(holidays.update(date(7)), None);
+    assert_eq!(holidays.relaxed(Duration::hours(12)), Duration::hours(6));
+    assert_eq!(holidays.unrelaxed(Duration::hours(6)), Duration::hours(12));
+    assert_eq!(holidays.update(date(8)), Some(false));
+    assert_eq!(holidays.relaxed(Duration::hours(12)), Duration::hours(12));
+}
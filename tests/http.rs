@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+
+use cat_reminder::http::{escape_text, ApiToken, Authorizer, Denial, Role};
+
+fn api_token(name: &str, role: Role, max_per_minute: usize) -> ApiToken {
+    ApiToken { name: name.to_string(), token: format!("{}-token", name), role, max_per_minute }
+}
+
+fn authorizer() -> Authorizer {
+    Authorizer::new(vec![api_token("phone", Role::Viewer, 30), api_token("tablet", Role::Resetter, 30), api_token("laptop", Role::Admin, 30)])
+}
+
+#[test]
+fn unknown_or_missing_tokens_are_unauthorized() {
+    let mut authorizer = authorizer();
+    let now = Instant::now();
+    assert_eq!(authorizer.authorize(&[], Role::Viewer, "/status", now).err(), Some(Denial::Unauthorized));
+    assert_eq!(authorizer.authorize(&["guessed"], Role::Viewer, "/status", now).err(), Some(Denial::Unauthorized));
+    assert_eq!(authorizer.authorize(&["phone-toke"], Role::Viewer, "/status", now).err(), Some(Denial::Unauthorized));
+}
+
+#[test]
+fn roles_allow_what_the_previous_ones_do() {
+    let mut authorizer = authorizer();
+    let now = Instant::now();
+    assert_eq!(authorizer.authorize(&["phone-token"], Role::Viewer, "/status", now).map(|t| t.name.clone()), Ok("phone".to_string()));
+    assert_eq!(authorizer.authorize(&["phone-token"], Role::Resetter, "/reset", now).err(), Some(Denial::Forbidden));
+    assert!(authorizer.authorize(&["tablet-token"], Role::Viewer, "/status", now).is_ok());
+    assert!(authorizer.authorize(&["tablet-token"], Role::Resetter, "/reset", now).is_ok());
+    assert_eq!(authorizer.authorize(&["tablet-token"], Role::Admin, "/diagnostics", now).err(), Some(Denial::Forbidden));
+    assert!(authorizer.authorize(&["laptop-token"], Role::Admin, "/diagnostics", now).is_ok());
+}
+
+#[test]
+fn requests_above_the_rate_limit_are_refused_for_a_minute() {
+    let mut authorizer = Authorizer::new(vec![api_token("phone", Role::Viewer, 2), api_token("tablet", Role::Viewer, 2)]);
+    let start = Instant::now();
+    assert!(authorizer.authorize(&["phone-token"], Role::Viewer, "/status", start).is_ok());
+    assert!(authorizer.authorize(&["phone-token"], Role::Viewer, "/status", start + Duration::from_secs(10)).is_ok());
+    assert_eq!(authorizer.authorize(&["phone-token"], Role::Viewer, "/status", start + Duration::from_secs(20)).err(), Some(Denial::TooManyRequests));
+    // the limit is per token
+    assert!(authorizer.authorize(&["tablet-token"], Role::Viewer, "/status", start + Duration::from_secs(20)).is_ok());
+    assert!(authorizer.authorize(&["phone-token"], Role::Viewer, "/status", start + Duration::from_secs(61)).is_ok());
+    assert_eq!(authorizer.authorize(&["phone-token"], Role::Viewer, "/status", start + Duration::from_secs(65)).err(), Some(Denial::TooManyRequests));
+}
+
+#[test]
+fn calendar_texts_are_escaped() {
+    assert_eq!(escape_text("Litter box cleaned by Anna"), "Litter box cleaned by Anna");
+    assert_eq!(escape_text("Anna, Ben; and \\o/"), "Anna\\, Ben\\; and \\\\o/");
+    assert_eq!(escape_text("Anna\r\nBen"), "Anna\\nBen");
+}
@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+use cat_reminder::input::{default_inputs, InputConfig, InputState, Transition};
+
+fn button() -> InputConfig {
+    default_inputs().remove(0)
+}
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn short_presses_do_not_trigger_held_inputs() {
+    let config = InputConfig { hold_ms: 2000, ..button() };
+    let mut state = InputState::default();
+    let start = Instant::now();
+    assert_eq!(state.poll(&config, true, start), None);
+    assert_eq!(state.poll(&config, true, start + ms(1500)), None);
+    assert_eq!(state.poll(&config, false, start + ms(1600)), None);
+
+    // the hold time starts over with the next press
+    assert_eq!(state.poll(&config, true, start + ms(3000)), None);
+    assert_eq!(state.poll(&config, true, start + ms(4900)), None);
+    assert_eq!(state.poll(&config, true, start + ms(5000)), Some(Transition::Triggered));
+    assert_eq!(state.poll(&config, true, start + ms(9000)), None);
+}
+
+#[test]
+fn armed_resets_are_confirmed_by_a_second_press() {
+    let config = InputConfig { confirm: true, confirm_seconds: 5, ..button() };
+    let mut state = InputState::default();
+    let start = Instant::now();
+    assert_eq!(state.poll(&config, true, start), Some(Transition::Armed));
+    assert_eq!(state.poll(&config, true, start + ms(100)), None);
+    assert_eq!(state.poll(&config, false, start + ms(200)), None);
+    assert!(!state.expire(&config, start + ms(2000)));
+    assert_eq!(state.poll(&config, true, start + ms(2000)), Some(Transition::Triggered));
+    assert_eq!(state.poll(&config, false, start + ms(2100)), None);
+
+    // the confirmed reset does not stay armed
+    assert!(!state.expire(&config, start + ms(10_000)));
+    assert_eq!(state.poll(&config, true, start + ms(10_000)), Some(Transition::Armed));
+}
+
+#[test]
+fn armed_resets_time_out() {
+    let config = InputConfig { confirm: true, confirm_seconds: 5, ..button() };
+    let mut state = InputState::default();
+    let start = Instant::now();
+    assert_eq!(state.poll(&config, true, start), Some(Transition::Armed));
+    assert_eq!(state.poll(&config, false, start + ms(200)), None);
+    assert!(!state.expire(&config, start + ms(4900)));
+    assert!(state.expire(&config, start + ms(5000)));
+    assert!(!state.expire(&config, start + ms(5100)));
+
+    // the next press only arms the reset again
+    assert_eq!(state.poll(&config, true, start + ms(6000)), Some(Transition::Armed));
+}
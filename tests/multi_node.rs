@@ -0,0 +1,218 @@
+mod common;
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration as StdDuration;
+
+use chrono::Duration;
+
+use cat_reminder::Protocol;
+use cat_reminder::reminder::{LEDStripState, ReminderEvent, ResetSource};
+use cat_reminder::transport::Zoning;
+
+use common::{start_time, Cluster};
+
+#[test]
+fn reset_propagates_to_all_nodes() {
+    let mut cluster = Cluster::connected(3, 15301);
+    cluster.advance(Duration::hours(2));
+
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, None)).unwrap();
+
+    let reset_time = start_time() + Duration::hours(2);
+    assert!(cluster.step_until(|c| c.agrees_on(reset_time)));
+}
+
+#[test]
+fn joining_node_requests_the_state() {
+    let mut cluster = Cluster::start(2, 15302, start_time());
+
+    // only the joining node learns about the other one, which answers its request
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::CleaningTimeSet(start_time() - Duration::hours(3), ResetSource::Button, None)).unwrap();
+    cluster.settle();
+    cluster.reach(1, &[0]);
+
+    assert!(cluster.step_until(|c| c.agrees_on(start_time() - Duration::hours(3))));
+}
+
+#[test]
+fn partitions_reconcile_when_healed() {
+    let mut cluster = Cluster::connected(4, 15303);
+    cluster.partition(&[&[0, 1], &[2, 3]]);
+    cluster.advance(Duration::hours(1));
+
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, Some("Anna".to_string()))).unwrap();
+    let reset_time = start_time() + Duration::hours(1);
+    assert!(cluster.step_until(|c| c.nodes[1].last_cleaning_time() == reset_time));
+    cluster.settle();
+    assert_eq!(cluster.nodes[2].last_cleaning_time(), start_time());
+    assert_eq!(cluster.nodes[3].last_cleaning_time(), start_time());
+
+    cluster.heal();
+
+    assert!(cluster.step_until(|c| c.agrees_on(reset_time)));
+}
+
+#[test]
+fn corrected_cleaning_time_propagates_and_sticks() {
+    let mut cluster = Cluster::connected(3, 15308);
+    cluster.advance(Duration::hours(2));
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, None)).unwrap();
+    assert!(cluster.step_until(|c| c.agrees_on(start_time() + Duration::hours(2))));
+
+    // the third node misses the correction, and pushes the reset it knows of once it is back
+    cluster.partition(&[&[0, 1], &[2]]);
+    cluster.advance(Duration::minutes(30));
+    let corrected_time = start_time() + Duration::hours(1);
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::CleaningTimeSet(corrected_time, ResetSource::Control, None)).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[1].last_cleaning_time() == corrected_time));
+    cluster.heal();
+
+    assert!(cluster.step_until(|c| c.agrees_on(corrected_time)));
+    cluster.settle();
+    assert!(cluster.agrees_on(corrected_time));
+}
+
+#[test]
+fn strip_escalates_with_simulated_time() {
+    let mut cluster = Cluster::start(1, 15304, start_time());
+    assert!(cluster.step_until(|c| c.nodes[0].strip.lock().unwrap().last() == Some(&LEDStripState::LightGreen)));
+
+    cluster.advance(LEDStripState::Red.starts_after());
+
+    assert!(cluster.step_until(|c| c.nodes[0].strip.lock().unwrap().last() == Some(&LEDStripState::Red)));
+    assert!(!cluster.nodes[0].strip.lock().unwrap().contains(&LEDStripState::BlinkingRed));
+}
+
+#[test]
+fn guest_mode_holds_the_strip_at_red() {
+    let mut cluster = Cluster::start(1, 15305, start_time());
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::GuestMode(Duration::hours(4))).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[0].status.read().unwrap().guest_until.is_some()));
+
+    cluster.advance(LEDStripState::BlinkingRed.starts_after());
+
+    assert!(cluster.step_until(|c| c.nodes[0].strip.lock().unwrap().last() == Some(&LEDStripState::Red)));
+    assert!(!cluster.nodes[0].strip.lock().unwrap().contains(&LEDStripState::BlinkingRed));
+
+    cluster.advance(Duration::hours(4));
+
+    assert!(cluster.step_until(|c| c.nodes[0].strip.lock().unwrap().last() == Some(&LEDStripState::BlinkingRed)));
+    assert_eq!(cluster.nodes[0].last_cleaning_time(), start_time());
+}
+
+#[test]
+fn zones_keep_their_own_cleaning_time_while_the_hub_shows_all() {
+    let zone = |name: &str, hub: bool| Zoning { zone: Some(name.to_string()), hub };
+    let mut cluster = Cluster::zoned(&[zone("upstairs", false), zone("downstairs", false), zone("downstairs", true)], 15306);
+    cluster.advance(LEDStripState::Red.starts_after());
+
+    cluster.nodes[1].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, None)).unwrap();
+
+    let reset_time = start_time() + LEDStripState::Red.starts_after();
+    assert!(cluster.step_until(|c| c.nodes[2].last_cleaning_time().timestamp() == reset_time.timestamp()));
+    assert_eq!(cluster.nodes[0].last_cleaning_time(), start_time());
+
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, None)).unwrap();
+
+    assert!(cluster.step_until(|c| c.nodes[2].status.read().unwrap().zones.get("upstairs") == Some(&LEDStripState::LightGreen)));
+    assert_eq!(cluster.nodes[1].last_cleaning_time().timestamp(), reset_time.timestamp());
+    assert_eq!(cluster.nodes[2].last_cleaning_time().timestamp(), reset_time.timestamp());
+}
+
+#[test]
+fn running_node_takes_the_snooze_of_a_joining_node() {
+    let mut cluster = Cluster::start(2, 15309, start_time());
+
+    // the second node stays up and is only reached by the first one, which snoozed while it was on its own
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::Snooze(Duration::hours(1), None)).unwrap();
+    cluster.settle();
+    cluster.reach(0, &[1]);
+
+    let until = start_time() + Duration::hours(1);
+    assert!(cluster.step_until(|c| c.nodes[1].status.read().unwrap().snoozed_until.is_some_and(|time| time.timestamp() == until.timestamp())));
+}
+
+#[test]
+fn joining_node_takes_the_running_snooze() {
+    let mut cluster = Cluster::start(2, 15307, start_time());
+
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::Snooze(Duration::hours(1), None)).unwrap();
+    cluster.settle();
+    cluster.reach(1, &[0]);
+
+    let until = start_time() + Duration::hours(1);
+    assert!(cluster.step_until(|c| c.nodes[1].status.read().unwrap().snoozed_until.is_some_and(|time| time.timestamp() == until.timestamp())));
+}
+
+/// The messages received on the socket until none arrives for a while.
+fn received(socket: &UdpSocket) -> Vec<Protocol> {
+    let mut buffer = [0u8; 2048];
+    let mut messages = Vec::new();
+    while let Ok((length, _)) = socket.recv_from(&mut buffer) {
+        messages.extend(Protocol::decode(&buffer[..length]).ok());
+    }
+    messages
+}
+
+#[test]
+fn nodes_going_down_are_left_out() {
+    let mut cluster = Cluster::start(1, 15310, start_time());
+    let peer = UdpSocket::bind((Ipv4Addr::new(127, 0, 0, 30), 15310)).unwrap();
+    peer.set_read_timeout(Some(StdDuration::from_millis(500))).unwrap();
+    let node = SocketAddr::from((cluster.nodes[0].ip, 15310));
+    cluster.announce(0, HashMap::from([("peer".to_string(), vec![Ipv4Addr::new(127, 0, 0, 30)])]));
+    cluster.settle();
+    received(&peer);
+
+    cluster.advance(Duration::hours(1));
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, None)).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[0].last_cleaning_time() == start_time() + Duration::hours(1)));
+    cluster.settle();
+    assert!(received(&peer).iter().any(|message| matches!(message, Protocol::UpdateState(..))));
+
+    peer.send_to(&Protocol::GoingDown.encode().unwrap(), node).unwrap();
+    cluster.settle();
+    cluster.advance(Duration::hours(1));
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, None)).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[0].last_cleaning_time() == start_time() + Duration::hours(2)));
+    cluster.settle();
+    assert!(!received(&peer).iter().any(|message| matches!(message, Protocol::UpdateState(..))));
+}
+
+#[test]
+fn nobody_home_quiets_the_alarms() {
+    let mut cluster = Cluster::start(1, 15311, start_time());
+    cluster.advance(LEDStripState::BlinkingRed.starts_after());
+    assert!(cluster.step_until(|c| c.nodes[0].status.read().unwrap().state == LEDStripState::BlinkingRed));
+    assert!(!cluster.nodes[0].status.read().unwrap().quiet);
+
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::PresenceChanged(false)).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[0].status.read().unwrap().quiet));
+    // the litter box is still overdue, only the lamp and the sounds leave it be
+    assert_eq!(cluster.nodes[0].status.read().unwrap().state, LEDStripState::BlinkingRed);
+
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::PresenceChanged(true)).unwrap();
+    assert!(cluster.step_until(|c| !c.nodes[0].status.read().unwrap().quiet));
+}
+
+#[test]
+fn hubs_reset_the_box_selected_with_an_input() {
+    let zone = |name: &str, hub: bool| Zoning { zone: Some(name.to_string()), hub };
+    let mut cluster = Cluster::zoned(&[zone("upstairs", false), zone("downstairs", true)], 15312);
+    cluster.advance(LEDStripState::Red.starts_after());
+    cluster.nodes[0].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, None)).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[1].status.read().unwrap().zones.get("upstairs") == Some(&LEDStripState::LightGreen)));
+
+    cluster.advance(LEDStripState::Red.starts_after());
+    cluster.nodes[1].reminder_tx.send(ReminderEvent::SelectNextBox).unwrap();
+    cluster.nodes[1].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, None)).unwrap();
+
+    let reset_time = start_time() + LEDStripState::Red.starts_after() * 2;
+    assert!(cluster.step_until(|c| c.nodes[0].last_cleaning_time().timestamp() == reset_time.timestamp()));
+    assert_eq!(cluster.nodes[1].last_cleaning_time(), start_time());
+
+    // the selection only holds for one reset
+    cluster.nodes[1].reminder_tx.send(ReminderEvent::ResetRequested(ResetSource::Button, None)).unwrap();
+    assert!(cluster.step_until(|c| c.nodes[1].last_cleaning_time().timestamp() == reset_time.timestamp()));
+}
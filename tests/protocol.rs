@@ -0,0 +1,53 @@
+use chrono::{TimeZone, Utc};
+
+use cat_reminder::Protocol;
+use cat_reminder::protocol::{Encoding, PROTOCOL_VERSION};
+
+fn update() -> Protocol {
+    Protocol::UpdateState(Some(Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap()), Some("Anna".to_string()), Some("kitchen".to_string()))
+}
+
+#[test]
+fn frames_round_trip() {
+    let zone_update = Protocol::ZoneState(Some("upstairs".to_string()), Some(Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap()), None, Some("attic".to_string()));
+    let due_time = Protocol::DueTime(Utc.with_ymd_and_hms(2024, 6, 2, 16, 0, 0).unwrap());
+    let snooze = Protocol::Snooze(Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap(), Some(Utc.with_ymd_and_hms(2024, 6, 1, 11, 0, 0).unwrap()));
+    let pause = Protocol::Snooze(Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap(), None);
+    let correction = Protocol::CorrectedState(None, Utc.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap(), Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap(),
+        None, Some("kitchen".to_string()));
+    for message in [Protocol::RequestState, update(), Protocol::GoingDown, Protocol::Heartbeat, Protocol::CheckForUpdate, zone_update, due_time, snooze, pause, correction] {
+        assert_eq!(Protocol::decode(&message.encode().unwrap()).unwrap(), message);
+    }
+}
+
+#[test]
+fn json_frames_round_trip() {
+    for message in [Protocol::RequestState, update(), Protocol::GoingDown] {
+        let frame = message.encode_as(Encoding::Json).unwrap();
+        assert_eq!(Protocol::decode_with_encoding(&frame).unwrap(), (message, Encoding::Json));
+    }
+    let frame = update().encode_as(Encoding::Json).unwrap();
+    assert_eq!(&frame[10..frame.len() - 4], br#"{"UpdateState":[1717236000,"Anna","kitchen"]}"#);
+}
+
+#[test]
+fn corrupted_frames_are_rejected() {
+    let frame = update().encode().unwrap();
+    for index in 0..frame.len() {
+        let mut corrupted = frame.clone();
+        corrupted[index] ^= 0x01;
+        assert!(Protocol::decode(&corrupted).is_err(), "flipped bit in byte {} went unnoticed", index);
+    }
+    assert!(Protocol::decode(&frame[..frame.len() - 1]).is_err());
+    assert!(Protocol::decode(&[frame.as_slice(), &[0]].concat()).is_err());
+}
+
+#[test]
+fn unframed_and_other_versions_are_rejected() {
+    assert!(Protocol::decode(&bincode::serialize(&update()).unwrap()).is_err());
+    assert!(Protocol::decode(b"").is_err());
+
+    let mut frame = update().encode().unwrap();
+    frame[4] = PROTOCOL_VERSION + 1;
+    assert!(Protocol::decode(&frame).is_err());
+}
@@ -0,0 +1,26 @@
+use std::fs;
+
+use chrono::{Duration, TimeZone, Utc};
+
+use cat_reminder::history::{HistoryEntry, HistoryEvent};
+use cat_reminder::replay;
+use cat_reminder::reminder::LEDStripState;
+
+#[test]
+fn replays_point_out_the_states_recorded_differently() {
+    let noon = Utc.with_ymd_and_hms(2024, 6, 4, 10, 0, 0).unwrap();
+    let entries = [
+        HistoryEntry { by: Some("Anna".to_string()), ..HistoryEntry::new(noon, HistoryEvent::Cleaned) },
+        HistoryEntry::new(noon, HistoryEvent::StateChanged(LEDStripState::LightGreen)),
+        HistoryEntry::new(noon + Duration::hours(1), HistoryEvent::StateChanged(LEDStripState::BlinkingRed)),
+        // the thresholds of the schedule are long past by then
+        HistoryEntry::new(noon + Duration::hours(2), HistoryEvent::StateChanged(LEDStripState::LightGreen))
+    ];
+    let file = std::env::temp_dir().join(format!("cat-reminder-replay-{}", std::process::id()));
+    let lines: Vec<String> = entries.iter().map(|entry| serde_json::to_string(entry).unwrap()).collect();
+    fs::write(&file, lines.join("\n")).unwrap();
+
+    let differences = replay::run(Some(&file), None).unwrap();
+    fs::remove_file(&file).unwrap();
+    assert_eq!(differences, 1);
+}
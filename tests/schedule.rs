@@ -0,0 +1,42 @@
+use chrono::{Duration, NaiveTime, TimeZone, Utc};
+
+use cat_reminder::config::{OvernightConfig, ScheduleConfig};
+use cat_reminder::state::DEFAULT_BOX;
+
+fn schedule(factor: f64) -> ScheduleConfig {
+    let overnight = OvernightConfig { start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(), end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(), factor };
+    ScheduleConfig { overnight: Some(overnight), ..ScheduleConfig::default() }
+}
+
+#[test]
+fn overnight_hours_count_by_their_factor() {
+    let schedule = schedule(0.5);
+    let evening = schedule.timezone.with_ymd_and_hms(2024, 1, 10, 20, 0, 0).unwrap().with_timezone(&Utc);
+    let morning = schedule.timezone.with_ymd_and_hms(2024, 1, 11, 9, 0, 0).unwrap().with_timezone(&Utc);
+    // 4 hours of the day counted fully, 9 hours of the night counted half
+    assert_eq!(schedule.urgency_elapsed(evening, morning), Duration::minutes(8 * 60 + 30));
+}
+
+#[test]
+fn cleanings_during_the_night_only_count_the_rest_of_it() {
+    let schedule = schedule(0.0);
+    let night = schedule.timezone.with_ymd_and_hms(2024, 1, 11, 2, 0, 0).unwrap().with_timezone(&Utc);
+    let morning = schedule.timezone.with_ymd_and_hms(2024, 1, 11, 8, 0, 0).unwrap().with_timezone(&Utc);
+    assert_eq!(schedule.urgency_elapsed(night, morning), Duration::hours(1));
+    assert_eq!(ScheduleConfig::default().urgency_elapsed(night, morning), Duration::hours(6));
+}
+
+#[test]
+fn due_times_leave_out_the_overnight_hours() {
+    let hours = [8, 12, 24, 26].map(|hours| hours * 3600);
+    let schedule = ScheduleConfig { thresholds: hours, ..schedule(0.0) };
+    let red = Duration::seconds(schedule.thresholds(DEFAULT_BOX)[2]);
+    let morning = schedule.timezone.with_ymd_and_hms(2024, 1, 11, 8, 0, 0).unwrap().with_timezone(&Utc);
+    let due_time = schedule.due_time(DEFAULT_BOX, morning);
+    assert_eq!(schedule.urgency_elapsed(morning, due_time), red);
+    assert!(schedule.urgency_elapsed(morning, due_time - Duration::seconds(1)) < red);
+    // the night of 9 hours in between does not count
+    assert_eq!(due_time, morning + red + Duration::hours(9));
+    let without_overnight = ScheduleConfig { thresholds: hours, ..ScheduleConfig::default() };
+    assert_eq!(without_overnight.due_time(DEFAULT_BOX, morning), morning + red);
+}
@@ -0,0 +1,37 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{TimeZone, Utc};
+
+use cat_reminder::state;
+
+fn state_file(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("cat-reminder-state-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    directory.join("state")
+}
+
+#[test]
+fn plain_timestamps_are_migrated() {
+    let file = state_file("timestamp");
+    fs::write(&file, "2024-06-01T10:00:00+02:00\n").unwrap();
+
+    let state = state::load(&file);
+
+    assert_eq!(state.last_cleaning_time(), Utc.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap());
+    assert_eq!(state.version, 1);
+    fs::remove_dir_all(file.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn corrupt_state_files_are_kept_aside() {
+    let file = state_file("corrupt");
+    fs::write(&file, "{\"version\": 1, \"node_id\":").unwrap();
+
+    let state = state::load(&file);
+
+    assert_eq!(fs::read_to_string(file.with_file_name("state.corrupt")).unwrap(), "{\"version\": 1, \"node_id\":");
+    // the state written in place of the unreadable one is read back as is
+    assert_eq!(state::load(&file).node_id, state.node_id);
+    fs::remove_dir_all(file.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,37 @@
+use chrono::Duration;
+
+use cat_reminder::led::{bright_leds, scoop_leds};
+use cat_reminder::reminder::{LEDStripState, Scoops};
+
+#[test]
+fn bright_leds_grow_with_the_progress() {
+    assert_eq!(bright_leds(10, 0.0), 1);
+    assert_eq!(bright_leds(10, 0.05), 1);
+    assert_eq!(bright_leds(10, 0.5), 5);
+    assert_eq!(bright_leds(10, 0.51), 6);
+    assert_eq!(bright_leds(10, 1.0), 10);
+    assert_eq!(bright_leds(10, 1.5), 10);
+    assert_eq!(bright_leds(0, 0.5), 0);
+}
+
+#[test]
+fn progress_goes_from_one_state_to_the_next() {
+    let orange = LEDStripState::Orange.starts_after();
+    let red = LEDStripState::Red.starts_after();
+    assert_eq!(LEDStripState::progress(&Duration::zero()), 0.0);
+    assert_eq!(LEDStripState::progress(&orange), 0.0);
+    let halfway = orange + (red - orange) / 2;
+    assert!((LEDStripState::progress(&halfway) - 0.5).abs() < 0.01);
+    assert!(LEDStripState::progress(&(red - Duration::seconds(1))) > 0.9);
+    assert_eq!(LEDStripState::progress(&red), 0.0);
+    assert_eq!(LEDStripState::progress(&(LEDStripState::BlinkingRed.starts_after() + Duration::days(1))), 1.0);
+}
+
+#[test]
+fn scoops_light_one_segment_each() {
+    assert_eq!(scoop_leds(10, &Scoops { done: 0, target: 3 }), 0);
+    assert_eq!(scoop_leds(12, &Scoops { done: 2, target: 3 }), 8);
+    assert_eq!(scoop_leds(10, &Scoops { done: 3, target: 3 }), 10);
+    assert_eq!(scoop_leds(10, &Scoops { done: 5, target: 3 }), 10);
+    assert_eq!(scoop_leds(10, &Scoops { done: 0, target: 0 }), 10);
+}
@@ -0,0 +1,42 @@
+use ed25519_dalek::{Signer, SigningKey};
+
+use cat_reminder::update::{decode_hex, is_newer, signed_message, verify};
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn versions_are_compared_number_by_number() {
+    assert!(is_newer("0.3.0", "0.2.9"));
+    assert!(is_newer("v0.3.0", "0.2.9"));
+    assert!(is_newer("0.10.0", "0.9.1"));
+    assert!(is_newer("1.0.0.1", "1.0.0"));
+    assert!(!is_newer("0.2.0", "0.2.0"));
+    assert!(!is_newer("v0.2.0", "0.2.0"));
+    assert!(!is_newer("0.2.0", "0.10.0"));
+}
+
+#[test]
+fn hexadecimal_is_decoded() {
+    assert_eq!(decode_hex("00ff10").unwrap(), vec![0x00, 0xff, 0x10]);
+    assert_eq!(decode_hex(" AB\n").unwrap(), vec![0xab]);
+    assert!(decode_hex("abc").is_err());
+    assert!(decode_hex("zz").is_err());
+    assert!(decode_hex("é0").is_err());
+}
+
+#[test]
+fn signatures_cover_the_version_and_the_binary() {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let public_key = hex(key.verifying_key().as_bytes());
+    let binary = b"binary of 0.3.0";
+    let signature = hex(&key.sign(&signed_message("0.3.0", binary)).to_bytes());
+
+    assert!(verify(&signed_message("0.3.0", binary), &signature, &public_key).is_ok());
+    assert!(verify(&signed_message("v0.3.0", binary), &signature, &public_key).is_ok());
+    assert!(verify(&signed_message("0.4.0", binary), &signature, &public_key).is_err());
+    assert!(verify(&signed_message("0.3.0", b"binary of 0.2.0"), &signature, &public_key).is_err());
+    assert!(verify(&signed_message("0.3.0", binary), &signature, &public_key[2..]).is_err());
+    assert!(verify(&signed_message("0.3.0", binary), &signature[2..], &public_key).is_err());
+}